@@ -1,11 +1,17 @@
 pub mod engine {
-    use crate::storage::column::generic::{Uncompressed, ColumnInterface, Column};
+    use bitvec::prelude::*;
+    use crate::storage::column::generic::{Segmented, Column};
     use crate::storage::table::table::*;
+    use crate::storage::persist::persist::*;
+    use crate::storage::wal::wal::*;
+    use crate::storage::mapped::mapped::MappedFile;
     use crate::sqlscript::parser::parser::*;
     use crate::sqlscript::types::types::*;
     use super::super::script::env::*;
     use super::super::script::engine::*;
     use std::rc::Rc;
+    use std::io::Write;
+    use std::collections::HashMap;
 
     macro_rules! handle{
         ($e:expr) => {
@@ -13,6 +19,32 @@ pub mod engine {
         }
     }
 
+    macro_rules! handle_err{
+        ($e:expr) => {
+            (match $e { Ok(v) => v, Err(s) => return Err(s) })
+        }
+    }
+
+    // Tables whose aggregates are in scope of a select, and other names its fields go by
+    struct Scope {
+        tables: Vec<usize>,
+        aliases: Vec<(String, usize)> // Unqualified names of joined fields, with their column
+    }
+
+    // A table as stored in a database file: its rows, or the file and table to attach it from
+    enum Stored {
+        Rows(Table),
+        Source(String, String)
+    }
+    // A table entry of a database file, with its calculated column expressions
+    type Entry = (Vec<Option<Expr>>, Stored);
+
+    // Number of log records after which the database is checkpointed automatically
+    pub const CHECKPOINT_INTERVAL: usize = 1000;
+
+    // Whole numbers up to this size are exact as floats, so adding and subtracting them never rounds
+    const MAX_EXACT: f64 = 9007199254740992.0;
+
     pub enum QueryResult {
         Table(Table),
         Value(Val),
@@ -25,7 +57,11 @@ pub mod engine {
         tables: Vec<Table>,
         table_names: Vec<String>,
         constants: Vec<(String, Val)>,
-        calculated: Vec<Vec<Option<Expr>>>
+        calculated: Vec<Vec<Option<Expr>>>,
+        path: Option<String>,
+        wal: Option<WriteAheadLog>,
+        generation: u64,
+        skipped: Vec<String> // Log records that failed when replayed on open, and why
     }
     impl Database {
         fn insert(&mut self, table_name: &String, fields: &Option<Vec<String>>, values: &Vec<Rc<Expr>>) -> QueryResult {
@@ -86,62 +122,65 @@ pub mod engine {
             let table = &self.tables[table_idx];
             // Is this the first row?
             let first_row = table.len() == 0;
-            // Calculate aggregates
+            // Calculate aggregates, a grouped aggregate only folds the row into its key's value
             let mut ag_vals = Vec::new();
             for ag in table.get_aggregates() {
-                // Are you given an initial aggregate?
-                let has_init = match &ag.3 {
-                    Some(_) => true,
-                    _ => false,
-                };
                 // Environment
                 let mut env = self.default_environment();
                 // Add new row to environment
                 for i in 0..values_insert.len() {
                     env.push(&table.get_headers()[i], &values_insert[i]);
                 }
-                // Add current value to environment, unless is first row and has init
-                if !(first_row && has_init) {
-                    env.push(&"current".to_string(), &ag.1);
-                }
                 // Evaluate
-                let val = match first_row {
-                    true => match &ag.3 {
-                        Some(e1) => handle!(eval(e1, &mut env)),
-                        None => handle!(eval(&ag.2, &mut env))
+                match &ag.4 {
+                    Some(field) => {
+                        let key = &values_insert[handle!(table.header_idx(field))];
+                        let current = match &ag.1 {
+                            Val::GroupVal(groups) => groups.get(key),
+                            _ => None
+                        };
+                        let val = handle!(Database::fold_row(&ag.2, &ag.3, current.unwrap_or(&Val::NullVal), current.is_none(), &mut env));
+                        ag_vals.push((Some(key.clone()), val))
                     },
-                    false => handle!(eval(&ag.2, &mut env))
+                    None => ag_vals.push((None, handle!(Database::fold_row(&ag.2, &ag.3, &ag.1, first_row, &mut env))))
                 };
-                // Push
-                ag_vals.push(val);
             }
+            // Update aggregates in place, they are restored if the insert fails
+            let previous = handle!(self.tables[table_idx].fold_aggregates(ag_vals));
             // Calculate computations
             let mut cmp_vals = Vec::new();
-            for cmp in table.get_computations() {
+            for cmp in self.tables[table_idx].get_computations() {
                 // Environment
                 let mut env = self.default_environment();
-                let mut i = 0;
-                for ag in table.get_aggregates() {
-                    env.push(&ag.0, &ag_vals[i]);
-                    i += 1;
-                }
+                self.tables[table_idx].push_aggregates(&mut env);
                 // Evaluate
-                cmp_vals.push(handle!(eval(&cmp.2, &mut env)));
+                match eval(&cmp.2, &mut env) {
+                    Ok(val) => cmp_vals.push(val),
+                    Err(s) => {
+                        self.tables[table_idx].restore_aggregates(previous);
+                        return QueryResult::Error(s)
+                    }
+                }
             }
             // Borrow table as mutable
             let table = &mut self.tables[table_idx];
             // Add row to table
-            handle!(table.add_row(values_insert));
-            // Add aggregates
-            handle!(table.update_aggregates(&ag_vals));
+            if let Err(s) = table.add_row(values_insert) {
+                table.restore_aggregates(previous);
+                return QueryResult::Error(s)
+            }
             // Add computations
             handle!(table.update_computations(&cmp_vals));
             // Return
             QueryResult::Success("Insert on ".to_string() + table_name)
         }
-        fn create_table(&mut self, table_name: &String, schema: &Vec<(String, ColType, Option<CompressType>)>) -> QueryResult {
+        fn create_table(&mut self, table_name: &String, schema: &Vec<(String, ColType, Option<CompressType>)>, cluster: &Option<String>) -> QueryResult {
             // Check that table doesn't already exist
-            if self.table_names.contains(table_name) { panic!("Table already exists") }
+            if self.table_names.contains(table_name) { return QueryResult::Error("Table ".to_string() + table_name + " already exists") }
+            // Check that the cluster key is one of the columns
+            if let Some(field) = cluster {
+                if !schema.iter().any(|col| col.0 == *field) { return QueryResult::Error("Invalid column name ".to_string() + field) }
+            }
             // Push table name
             self.table_names.push(table_name.clone());
             // Create new table
@@ -159,35 +198,83 @@ pub mod engine {
                 handle!(self.tables[idx].add_column(&schema_item.0, schema_item.1, ctype));
                 self.calculated[idx].push(None)
             }
+            // Keep rows sorted on the cluster key
+            if let Some(field) = cluster {
+                let col_idx = handle!(self.tables[idx].header_idx(field));
+                handle!(self.tables[idx].set_cluster_key(col_idx));
+            }
             QueryResult::Success("Created table ".to_string() + table_name)
         }
-        fn select(&mut self, fields: &Option<Vec<String>>, table_name: &String, where_: &Option<Expr>, sort_by: &Option<(String, SortType)>, limit: &Option<Expr>, ecsv: &Option<String>) -> QueryResult {
-            // Get index of table
-            let table_idx = handle!(self.get_table_index(table_name));
-            let table = &self.tables[table_idx];
+        fn select(&mut self, fields: &Option<Vec<SelectItem>>, source: &Source, where_: &Option<Expr>, sort_by: &Option<(String, SortType)>, limit: &Option<Expr>, ecsv: &Option<String>) -> QueryResult {
+            let (source_table, scope) = handle!(self.source_table(source));
+            let table = match &source_table {
+                Some(t) => t,
+                None => &self.tables[scope.tables[0]]
+            };
+            let result = self.select_from(table, &scope, fields, where_, sort_by, limit);
+            let table_project = match result {
+                QueryResult::Table(t) => t,
+                r => return r
+            };
+            match ecsv {
+                Some(path) => match self.export_csv_table(path, &table_project) {
+                    Ok(_) => (),
+                    Err(_) => println!("Warning: Error exporting result")
+                },
+                None => ()
+            };
+            // Return new table
+            QueryResult::Table(table_project)
+        }
+        // Select rows of a table, with the aggregates of the tables in scope
+        fn select_from(&self, table: &Table, scope: &Scope, fields: &Option<Vec<SelectItem>>, where_: &Option<Expr>, sort_by: &Option<(String, SortType)>, limit: &Option<Expr>) -> QueryResult {
             // Vector of added rows
             let mut added_rows: Vec<Vec<Val>> = Vec::new();
-            let mut full_rows: Vec<(Vec<Val>, usize)> = Vec::new();
+            let mut sort_rows: Vec<(Val, usize)> = Vec::new();
+            // Folds need GROUP BY
+            for item in fields.iter().flatten() {
+                if let SelectItem::Fold(.., name) = item { return QueryResult::Error("Fold ".to_string() + name + " needs GROUP BY") }
+            }
+            // Sort on a projected expression with the sort field's name, otherwise on a column
+            let sort_project = match (sort_by, fields) {
+                (Some(s), Some(items)) => items.iter().position(|item| matches!(item, SelectItem::Project(_, _, name) if *name == s.0)),
+                _ => None
+            };
+            let sort_idx = match sort_by {
+                Some(s) if sort_project.is_none() => Some(handle!(Database::field_idx(table, scope, &s.0))),
+                _ => None
+            };
+            // Only decode rows selected by the columns themselves when the where clause is made of equalities,
+            // otherwise skip zones that can't match when it is made of simple comparisons
+            let rows: Box<dyn Iterator<Item=SelectedRow>> = match where_.as_ref().and_then(|expr| Database::selection(table, expr)) {
+                Some(selected) => Box::new(table.iter_selected(selected)),
+                None => match where_.as_ref().and_then(|expr| Database::zone_predicates(table, expr)) {
+                    Some(preds) => Box::new(table.iter_zones(table.zones_matching(&preds))),
+                    None => Box::new(table.iter_indexed().map(Ok))
+                }
+            };
             // Iterate through each row in the table
             let mut i: usize = 0;
-            for row in table.iter() {
+            for row in rows {
+                let (row_idx, row) = handle!(row);
+                // Environment in which to evaluate row
+                let mut env = self.scope_environment(table.get_headers(), scope, &row);
+                // Evaluate where clause, convert to bool
+                let should_add = match where_ {
+                    Some(expr) => handle!(eval_bool(expr, &mut env)),
+                    None => true
+                };
+                if !should_add { continue }
                 // New row
                 let mut new_row: Vec<Val> = Vec::new();
-                // Environment in which to evaluate row
-                let mut env = self.default_environment();
-                // Add aggregates into environment
-                table.push_aggregates(&mut env);
-                // Add all fields to environment
-                for field in table.get_headers() {
-                    let idx = handle!(table.header_idx(field));
-                    env.push(field, &row[idx]);
-                }
                 // Add items to new row
                 match fields {
                     Some(v) => {
-                        for field in v {
-                            let idx = handle!(table.header_idx(field));
-                            new_row.push(row[idx].clone());
+                        for item in v {
+                            new_row.push(match item {
+                                SelectItem::Project(expr, col_type, _) | SelectItem::Fold(expr, _, col_type, _) => Database::as_type(handle!(eval(expr, &mut env)), col_type),
+                                SelectItem::Field(field) => row[handle!(Database::field_idx(table, scope, field))].clone()
+                            });
                         }
                     },
                     None => {
@@ -197,33 +284,26 @@ pub mod engine {
                         }
                     }
                 }
-                // Evaluate where clause, convert to bool
-                let should_add = match where_ {
-                    Some(expr) => handle!(eval_bool(expr, &mut env)),
-                    None => true
+                // Only look up sort key if sorting
+                let sort_val = match (sort_project, sort_idx) {
+                    (Some(item_idx), _) => Some(new_row[item_idx].clone()),
+                    (None, Some(col_idx)) => Some(handle!(table.get_value(col_idx, row_idx))),
+                    _ => None
                 };
-                // Push to new table if should add
-                if should_add {
-                    added_rows.push(new_row);
-                    // Only add to full rows if sorting
-                    match sort_by {
-                        Some(_) => {
-                            full_rows.push((row.clone(), i));
-                            // Increment i
-                            i += 1;
-                        },
-                        None => ()
-                    };
+                if let Some(val) = sort_val {
+                    sort_rows.push((val, i));
+                    // Increment i
+                    i += 1;
                 }
-                
+                // Push to new table
+                added_rows.push(new_row);
             };
-            // If sorting, sort full rows by sort_by header
+            // If sorting, sort rows by sort_by header
             match sort_by {
                 Some(s) => {
-                    let col_idx = handle!(table.header_idx(&s.0));
                     match &s.1 {
-                        SortType::Ascending => full_rows.sort_by(|a, b| eval_ordering(&(a.0)[col_idx], &(b.0)[col_idx])),
-                        SortType::Descending => full_rows.sort_by(|a, b| eval_ordering_desc(&(a.0)[col_idx], &(b.0)[col_idx]))
+                        SortType::Ascending => sort_rows.sort_by(|a, b| eval_ordering(&a.0, &b.0)),
+                        SortType::Descending => sort_rows.sort_by(|a, b| eval_ordering_desc(&a.0, &b.0))
                     }
                 },
                 None => ()
@@ -233,11 +313,18 @@ pub mod engine {
             // Setup table
             match fields {
                 Some(v) => {
-                    for field in v {
-                        handle!(match handle!(table.get_column(field)) {
-                            Column::Boolean(_) => table_project.add_column(field, ColType::Boolean, CompressType::Uncompressed),
-                            Column::Number(_) => table_project.add_column(field, ColType::Number, CompressType::Uncompressed),
-                            Column::String(_) => table_project.add_column(field, ColType::String, CompressType::Uncompressed)
+                    let col_types = table.get_col_types();
+                    for (item_idx, item) in v.iter().enumerate() {
+                        handle!(match item {
+                            SelectItem::Project(_, declared, name) | SelectItem::Fold(_, _, declared, name) => {
+                                // Infer the type from the values unless it is declared
+                                let col_type = match declared {
+                                    Some(t) => *t,
+                                    None => handle!(Database::value_type(added_rows.iter().map(|r| &r[item_idx])).map_err(|s| "Expression ".to_string() + name + s.as_str()))
+                                };
+                                table_project.add_column(name, col_type, CompressType::Uncompressed)
+                            },
+                            SelectItem::Field(field) => table_project.add_column(field, col_types[handle!(Database::field_idx(table, scope, field))], CompressType::Uncompressed)
                         })
                     }
                 },
@@ -257,7 +344,7 @@ pub mod engine {
                     // Evaluate expression
                     let mut env = self.default_environment();
                     // Add aggregates into environment
-                    table.push_aggregates(&mut env);
+                    self.push_scope_aggregates(scope, &mut env);
                     Some(handle!(eval_num(expr, &mut env)) as usize)
                 },
                 None => None
@@ -265,14 +352,14 @@ pub mod engine {
             match sort_by {
                 Some(_) => {
                     // Add rows to new table
-                    for i in 0..full_rows.len() {
+                    for i in 0..sort_rows.len() {
                         // Stop adding if reached limit
                         match lim_usize {
                             Some(x) => if i >= x {break},
                             None => ()
                         };
                         // Matching index of added row
-                        let matching_index = full_rows[i].1;
+                        let matching_index = sort_rows[i].1;
                         // Add row to table
                         handle!(table_project.add_row(added_rows[matching_index].clone()))
                     };
@@ -292,15 +379,177 @@ pub mod engine {
                     };
                 }
             };
-            match ecsv {
-                Some(path) => match self.export_csv_table(path, &table_project) {
-                    Ok(_) => (),
-                    Err(_) => println!("Warning: Error exporting result")
+            QueryResult::Table(table_project)
+        }
+        // Table a select reads from along with the tables in scope, None when that is a table of the database itself
+        fn source_table(&mut self, source: &Source) -> Result<(Option<Table>, Scope), String> {
+            // Run the subquery selected from
+            let (first, first_idx) = match &source.2 {
+                Some(q) => match self.apply(q) {
+                    QueryResult::Table(t) => (Some(t), None),
+                    QueryResult::Error(s) => return Err(s),
+                    _ => return Err("Subquery ".to_string() + &source.0 + " does not return a table")
                 },
-                None => ()
+                None => (None, Some(handle_err!(self.get_table_index(&source.0))))
             };
-            // Return new table
-            QueryResult::Table(table_project)
+            let scope = Scope { tables: first_idx.into_iter().collect(), aliases: Vec::new() };
+            if source.1.is_empty() { return Ok((first, scope)) }
+            // Join the tables into one whose fields are qualified with their table
+            let first_table = match &first {
+                Some(t) => t,
+                None => &self.tables[scope.tables[0]]
+            };
+            let (joined, scope) = handle_err!(self.join(first_table, &source.0, first_idx, &source.1));
+            Ok((Some(joined), scope))
+        }
+        // Join tables onto one, into a table whose fields are qualified with their table (like a.id).
+        // The first table is a table of the database when it has an index, and the result of a subquery otherwise
+        fn join(&self, first: &Table, first_name: &String, first_idx: Option<usize>, joins: &Vec<Join>) -> Result<(Table, Scope), String> {
+            let mut scope = Scope { tables: first_idx.into_iter().collect(), aliases: Vec::new() };
+            let mut sides: Vec<(&String, &Table)> = vec![(first_name, first)];
+            for join in joins {
+                let idx = handle_err!(self.get_table_index(&join.1));
+                if sides.iter().any(|s| *s.0 == join.1) { return Err("Table ".to_string() + &join.1 + " is joined more than once") }
+                scope.tables.push(idx);
+                sides.push((&self.table_names[idx], &self.tables[idx]));
+            }
+            // Qualified headers and types of the joined rows
+            let mut headers: Vec<String> = Vec::new();
+            let mut col_types: Vec<ColType> = Vec::new();
+            for (name, table) in &sides {
+                for (header, col_type) in table.get_headers().iter().zip(table.get_col_types()) {
+                    headers.push(name.to_string() + "." + header);
+                    col_types.push(col_type);
+                }
+            }
+            // Fields only one of the tables has can also go unqualified
+            for (name, table) in &sides {
+                for header in table.get_headers() {
+                    let qualified = name.to_string() + "." + header;
+                    if sides.iter().filter(|s| s.1.get_headers().contains(header)).count() == 1 {
+                        scope.aliases.push((header.clone(), headers.iter().position(|h| *h == qualified).unwrap()))
+                    }
+                }
+            }
+            // Rows of the first table, joined with each of the others in turn
+            let mut rows: Vec<Vec<Val>> = first.iter().collect();
+            let mut width = first.get_headers().len();
+            for (join_idx, (join_type, _, on)) in joins.iter().enumerate() {
+                let right = sides[join_idx + 1].1;
+                let right_width = right.get_headers().len();
+                let right_rows: Vec<Vec<Val>> = right.iter().collect();
+                // ON sees the tables joined so far
+                let on_scope = Scope {
+                    tables: scope.tables[..scope.tables.len() - joins.len() + join_idx + 1].to_vec(),
+                    aliases: scope.aliases.iter().filter(|a| a.1 < width + right_width).cloned().collect()
+                };
+                // Rows of the right side matching each row of the left side
+                let matches: Vec<Vec<usize>> = match Database::join_keys(on, &headers[..width + right_width], &on_scope, width, &col_types) {
+                    Some((left_col, right_col)) => {
+                        // Hash join on the equality
+                        let mut hashed: HashMap<String, Vec<usize>> = HashMap::new();
+                        for (i, row) in right_rows.iter().enumerate() {
+                            if let Some(key) = Database::join_key(&row[right_col - width]) { hashed.entry(key).or_default().push(i) }
+                        }
+                        rows.iter().map(|row| Database::join_key(&row[left_col]).and_then(|key| hashed.get(&key).cloned()).unwrap_or_default()).collect()
+                    },
+                    None => {
+                        // Evaluate ON for every pair of rows
+                        let mut matches = Vec::new();
+                        for row in &rows {
+                            let mut row_matches = Vec::new();
+                            for (i, right_row) in right_rows.iter().enumerate() {
+                                let pair: Vec<Val> = row.iter().chain(right_row.iter()).cloned().collect();
+                                if handle_err!(eval_bool(on, &mut self.scope_environment(&headers[..width + right_width], &on_scope, &pair))) { row_matches.push(i) }
+                            }
+                            matches.push(row_matches)
+                        }
+                        matches
+                    }
+                };
+                let mut joined = Vec::new();
+                for (row, row_matches) in rows.into_iter().zip(matches) {
+                    if row_matches.is_empty() && *join_type == JoinType::Left {
+                        joined.push(row.iter().cloned().chain(std::iter::repeat_n(Val::NullVal, right_width)).collect())
+                    }
+                    for i in row_matches {
+                        joined.push(row.iter().chain(right_rows[i].iter()).cloned().collect())
+                    }
+                }
+                rows = joined;
+                width += right_width;
+            }
+            // Put the joined rows in a table
+            let mut table = Table::new();
+            for (header, col_type) in headers.iter().zip(col_types) {
+                handle_err!(table.add_column(header, col_type, CompressType::Uncompressed))
+            }
+            for row in rows {
+                handle_err!(table.add_row(row))
+            }
+            Ok((table, scope))
+        }
+        // Columns compared by an ON expression that is an equality between a field of the left side and a field of the
+        // same type on the right side, which can be joined by hashing
+        fn join_keys(on: &Expr, headers: &[String], scope: &Scope, width: usize, col_types: &Vec<ColType>) -> Option<(usize, usize)> {
+            let (e1, e2) = match on {
+                Expr::BopExpr(e1, BopType::EqBop | BopType::StrEqBop, e2) => (e1.as_ref(), e2.as_ref()),
+                _ => return None
+            };
+            // Column a field refers to
+            let column = |e: &Expr| match e {
+                Expr::IdentExpr(name) => scope.aliases.iter().find(|a| a.0 == *name).map(|a| a.1),
+                Expr::BopExpr(t, BopType::DotBop, f) => match (t.as_ref(), f.as_ref()) {
+                    (Expr::IdentExpr(t), Expr::IdentExpr(f)) => headers.iter().position(|h| *h == t.clone() + "." + f),
+                    _ => None
+                },
+                _ => None
+            };
+            let (c1, c2) = (column(e1)?, column(e2)?);
+            let (left, right) = if c1 < width { (c1, c2) } else { (c2, c1) };
+            if left >= width || right < width || str_of_coltype(col_types[left]) != str_of_coltype(col_types[right]) { return None }
+            Some((left, right))
+        }
+        // Key of a value in a hash join, values of one type share a key exactly when they are equal
+        fn join_key(val: &Val) -> Option<String> {
+            match val {
+                Val::NumVal(x) => if x.is_nan() { None } else { Some("n".to_string() + &(x + 0.0).to_bits().to_string()) },
+                Val::StrVal(s) => Some("s".to_string() + s),
+                Val::BoolVal(b) => Some(b.to_string()),
+                // Nulls are equal to each other
+                Val::NullVal | Val::UndefVal => Some("null".to_string()),
+                _ => None
+            }
+        }
+        // Column of a field, which may be an unqualified name of a joined field
+        fn field_idx(table: &Table, scope: &Scope, field: &String) -> Result<usize, String> {
+            match scope.aliases.iter().find(|a| a.0 == *field) {
+                Some(alias) => Ok(alias.1),
+                None => table.header_idx(field)
+            }
+        }
+        // Add the aggregates of the tables in scope to an environment, qualified with their table when joining
+        fn push_scope_aggregates(&self, scope: &Scope, env: &mut Environment) {
+            for idx in &scope.tables {
+                self.tables[*idx].push_aggregates(env);
+                if scope.tables.len() > 1 {
+                    for ag in self.tables[*idx].get_aggregates() {
+                        env.push(&(self.table_names[*idx].clone() + "." + &ag.0), &ag.1)
+                    }
+                }
+            }
+        }
+        // Environment for evaluating an expression against a selected row
+        fn scope_environment(&self, headers: &[String], scope: &Scope, row: &Vec<Val>) -> Environment {
+            let mut env = self.default_environment();
+            self.push_scope_aggregates(scope, &mut env);
+            for i in 0..row.len() {
+                env.push(&headers[i], &row[i]);
+            }
+            for (name, col_idx) in &scope.aliases {
+                env.push(name, &row[*col_idx]);
+            }
+            env
         }
         fn create_const(&mut self, name: &String, expr: &Expr) -> QueryResult {
             // Evaluate expr
@@ -323,7 +572,7 @@ pub mod engine {
             let col = match t {
                 ColType::Boolean => {
                     // Data object
-                    let mut col_data: Uncompressed<bool> = Uncompressed::new();
+                    let mut col_data: Segmented<bool> = Segmented::new();
                     // Iterate through table rows
                     for row in table.iter() {
                         // Environment
@@ -336,14 +585,14 @@ pub mod engine {
                         // Get column value
                         let val = handle!(eval_bool_option(expr, &mut env));
                         // Push value to data container
-                        col_data.insert(val);
+                        handle!(col_data.insert(val));
                     }
                     // Return column
                     Column::Boolean(Box::new(col_data))
                 },
                 ColType::Number => {
                     // Data object
-                    let mut col_data: Uncompressed<f64> = Uncompressed::new();
+                    let mut col_data: Segmented<f64> = Segmented::new();
                     // Iterate through table rows
                     for row in table.iter() {
                         // Environment
@@ -356,14 +605,14 @@ pub mod engine {
                         // Get column value
                         let val = handle!(eval_num_option(expr, &mut env));
                         // Push value to data container
-                        col_data.insert(val);
+                        handle!(col_data.insert(val));
                     }
                     // Return column
                     Column::Number(Box::new(col_data))
                 },
                 ColType::String => {
                     // Data object
-                    let mut col_data: Uncompressed<String> = Uncompressed::new();
+                    let mut col_data: Segmented<String> = Segmented::new();
                     // Iterate through table rows
                     for row in table.iter() {
                         // Environment
@@ -376,7 +625,7 @@ pub mod engine {
                         // Get column value
                         let val = handle!(eval_str_option(expr, &mut env));
                         // Push value to data container
-                        col_data.insert(val);
+                        handle!(col_data.insert(val));
                     }
                     // Return column
                     Column::String(Box::new(col_data))
@@ -396,76 +645,410 @@ pub mod engine {
             // Return nothing
             QueryResult::Success("Column ".to_string() + col_name + " on " + table_name)
         }
-        fn create_aggregate(&mut self, ag_name: &String, expr: &Expr, init: &Option<Expr>, table_name: &String) -> QueryResult {
+        fn create_aggregate(&mut self, ag_name: &String, expr: &Expr, init: &Option<Expr>, group: &Option<String>, table_name: &String) -> QueryResult {
+            // Get index of table
+            let table_idx = handle!(self.get_table_index(table_name));
+            let table = &self.tables[table_idx];
+            // Check that the group field is one of the columns
+            if let Some(field) = group {
+                handle!(table.header_idx(field));
+            }
+            // Calculate aggregate for existing rows
+            let ag_val = handle!(self.fold_aggregate(table, expr, init, group));
+            // Register aggregate into table
+            let table = &mut self.tables[table_idx];
+            table.add_aggregate(ag_name, &ag_val, expr, init, group);
+            // Finished
+            QueryResult::Success("Aggregate ".to_string() + ag_name + " on " + table_name)
+        }
+        fn select_aggregate(&mut self, ag_name: &String, table_name: &String) -> QueryResult {
             // Get index of table
             let table_idx = handle!(self.get_table_index(table_name));
             let table = &self.tables[table_idx];
-            // Value of aggregate
+            let ag_val = handle!(table.get_aggregate(ag_name));
+            // Grouped aggregates are a table of keys and values
+            let group = table.get_aggregates().iter().find(|ag| ag.0 == *ag_name).and_then(|ag| ag.4.clone());
+            match group {
+                Some(field) => {
+                    let groups = match &ag_val {
+                        Val::GroupVal(groups) => groups.clone(),
+                        _ => Rc::new(Groups::new())
+                    };
+                    let pairs = groups.pairs();
+                    let key_type = table.get_col_types()[handle!(table.header_idx(&field))];
+                    let val_type = handle!(Database::value_type(pairs.iter().map(|p| &p.1)).map_err(|s| "Aggregate ".to_string() + ag_name + s.as_str()));
+                    let mut result = Table::new();
+                    handle!(result.add_column(&field, key_type, CompressType::Uncompressed));
+                    handle!(result.add_column(&"value".to_string(), val_type, CompressType::Uncompressed));
+                    for (key, val) in pairs {
+                        handle!(result.add_row(vec![key.clone(), val.clone()]))
+                    }
+                    QueryResult::Table(result)
+                },
+                None => QueryResult::Value(ag_val)
+            }
+        }
+        fn create_computation(&mut self, cmp_name: &String, expr: &Expr, table_name: &String) -> QueryResult {
+            // Get index of table
+            let table_idx = handle!(self.get_table_index(table_name));
+            let table = &self.tables[table_idx]; 
+            // Get value of computation (null if table is empty)
+            let comp_val = handle!(self.compute(table, expr));
+            // Register computation into table
+            let table = &mut self.tables[table_idx];
+            table.add_computation(cmp_name, &comp_val, expr);
+            // Return nothing
+            QueryResult::Success("Computation ".to_string() + cmp_name + " on " + table_name)
+        }
+        // Fold an aggregate over every row in the table from scratch
+        fn fold_aggregate(&self, table: &Table, expr: &Expr, init: &Option<Expr>, group: &Option<String>) -> Result<Val, String> {
+            // Value of aggregate, grouped aggregates start without any groups
             let mut ag_val = Val::NullVal;
-            // Does this have an init?
-            let has_init = match init {
-                Some(_) => true,
-                None => false
+            let mut groups = Groups::new();
+            let key_idx = match group {
+                Some(field) => Some(handle_err!(table.header_idx(field))),
+                None => None
             };
-            // Calculate aggregate for existing rows
             let mut i: usize = 0;
-            for row in table.iter() {
-                // Is this the first row?
-                let first_row = i == 0;
+            for row in table.iter_inserted() {
                 // Environment
                 let mut env = self.default_environment();
                 // Add row to environment
                 for i in 0..row.len() {
                     env.push(&table.get_headers()[i], &row[i]);
                 }
-                // Add current value to environment, unless has init and is first row
-                if !(has_init && first_row) {
-                    env.push(&"current".to_string(), &ag_val);
-                }
                 // Evaluate
-                ag_val = handle!(match first_row {
-                    true => match init {
-                        Some(e1) => eval(e1, &mut env),
-                        None => eval(expr, &mut env)
+                match key_idx {
+                    Some(idx) => {
+                        let current = groups.get(&row[idx]);
+                        let val = handle_err!(Database::fold_row(expr, init, current.unwrap_or(&Val::NullVal), current.is_none(), &mut env));
+                        groups.set(&row[idx], val);
                     },
-                    false => eval(expr, &mut env)
-                });
+                    None => ag_val = handle_err!(Database::fold_row(expr, init, &ag_val, i == 0, &mut env))
+                };
                 // Increment i
                 i += 1;
             };
-            // Register aggregate into table
-            let table = &mut self.tables[table_idx];
-            table.add_aggregate(ag_name, &ag_val, expr, init);
-            // Finished
-            QueryResult::Success("Aggregate ".to_string() + ag_name + " on " + table_name)
+            Ok(match key_idx {
+                Some(_) => Val::GroupVal(Rc::new(groups)),
+                None => ag_val
+            })
         }
-        fn select_aggregate(&mut self, ag_name: &String, table_name: &String) -> QueryResult {
+        // Fold a row into the current value of an aggregate, the row is already in the environment
+        fn fold_row(expr: &Expr, init: &Option<Expr>, current: &Val, first_row: bool, env: &mut Environment) -> Result<Val, String> {
+            // Add current value to environment, unless has init and is first row
+            if !(init.is_some() && first_row) {
+                env.push(&"current".to_string(), current);
+            }
+            match (first_row, init) {
+                (true, Some(e1)) => eval(e1, env),
+                _ => eval(expr, env)
+            }
+        }
+        fn select_group(&mut self, items: &Vec<SelectItem>, source: &Source, where_: &Option<Expr>, key: &String) -> QueryResult {
+            let (source_table, scope) = handle!(self.source_table(source));
+            let table = match &source_table {
+                Some(t) => t,
+                None => &self.tables[scope.tables[0]]
+            };
+            let key_idx = handle!(Database::field_idx(table, &scope, key));
+            // Only the key can be selected besides folds
+            for item in items {
+                match item {
+                    SelectItem::Field(field) => if handle!(Database::field_idx(table, &scope, field)) != key_idx { return QueryResult::Error("Field ".to_string() + field + " is not grouped by") },
+                    SelectItem::Project(_, _, name) => return QueryResult::Error("Expression ".to_string() + name + " is not a fold"),
+                    SelectItem::Fold(..) => ()
+                }
+            }
+            // Key and value of every fold of each group, in order of each group's first row
+            let mut groups: Vec<(Val, Vec<Val>)> = Vec::new();
+            let mut group_idxs: HashMap<String, usize> = HashMap::new();
+            for row in table.iter_inserted() {
+                let should_add = match where_ {
+                    Some(expr) => handle!(eval_bool(expr, &mut self.scope_environment(table.get_headers(), &scope, &row))),
+                    None => true
+                };
+                if !should_add { continue }
+                // Find the row's group
+                let group_key = Groups::key(&row[key_idx]);
+                let first_row = !group_idxs.contains_key(&group_key);
+                let group_idx = *group_idxs.entry(group_key).or_insert(groups.len());
+                if first_row { groups.push((row[key_idx].clone(), vec![Val::NullVal; items.len()])) }
+                // Fold the row into each fold of its group
+                for (item_idx, item) in items.iter().enumerate() {
+                    if let SelectItem::Fold(expr, init, _, _) = item {
+                        let mut env = self.scope_environment(table.get_headers(), &scope, &row);
+                        let val = handle!(Database::fold_row(expr, init, &groups[group_idx].1[item_idx], first_row, &mut env));
+                        groups[group_idx].1[item_idx] = val;
+                    }
+                }
+            }
+            // One row per group
+            let mut result = Table::new();
+            for (item_idx, item) in items.iter().enumerate() {
+                handle!(match item {
+                    SelectItem::Fold(_, _, declared, name) => {
+                        let col_type = match declared {
+                            Some(t) => *t,
+                            None => handle!(Database::value_type(groups.iter().map(|g| &g.1[item_idx])).map_err(|s| "Fold ".to_string() + name + s.as_str()))
+                        };
+                        result.add_column(name, col_type, CompressType::Uncompressed)
+                    },
+                    SelectItem::Field(field) | SelectItem::Project(.., field) => result.add_column(field, table.get_col_types()[key_idx], CompressType::Uncompressed)
+                })
+            }
+            for (key_val, vals) in groups {
+                let row = items.iter().zip(vals).map(|(item, val)| match item {
+                    SelectItem::Fold(_, _, declared, _) => Database::as_type(val, declared),
+                    _ => key_val.clone()
+                }).collect();
+                handle!(result.add_row(row))
+            }
+            QueryResult::Table(result)
+        }
+        // Convert a value to a declared column type, leaving it as it is when no type is declared
+        fn as_type(val: Val, col_type: &Option<ColType>) -> Val {
+            match (col_type, &val) {
+                (_, Val::NullVal | Val::UndefVal) => Val::NullVal,
+                (Some(ColType::Number), _) => to_num(&val),
+                (Some(ColType::String), _) => to_str(&val),
+                (Some(ColType::Boolean), _) => to_bool(&val),
+                (None, _) => val
+            }
+        }
+        // Column type that can hold every one of a list of values, nulls fit in any column
+        fn value_type<'a>(vals: impl Iterator<Item=&'a Val>) -> Result<ColType, String> {
+            let mut col_type: Option<ColType> = None;
+            for val in vals {
+                let val_type = match val {
+                    Val::NumVal(_) => ColType::Number,
+                    Val::StrVal(_) => ColType::String,
+                    Val::BoolVal(_) => ColType::Boolean,
+                    Val::NullVal | Val::UndefVal => continue,
+                    _ => return Err(" is not a number, string or boolean".to_string())
+                };
+                match col_type {
+                    Some(t) if str_of_coltype(t) != str_of_coltype(val_type) => return Err(" has values of different types".to_string()),
+                    _ => col_type = Some(val_type)
+                }
+            }
+            // Columns of only nulls are numbers
+            Ok(col_type.unwrap_or(ColType::Number))
+        }
+        // Evaluate a computation against the table's current aggregates
+        fn compute(&self, table: &Table, expr: &Expr) -> Result<Val, String> {
+            if table.len() == 0 { return Ok(Val::NullVal) }
+            // Environment
+            let mut env = self.default_environment();
+            table.push_aggregates(&mut env);
+            // Evaluate
+            eval(expr, &mut env)
+        }
+        // Break a where clause made of comparisons between a field and a literal (joined by &&) into
+        // (column, op, literal, whether a null satisfies it), None if it is anything else
+        fn zone_predicates(table: &Table, expr: &Expr) -> Option<Vec<(usize, BopType, Val, bool)>> {
+            match expr {
+                Expr::BopExpr(e1, BopType::LogAndBop, e2) => {
+                    let mut preds = Database::zone_predicates(table, e1)?;
+                    preds.append(&mut Database::zone_predicates(table, e2)?);
+                    Some(preds)
+                },
+                Expr::BopExpr(e1, op @ (BopType::GtBop | BopType::GteBop | BopType::LtBop | BopType::LteBop | BopType::EqBop | BopType::StrEqBop), e2) => {
+                    // Put the field on the left
+                    let (field, val, field_op) = match (e1.as_ref(), e2.as_ref()) {
+                        (Expr::IdentExpr(field), Expr::ValExpr(val)) => (field, val, *op),
+                        (Expr::ValExpr(val), Expr::IdentExpr(field)) => (field, val, match op {
+                            BopType::GtBop => BopType::LtBop,
+                            BopType::GteBop => BopType::LteBop,
+                            BopType::LtBop => BopType::GtBop,
+                            BopType::LteBop => BopType::GteBop,
+                            _ => *op
+                        }),
+                        _ => return None
+                    };
+                    let col_idx = table.header_idx(field).ok()?;
+                    // Evaluate the comparison the way it is written with a null in place of the field
+                    let null_expr = match e1.as_ref() {
+                        Expr::IdentExpr(_) => Expr::BopExpr(Rc::new(Expr::ValExpr(Val::NullVal)), *op, e2.clone()),
+                        _ => Expr::BopExpr(e1.clone(), *op, Rc::new(Expr::ValExpr(Val::NullVal)))
+                    };
+                    let null_matches = eval_bool(&null_expr, &mut Environment::new()).ok()?;
+                    Some(vec![(col_idx, field_op, val.clone(), null_matches)])
+                },
+                _ => None
+            }
+        }
+        // Rows that could satisfy a where clause of equalities and IN-lists between fields and literals, joined by && and ||
+        fn selection(table: &Table, expr: &Expr) -> Option<BitVec> {
+            match expr {
+                Expr::BopExpr(e1, BopType::LogAndBop, e2) => {
+                    let mut rows = Database::selection(table, e1)?;
+                    rows &= Database::selection(table, e2)?;
+                    Some(rows)
+                },
+                Expr::BopExpr(e1, BopType::LogOrBop, e2) => {
+                    let mut rows = Database::selection(table, e1)?;
+                    rows |= Database::selection(table, e2)?;
+                    Some(rows)
+                },
+                Expr::BopExpr(e1, op @ (BopType::EqBop | BopType::StrEqBop | BopType::InBop), e2) => {
+                    let (field, vals) = match (e1.as_ref(), e2.as_ref(), op) {
+                        (Expr::IdentExpr(field), Expr::ValExpr(val), _) => (field, vec![val.clone()]),
+                        (Expr::ValExpr(val), Expr::IdentExpr(field), BopType::EqBop | BopType::StrEqBop) => (field, vec![val.clone()]),
+                        (Expr::IdentExpr(field), Expr::TupExpr(es), BopType::InBop) => {
+                            let mut vals = Vec::new();
+                            for e in es {
+                                match e.as_ref() {
+                                    Expr::ValExpr(val) => vals.push(val.clone()),
+                                    _ => return None
+                                }
+                            }
+                            (field, vals)
+                        },
+                        _ => return None
+                    };
+                    let col_idx = table.header_idx(field).ok()?;
+                    // Evaluate the comparison the way it is written with a null in place of the field
+                    let null_expr = match e1.as_ref() {
+                        Expr::IdentExpr(_) => Expr::BopExpr(Rc::new(Expr::ValExpr(Val::NullVal)), *op, e2.clone()),
+                        _ => Expr::BopExpr(e1.clone(), *op, Rc::new(Expr::ValExpr(Val::NullVal)))
+                    };
+                    let null_matches = eval_bool(&null_expr, &mut Environment::new()).ok()?;
+                    // Null literals can only ever match null rows, which come from the validity bitmaps
+                    let vals: Vec<Val> = vals.into_iter().filter(|v| !matches!(v, Val::NullVal | Val::UndefVal)).collect();
+                    let mut rows = table.rows_in(col_idx, &vals)?;
+                    if null_matches { rows |= table.null_rows(col_idx) }
+                    Some(rows)
+                },
+                _ => None
+            }
+        }
+        // Recalculate every aggregate and computation of a table from its rows
+        fn refold(&mut self, table_idx: usize) -> Result<(), String> {
+            let table = &self.tables[table_idx];
+            let mut ag_vals = Vec::new();
+            for ag in table.get_aggregates() {
+                ag_vals.push(handle_err!(self.fold_aggregate(table, &ag.2, &ag.3, &ag.4)));
+            }
+            handle_err!(self.tables[table_idx].update_aggregates(&ag_vals));
+            self.recompute(table_idx)
+        }
+        // Recalculate every computation of a table from its aggregates
+        fn recompute(&mut self, table_idx: usize) -> Result<(), String> {
+            let table = &self.tables[table_idx];
+            let mut cmp_vals = Vec::new();
+            for cmp in table.get_computations() {
+                cmp_vals.push(handle_err!(self.compute(table, &cmp.2)));
+            }
+            self.tables[table_idx].update_computations(&cmp_vals)
+        }
+        // What each row adds to an ungrouped aggregate of the form `current + e INIT e`, where e is a number or a number field.
+        // Such an aggregate is a sum (or a count), so rows can be taken out of it again. None for any other aggregate
+        fn summand(table: &Table, expr: &Expr, init: &Option<Expr>, group: &Option<String>) -> Option<Expr> {
+            if group.is_some() { return None }
+            let term = match expr {
+                Expr::BopExpr(e1, BopType::PlusBop, e2) => match (e1.as_ref(), e2.as_ref()) {
+                    (Expr::IdentExpr(c), term) | (term, Expr::IdentExpr(c)) if c == "current" => term,
+                    _ => return None
+                },
+                _ => return None
+            };
+            let invertible = match (term, init) {
+                (Expr::ValExpr(Val::NumVal(x)), Some(Expr::ValExpr(Val::NumVal(y)))) => x.to_bits() == y.to_bits(),
+                (Expr::IdentExpr(f), Some(Expr::IdentExpr(g))) => f == g && f != "current" && table.header_idx(f).is_ok_and(|i| matches!(table.get_col_types()[i], ColType::Number)),
+                _ => false
+            };
+            if invertible { Some(term.clone()) } else { None }
+        }
+        fn delete(&mut self, table_name: &String, where_: &Option<Expr>) -> QueryResult {
             // Get index of table
             let table_idx = handle!(self.get_table_index(table_name));
             let table = &self.tables[table_idx];
-            // Return aggregate
-            QueryResult::Value(handle!(table.get_aggregate(ag_name)))
+            // Sums and counts have what the deleted rows added taken out of them, as long as it's all whole numbers that add up exactly
+            let summands: Vec<Option<Expr>> = table.get_aggregates().iter().map(|ag| Database::summand(table, &ag.2, &ag.3, &ag.4)).collect();
+            let mut removed: Vec<Option<f64>> = summands.iter().map(|term| term.as_ref().map(|_| 0.0)).collect();
+            // Find rows matching where clause
+            let mut rows = Vec::new();
+            for (row_idx, row) in table.iter_indexed() {
+                let mut env = self.row_environment(table, &row);
+                let should_delete = match where_ {
+                    Some(expr) => handle!(eval_bool(expr, &mut env)),
+                    None => true
+                };
+                if !should_delete { continue }
+                rows.push(row_idx);
+                for (term, total) in summands.iter().zip(removed.iter_mut()) {
+                    if let (Some(term), Some(sum)) = (term, *total) {
+                        let x = extract_num(&handle!(eval(term, &mut env)));
+                        *total = if x.fract() == 0.0 && (sum + x).abs() <= MAX_EXACT { Some(sum + x) } else { None }
+                    }
+                }
+            }
+            handle!(self.tables[table_idx].delete_rows(&rows));
+            // Bring aggregates back into line, refolding the ones that can't be subtracted from.
+            // A sum only holds a number once it has two rows, so it's refolded when fewer are left
+            let table = &self.tables[table_idx];
+            let mut ag_vals = Vec::new();
+            for (ag, total) in table.get_aggregates().iter().zip(removed) {
+                ag_vals.push(match (&ag.1, total) {
+                    (Val::NumVal(x), Some(sum)) if table.len() >= 2 && x.fract() == 0.0 && x.abs() <= MAX_EXACT => Val::NumVal(x - sum),
+                    _ => handle!(self.fold_aggregate(table, &ag.2, &ag.3, &ag.4))
+                })
+            }
+            handle!(self.tables[table_idx].update_aggregates(&ag_vals));
+            handle!(self.recompute(table_idx));
+            QueryResult::Success("Deleted ".to_string() + &rows.len().to_string() + " rows from " + table_name)
         }
-        fn create_computation(&mut self, cmp_name: &String, expr: &Expr, table_name: &String) -> QueryResult {
+        fn update(&mut self, table_name: &String, sets: &Vec<(String, Expr)>, where_: &Option<Expr>) -> QueryResult {
             // Get index of table
             let table_idx = handle!(self.get_table_index(table_name));
-            let table = &self.tables[table_idx]; 
-            // Get value of computation (null if table is empty)
-            let comp_val = match table.len() == 0 {
-                true => Val::NullVal,
-                false => {
-                    // Environment
-                    let mut env = self.default_environment();
-                    table.push_aggregates(&mut env);
-                    // Evaluate
-                    handle!(eval(expr, &mut env))
+            let table = &self.tables[table_idx];
+            let headers = table.get_headers();
+            // Find columns being set, calculated columns can only be derived
+            let mut set_idxs = Vec::new();
+            for (field, _) in sets {
+                let col_idx = handle!(table.header_idx(field));
+                if self.calculated[table_idx][col_idx].is_some() { return QueryResult::Error("Cannot set calculated column ".to_string() + field) }
+                set_idxs.push(col_idx);
+            }
+            // Work out new values of matching rows
+            let mut updates = Vec::new();
+            let mut num_rows: usize = 0;
+            for (row_idx, row) in table.iter_indexed() {
+                let should_update = match where_ {
+                    Some(expr) => handle!(eval_bool(expr, &mut self.row_environment(table, &row))),
+                    None => true
+                };
+                if !should_update { continue }
+                num_rows += 1;
+                // Evaluate every assignment against the original row
+                let mut new_row = row.clone();
+                let mut changed = vec![false; row.len()];
+                for i in 0..sets.len() {
+                    new_row[set_idxs[i]] = handle!(eval(&sets[i].1, &mut self.row_environment(table, &row)));
+                    changed[set_idxs[i]] = true;
                 }
-            };
-            // Register computation into table
-            let table = &mut self.tables[table_idx];
-            table.add_computation(cmp_name, &comp_val, expr);
-            // Return nothing
-            QueryResult::Success("Computation ".to_string() + cmp_name + " on " + table_name)
+                // Re-derive calculated columns that depend on a changed field, the same way insert derives them
+                for col_idx in 0..new_row.len() {
+                    if let Some(expr) = &self.calculated[table_idx][col_idx] {
+                        let changed_fields: Vec<String> = (0..col_idx).filter(|j| changed[*j]).map(|j| headers[j].clone()).collect();
+                        if !mentions(expr, &changed_fields) { continue }
+                        let mut env = self.default_environment();
+                        for j in 0..col_idx {
+                            env.push(&headers[j], &new_row[j]);
+                        }
+                        new_row[col_idx] = handle!(eval(expr, &mut env));
+                        changed[col_idx] = true;
+                    }
+                }
+                for col_idx in 0..new_row.len() {
+                    if changed[col_idx] { updates.push((row_idx, col_idx, new_row[col_idx].clone())) }
+                }
+            }
+            // Write new values and bring aggregates back into line
+            handle!(self.tables[table_idx].update_rows(&updates));
+            handle!(self.refold(table_idx));
+            QueryResult::Success("Updated ".to_string() + &num_rows.to_string() + " rows in " + table_name)
         }
         fn select_computation(&mut self, cmp_name: &String, table_name: &String) -> QueryResult {
             // Get index of table
@@ -478,16 +1061,84 @@ pub mod engine {
             // Get index of table
             let table_idx = handle!(self.get_table_index(table_name));
             let table = &mut self.tables[table_idx];
+            // No fields means every field
+            let (fields, strats) = match fields.len() {
+                0 => (table.get_headers().clone(), vec![strats[0]; table.get_headers().len()]),
+                _ => (fields.clone(), strats.clone())
+            };
             // Check fields and strats len
-            if fields.len() != strats.len() { panic!("Unequal amount of fields and strategies") }
+            if fields.len() != strats.len() { return QueryResult::Error("Unequal amount of fields and strategies".to_string()) }
+            // Report of automatic choices
+            let mut report = Table::new();
+            handle!(report.add_column(&"column".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"strategy".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"estimated_bytes".to_string(), ColType::Number, CompressType::Uncompressed));
+            handle!(report.add_column(&"chosen".to_string(), ColType::Boolean, CompressType::Uncompressed));
             // Call recompress on each column
             for i in 0..fields.len() {
-                let col_idx = table.get_headers().iter().position(|r| *r == fields[i]).unwrap();
-                handle!(table.recompress(col_idx, strats[i]))
+                let col_idx = handle!(table.header_idx(&fields[i]));
+                match strats[i] {
+                    CompressType::Auto => {
+                        // Choose now rather than waiting for more rows
+                        if table.len() == 0 { return QueryResult::Error("Cannot choose compression for empty table ".to_string() + table_name) }
+                        let (sizes, chosen) = handle!(table.auto_compress(col_idx));
+                        for (strategy, bytes) in sizes {
+                            handle!(report.add_row(vec![Val::StrVal(fields[i].clone()), Val::StrVal(str_of_ctype(strategy)), Val::NumVal(bytes as f64), Val::BoolVal(strategy == chosen)]));
+                        }
+                    },
+                    strategy => handle!(table.recompress(col_idx, strategy))
+                }
             }
-            // Return aggregate
+            // Return report if anything was chosen automatically
+            if report.len() > 0 { return QueryResult::Table(report) }
             QueryResult::Success("Compression success on ".to_string() + table_name)
         }
+        fn show_storage(&mut self, table_name: &String) -> QueryResult {
+            // Get index of table
+            let table_idx = handle!(self.get_table_index(table_name));
+            let table = &self.tables[table_idx];
+            // Report table
+            let mut report = Table::new();
+            handle!(report.add_column(&"column".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"type".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"strategy".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"rows".to_string(), ColType::Number, CompressType::Uncompressed));
+            handle!(report.add_column(&"encoded_bytes".to_string(), ColType::Number, CompressType::Uncompressed));
+            handle!(report.add_column(&"uncompressed_bytes".to_string(), ColType::Number, CompressType::Uncompressed));
+            handle!(report.add_column(&"ratio".to_string(), ColType::Number, CompressType::Uncompressed));
+            handle!(report.add_column(&"segments".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"nulls".to_string(), ColType::Number, CompressType::Uncompressed));
+            // One row per column
+            for (col_idx, col_type) in table.get_col_types().into_iter().enumerate() {
+                let (rows, encoded, uncompressed) = handle!(table.storage_stats(col_idx));
+                // Number of segments using each strategy, in order of first use
+                let mut seg_counts: Vec<(CompressType, usize)> = Vec::new();
+                for strategy in handle!(table.get_column(&table.get_headers()[col_idx])).segment_strats() {
+                    match seg_counts.iter().position(|(s, _)| *s == strategy) {
+                        Some(pos) => seg_counts[pos].1 += 1,
+                        None => seg_counts.push((strategy, 1))
+                    }
+                }
+                let segments: Vec<String> = seg_counts.iter().map(|(s, n)| n.to_string() + " " + &str_of_ctype(*s)).collect();
+                // Ratio of uncompressed to encoded size, null if there's nothing stored
+                let ratio = match encoded {
+                    0 => Val::NullVal,
+                    _ => Val::NumVal(uncompressed as f64 / encoded as f64)
+                };
+                handle!(report.add_row(vec![
+                    Val::StrVal(table.get_headers()[col_idx].clone()),
+                    Val::StrVal(str_of_coltype(col_type)),
+                    Val::StrVal(str_of_ctype(table.get_compression_strats()[col_idx])),
+                    Val::NumVal(rows as f64),
+                    Val::NumVal(encoded as f64),
+                    Val::NumVal(uncompressed as f64),
+                    ratio,
+                    Val::StrVal(segments.join(", ")),
+                    Val::NumVal(table.null_count(col_idx) as f64)
+                ]));
+            }
+            QueryResult::Table(report)
+        }
         fn script(&mut self, expr: &Expr, tname: &Option<String>) -> QueryResult {
             // Setup environment
             let mut env = self.default_environment();
@@ -536,7 +1187,8 @@ pub mod engine {
                 // Increment I
                 i += 1;
             };
-            // Insert rows
+            // Read rows
+            let mut rows = Vec::new();
             for row in rdr.records() {
                 match row {
                     // Row success
@@ -564,15 +1216,23 @@ pub mod engine {
                                 None => vals_insert.push(Val::NullVal)
                             }
                         };
-                        // Insert values into table
-                        self.insert_and_update(tname, table_idx, vals_insert);
+                        rows.push(vals_insert)
                     },
                     // Row not successful
                     Err(_) => continue
                 }
             };
+            // Log rows before inserting them
+            handle!(self.log(&LogRecord::Import(tname.clone(), rows.clone())));
+            self.import_rows(tname, table_idx, rows);
             QueryResult::Success("Import ".to_string() + cname)
         }
+        fn import_rows(&mut self, tname: &String, table_idx: usize, rows: Vec<Vec<Val>>) {
+            // Insert values into table
+            for vals_insert in rows {
+                self.insert_and_update(tname, table_idx, vals_insert);
+            }
+        }
         fn export_csv(&self, cname: &String, tname: &String) -> QueryResult {
             // Get index of table
             let table_idx = handle!(self.get_table_index(tname));
@@ -606,21 +1266,276 @@ pub mod engine {
             };
             Ok(())
         }
+        fn save(&mut self, path: &Option<String>) -> QueryResult {
+            // Use given path, otherwise the file the database was opened from
+            let path = match (path, &self.path) {
+                (Some(p), _) => p.clone(),
+                (None, Some(p)) => p.clone(),
+                (None, None) => return QueryResult::Error("No file to save to, use SAVE 'path'".to_string())
+            };
+            // Saving to the opened file is a checkpoint
+            if Some(&path) == self.path.as_ref() {
+                handle!(self.checkpoint())
+            }
+            else {
+                handle!(self.save_file(&path, self.generation))
+            }
+            QueryResult::Success("Saved to ".to_string() + &path)
+        }
+        fn load(&mut self, path: &String) -> QueryResult {
+            // Replace the contents of this database with the file's contents
+            let loaded = handle!(Database::load_file(path));
+            if let Some(p) = &self.path {
+                if let Some(t) = loaded.attached_from(p) { return QueryResult::Error("Cannot load ".to_string() + path + ", table " + t + " is attached from " + p) }
+            }
+            self.tables = loaded.tables;
+            self.table_names = loaded.table_names;
+            self.constants = loaded.constants;
+            self.calculated = loaded.calculated;
+            // The log no longer describes the database, so checkpoint the new contents
+            if self.wal.is_some() { handle!(self.checkpoint()) }
+            QueryResult::Success("Loaded ".to_string() + path)
+        }
+        fn check_table(&mut self, table_name: &String) -> QueryResult {
+            // Get index of table
+            let table_idx = handle!(self.get_table_index(table_name));
+            let table = &self.tables[table_idx];
+            // Report table
+            let mut report = Table::new();
+            handle!(report.add_column(&"name".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"kind".to_string(), ColType::String, CompressType::Uncompressed));
+            handle!(report.add_column(&"ok".to_string(), ColType::Boolean, CompressType::Uncompressed));
+            handle!(report.add_column(&"problem".to_string(), ColType::String, CompressType::Uncompressed));
+            let mut checks: Vec<(String, &str, Result<(), String>)> = Vec::new();
+            // Rows and columns
+            checks.push((table_name.clone(), "table", table.check()));
+            for col_idx in 0..table.get_headers().len() {
+                checks.push((table.get_headers()[col_idx].clone(), "column", table.check_column(col_idx)));
+            }
+            // Aggregates and computations, recomputed from the rows
+            for ag in table.get_aggregates() {
+                let result = self.fold_aggregate(table, &ag.2, &ag.3, &ag.4).and_then(|val| Database::drift(&ag.1, &val));
+                checks.push((ag.0.clone(), "aggregate", result));
+            }
+            for cmp in table.get_computations() {
+                let result = self.compute(table, &cmp.2).and_then(|val| Database::drift(&cmp.1, &val));
+                checks.push((cmp.0.clone(), "computation", result));
+            }
+            // One row per check
+            for (name, kind, result) in checks {
+                let (ok, problem) = match result {
+                    Ok(_) => (true, Val::NullVal),
+                    Err(s) => (false, Val::StrVal(s))
+                };
+                handle!(report.add_row(vec![Val::StrVal(name), Val::StrVal(kind.to_string()), Val::BoolVal(ok), problem]));
+            }
+            QueryResult::Table(report)
+        }
+        // Compare a stored value against the same value recomputed, byte for byte
+        fn drift(stored: &Val, recomputed: &Val) -> Result<(), String> {
+            let (mut stored_bytes, mut recomputed_bytes) = (Vec::new(), Vec::new());
+            handle_err!(stored.write(&mut stored_bytes));
+            handle_err!(recomputed.write(&mut recomputed_bytes));
+            if stored_bytes == recomputed_bytes { return Ok(()) }
+            Err("Stored value ".to_string() + &extract_str(stored) + " but recomputed " + &extract_str(recomputed))
+        }
+        fn attach(&mut self, path: &String, table_name: &String) -> QueryResult {
+            // Check that table doesn't already exist
+            if self.table_names.contains(table_name) { return QueryResult::Error("Table ".to_string() + table_name + " already exists") }
+            // The file is replaced on every checkpoint, so it can't also be the source of one of its tables
+            if self.path.as_deref().map(file_key) == Some(file_key(path)) { return QueryResult::Error("Cannot attach from the database's own file".to_string()) }
+            let (table, calculated) = handle!(Database::attach_file(path, None, &[]));
+            self.table_names.push(table_name.clone());
+            self.calculated.push(calculated);
+            self.tables.push(table);
+            QueryResult::Success("Attached ".to_string() + path + " as " + table_name)
+        }
+        // Map a database file into memory and read one of its tables from the map, the file's only table if no name is given.
+        // Only that table's source is followed; visiting holds the files and tables being attached on the way here.
+        fn attach_file(path: &String, name: Option<&String>, visiting: &[(String, String)]) -> Result<(Table, Vec<Option<Expr>>), String> {
+            let file = Rc::new(handle_err!(MappedFile::open(path)));
+            let (mut db, mut entries) = handle_err!(Database::read_entries(path, &mut Reader::mapped(&file)));
+            let idx = match name {
+                Some(n) => handle_err!(db.get_table_index(n)),
+                None if entries.len() == 1 => 0,
+                None => return Err(path.clone() + " does not hold exactly one table")
+            };
+            let name = db.table_names.swap_remove(idx);
+            if visiting.contains(&(file_key(path), name.clone())) { return Err("Table ".to_string() + &name + " in " + path + " is attached from itself") }
+            let (calculated, stored) = entries.swap_remove(idx);
+            let (mut table, calculated) = handle_err!(Database::resolve_entry(path, &name, calculated, stored, visiting));
+            table.set_source(Some((path.clone(), name)));
+            Ok((table, calculated))
+        }
+        // Turn a table entry of the file at path into a table, attaching it if it has a source
+        fn resolve_entry(path: &str, name: &str, calculated: Vec<Option<Expr>>, stored: Stored, visiting: &[(String, String)]) -> Result<(Table, Vec<Option<Expr>>), String> {
+            let table = match stored {
+                Stored::Rows(table) => table,
+                Stored::Source(source_path, source_name) => {
+                    let mut visiting = visiting.to_vec();
+                    visiting.push((file_key(path), name.to_string()));
+                    handle_err!(Database::attach_file(&source_path, Some(&source_name), &visiting)).0
+                }
+            };
+            if calculated.len() != table.get_headers().len() { return Err("Corrupt database file: mismatched column count".to_string()) }
+            Ok((table, calculated))
+        }
+        // Name of a table attached from the file at path, if there is one
+        fn attached_from(&self, path: &str) -> Option<&String> {
+            let key = file_key(path);
+            (0..self.tables.len()).find(|i| match self.tables[*i].get_source() {
+                Some((p, _)) => file_key(p) == key,
+                None => false
+            }).map(|i| &self.table_names[i])
+        }
+        // Append a record to the log, if there is one
+        fn log(&mut self, record: &LogRecord) -> Result<(), String> {
+            match &mut self.wal {
+                Some(wal) => wal.append(record),
+                None => Ok(())
+            }
+        }
+        // Write the database to its file and empty the log.
+        // The snapshot is written under the next generation first, so a crash before the log is emptied
+        // leaves a log whose (older) generation tells open to ignore it.
+        fn checkpoint(&mut self) -> Result<(), String> {
+            let path = match &self.path {
+                Some(p) => p.clone(),
+                None => return Err("No file to checkpoint to".to_string())
+            };
+            handle_err!(self.save_file(&path, self.generation + 1));
+            self.generation += 1;
+            match &mut self.wal {
+                Some(wal) => wal.reset(self.generation),
+                None => Ok(())
+            }
+        }
+        fn save_file(&self, path: &String, generation: u64) -> Result<(), String> {
+            // Overwriting the file an attached table is mapped from would lose its rows and leave it attached from itself
+            if let Some(t) = self.attached_from(path) { return Err("Cannot save to ".to_string() + path + ", table " + t + " is attached from it") }
+            // Header
+            let mut out = Vec::new();
+            out.extend_from_slice(MAGIC);
+            handle_err!(FORMAT_VERSION.write(&mut out));
+            handle_err!(generation.write(&mut out));
+            // Constants
+            handle_err!(self.constants.write(&mut out));
+            // Tables, with calculated column expressions
+            handle_err!(self.tables.len().write(&mut out));
+            for i in 0..self.tables.len() {
+                handle_err!(self.table_names[i].write(&mut out));
+                handle_err!(self.calculated[i].write(&mut out));
+                // Attached tables are written as where to attach them from
+                handle_err!(self.tables[i].get_source().write(&mut out));
+                if self.tables[i].get_source().is_none() { handle_err!(self.tables[i].write(&mut out)) }
+            }
+            // Write to a temporary file first so that a crash never leaves a half-written database behind
+            let tmp_path = path.clone() + ".tmp";
+            let mut file = match std::fs::File::create(&tmp_path) {
+                Ok(f) => f,
+                Err(_) => return Err("Error creating ".to_string() + &tmp_path)
+            };
+            match file.write_all(&out).and_then(|_| file.sync_all()) {
+                Ok(_) => (),
+                Err(_) => return Err("Error writing ".to_string() + &tmp_path)
+            };
+            match std::fs::rename(&tmp_path, path) {
+                Ok(_) => Ok(()),
+                Err(_) => Err("Error writing ".to_string() + path)
+            }
+        }
+        fn load_file(path: &String) -> Result<Database, String> {
+            let bytes = match std::fs::read(path) {
+                Ok(b) => b,
+                Err(_) => return Err("Error opening ".to_string() + path)
+            };
+            Database::read_file(path, &mut Reader::new(&bytes))
+        }
+        fn read_file(path: &String, r: &mut Reader) -> Result<Database, String> {
+            let (mut db, entries) = handle_err!(Database::read_entries(path, r));
+            for (i, (calculated, stored)) in entries.into_iter().enumerate() {
+                let (table, calculated) = handle_err!(Database::resolve_entry(path, &db.table_names[i], calculated, stored, &[]));
+                db.calculated.push(calculated);
+                db.tables.push(table);
+            }
+            Ok(db)
+        }
+        // Read a database file's header and table entries, without attaching the tables that have a source
+        fn read_entries(path: &String, r: &mut Reader) -> Result<(Database, Vec<Entry>), String> {
+            // Check header
+            if handle_err!(r.take(MAGIC.len())) != MAGIC { return Err(path.clone() + " is not an AlexDB database file") }
+            let version = handle_err!(u32::read(r));
+            if version != FORMAT_VERSION { return Err("Unsupported database file version ".to_string() + &version.to_string()) }
+            let mut db = Database::new();
+            db.generation = handle_err!(u64::read(r));
+            // Constants
+            db.constants = handle_err!(Vec::read(r));
+            // Tables
+            let mut entries = Vec::new();
+            for _ in 0..handle_err!(usize::read(r)) {
+                db.table_names.push(handle_err!(String::read(r)));
+                let calculated: Vec<Option<Expr>> = handle_err!(Vec::read(r));
+                let source: Option<(String, String)> = handle_err!(Option::read(r));
+                let stored = match source {
+                    Some((path, name)) => Stored::Source(path, name),
+                    None => Stored::Rows(handle_err!(Table::read(r)))
+                };
+                entries.push((calculated, stored));
+            }
+            Ok((db, entries))
+        }
         pub fn execute(&mut self, q: String) -> QueryResult {
             // Parse given query
-            let mut query_parser = Parser::new(q);
+            let mut query_parser = Parser::new(q.clone());
             let parsed_query = match query_parser.parse() {
                 Ok(q) => q,
                 Err(s) => return QueryResult::Error(s)
             };
-            // Execute query
+            // Log queries that change the database before applying them
             match &parsed_query {
-                Query::CreateTable(table_name, schema) => self.create_table(table_name, schema),
+                Query::CreateTable(..) | Query::Insert(..) | Query::Const(..) | Query::Column(..) | Query::Aggregate(..) | Query::Comp(..) | Query::Compress(..) | Query::Delete(..) | Query::Update(..) | Query::Attach(..) => handle!(self.log(&LogRecord::Query(q))),
+                _ => ()
+            };
+            let result = self.apply(&parsed_query);
+            // Checkpoint once the log gets long
+            let log_len = match &self.wal {
+                Some(wal) => wal.num_records(),
+                None => 0
+            };
+            if log_len >= CHECKPOINT_INTERVAL {
+                handle!(self.checkpoint())
+            }
+            result
+        }
+        fn apply(&mut self, parsed_query: &Query) -> QueryResult {
+            // Columns, aggregates and computations are evaluated again on every insert, so a subquery's value would go stale
+            let stored = match parsed_query {
+                Query::Column(.., expr, _) | Query::Comp(_, expr, _) => vec![expr],
+                Query::Aggregate(_, expr, init, ..) => std::iter::once(expr).chain(init.iter()).collect(),
+                _ => Vec::new()
+            };
+            if stored.into_iter().any(has_subquery) { return QueryResult::Error("Subqueries cannot be used in columns, aggregates or computations".to_string()) }
+            // Run the subqueries in the query's expressions first
+            let resolved = handle!(self.resolve_query(parsed_query));
+            let parsed_query = &resolved;
+            // Attached tables can only be read
+            let target = match parsed_query {
+                Query::Insert(t, ..) | Query::Column(.., t) | Query::Aggregate(.., t) | Query::Comp(.., t) | Query::Compress(t, ..) | Query::ImportCSV(_, t) | Query::Delete(t, _) | Query::Update(t, ..) => Some(t),
+                _ => None
+            };
+            if let Some(t) = target {
+                if let Ok(idx) = self.get_table_index(t) {
+                    if self.tables[idx].get_source().is_some() { return QueryResult::Error("Table ".to_string() + t + " is attached read-only") }
+                }
+            }
+            // Execute query
+            match parsed_query {
+                Query::CreateTable(table_name, schema, cluster) => self.create_table(table_name, schema, cluster),
                 Query::Insert(table_name, fields, values) => self.insert(table_name, fields, values),
-                Query::Select(fields, table_name, where_, sort_by, limit, ecsv) => self.select(fields, table_name, where_, sort_by, limit, ecsv),
+                Query::Select(fields, source, where_, sort_by, limit, ecsv) => self.select(fields, source, where_, sort_by, limit, ecsv),
                 Query::Const(name, expr) => self.create_const(name, expr),
                 Query::Column(t, s, col_name, expr, table_name) => self.create_column(t, s, col_name, expr, table_name),
-                Query::Aggregate(ag_name, expr, init, table_name) => self.create_aggregate(ag_name, expr, init, table_name),
+                Query::Aggregate(ag_name, expr, init, group, table_name) => self.create_aggregate(ag_name, expr, init, group, table_name),
                 Query::SelectAggregate(ag_name, table_name) => self.select_aggregate(ag_name, table_name),
                 Query::Comp(cmp_name, expr, table_name) => self.create_computation(cmp_name, expr, table_name),
                 Query::SelectComp(cmp_name, table_name) => self.select_computation(cmp_name, table_name),
@@ -628,7 +1543,139 @@ pub mod engine {
                 Query::Script(expr, tname) => self.script(expr, tname),
                 Query::Exit => QueryResult::Exit,
                 Query::ImportCSV(cname, tname) => self.import_csv(cname, tname),
-                Query::ExportCSV(cname, tname) => self.export_csv(cname, tname)
+                Query::ExportCSV(cname, tname) => self.export_csv(cname, tname),
+                Query::Save(path) => self.save(path),
+                Query::Load(path) => self.load(path),
+                Query::Delete(table_name, where_) => self.delete(table_name, where_),
+                Query::Update(table_name, sets, where_) => self.update(table_name, sets, where_),
+                Query::ShowStorage(table_name) => self.show_storage(table_name),
+                Query::Attach(path, table_name) => self.attach(path, table_name),
+                Query::CheckTable(table_name) => self.check_table(table_name),
+                Query::SelectGroup(items, source, where_, key) => self.select_group(items, source, where_, key)
+            }
+        }
+        // Replace every subquery in a query's expressions with its value
+        fn resolve_query(&mut self, q: &Query) -> Result<Query, String> {
+            Ok(match q {
+                Query::Select(fields, source, where_, sort_by, limit, ecsv) => {
+                    let fields = match fields {
+                        Some(items) => Some(handle_err!(self.resolve_items(items))),
+                        None => None
+                    };
+                    let source = handle_err!(self.resolve_source(source));
+                    Query::Select(fields, source, handle_err!(self.resolve_option(where_)), sort_by.clone(), handle_err!(self.resolve_option(limit)), ecsv.clone())
+                },
+                Query::Insert(table_name, fields, values) => {
+                    let mut resolved = Vec::new();
+                    for value in values {
+                        resolved.push(Rc::new(handle_err!(self.resolve(value))))
+                    }
+                    Query::Insert(table_name.clone(), fields.clone(), resolved)
+                },
+                Query::Const(name, expr) => Query::Const(name.clone(), handle_err!(self.resolve(expr))),
+                Query::Script(expr, tname) => Query::Script(handle_err!(self.resolve(expr)), tname.clone()),
+                Query::Delete(table_name, where_) => Query::Delete(table_name.clone(), handle_err!(self.resolve_option(where_))),
+                Query::Update(table_name, sets, where_) => {
+                    let mut resolved = Vec::new();
+                    for (field, expr) in sets {
+                        resolved.push((field.clone(), handle_err!(self.resolve(expr))))
+                    }
+                    Query::Update(table_name.clone(), resolved, handle_err!(self.resolve_option(where_)))
+                },
+                Query::SelectGroup(items, source, where_, key) => {
+                    let resolved = handle_err!(self.resolve_items(items));
+                    let source = handle_err!(self.resolve_source(source));
+                    Query::SelectGroup(resolved, source, handle_err!(self.resolve_option(where_)), key.clone())
+                },
+                q => q.clone()
+            })
+        }
+        fn resolve_items(&mut self, items: &Vec<SelectItem>) -> Result<Vec<SelectItem>, String> {
+            let mut resolved = Vec::new();
+            for item in items {
+                resolved.push(match item {
+                    SelectItem::Fold(expr, init, col_type, name) => {
+                        let expr = handle_err!(self.resolve(expr));
+                        SelectItem::Fold(expr, handle_err!(self.resolve_option(init)), *col_type, name.clone())
+                    },
+                    SelectItem::Project(expr, col_type, name) => SelectItem::Project(handle_err!(self.resolve(expr)), *col_type, name.clone()),
+                    field => field.clone()
+                })
+            }
+            Ok(resolved)
+        }
+        // Resolve the ON expressions of a select's joins, a subquery selected from is resolved when it is run
+        fn resolve_source(&mut self, source: &Source) -> Result<Source, String> {
+            let mut joins = Vec::new();
+            for (join_type, table_name, on) in &source.1 {
+                joins.push((*join_type, table_name.clone(), handle_err!(self.resolve(on))))
+            }
+            Ok((source.0.clone(), joins, source.2.clone()))
+        }
+        fn resolve_option(&mut self, expr: &Option<Expr>) -> Result<Option<Expr>, String> {
+            match expr {
+                Some(e) => Ok(Some(handle_err!(self.resolve(e)))),
+                None => Ok(None)
+            }
+        }
+        // Replace every subquery in an expression with its value
+        fn resolve(&mut self, expr: &Expr) -> Result<Expr, String> {
+            Ok(match expr {
+                Expr::BopExpr(e1, bop, e2) => {
+                    let e1 = handle_err!(self.resolve(e1));
+                    Expr::BopExpr(Rc::new(e1), *bop, Rc::new(handle_err!(self.resolve(e2))))
+                },
+                Expr::UopExpr(uop, e1) => Expr::UopExpr(*uop, Rc::new(handle_err!(self.resolve(e1)))),
+                Expr::BlockExpr(b) => Expr::BlockExpr(handle_err!(self.resolve_block(b))),
+                Expr::CallExpr(e1, el) => {
+                    let e1 = handle_err!(self.resolve(e1));
+                    let mut args = Vec::new();
+                    for e in el {
+                        args.push(Rc::new(handle_err!(self.resolve(e))))
+                    }
+                    Expr::CallExpr(Rc::new(e1), args)
+                },
+                Expr::FunExpr(il, e1) => Expr::FunExpr(il.clone(), Rc::new(handle_err!(self.resolve(e1)))),
+                Expr::CondExpr(e1, e2, e3) => {
+                    let e1 = handle_err!(self.resolve(e1));
+                    let e2 = handle_err!(self.resolve(e2));
+                    Expr::CondExpr(Rc::new(e1), Rc::new(e2), Rc::new(handle_err!(self.resolve(e3))))
+                },
+                Expr::TupExpr(el) => {
+                    let mut items = Vec::new();
+                    for e in el {
+                        items.push(Rc::new(handle_err!(self.resolve(e))))
+                    }
+                    Expr::TupExpr(items)
+                },
+                Expr::QueryExpr(q) => Expr::ValExpr(handle_err!(self.subquery_value(q))),
+                e => e.clone()
+            })
+        }
+        fn resolve_block(&mut self, block: &Block) -> Result<Block, String> {
+            Ok(match block {
+                Block::StmtBlock(id, e1, b2) => {
+                    let e1 = handle_err!(self.resolve(e1));
+                    Block::StmtBlock(id.clone(), Rc::new(e1), Rc::new(handle_err!(self.resolve_block(b2))))
+                },
+                Block::ExprBlock(e1) => Block::ExprBlock(Rc::new(handle_err!(self.resolve(e1))))
+            })
+        }
+        // Value of a subquery: a value, or the field of a single row (null when there are no rows)
+        fn subquery_value(&mut self, q: &Query) -> Result<Val, String> {
+            match self.apply(q) {
+                QueryResult::Value(v) => Ok(v),
+                QueryResult::Table(t) => {
+                    if t.get_headers().len() != 1 { return Err("Subquery must select a single field".to_string()) }
+                    let mut rows = t.iter();
+                    match (rows.next(), rows.next()) {
+                        (None, _) => Ok(Val::NullVal),
+                        (Some(row), None) => Ok(row[0].clone()),
+                        _ => Err("Subquery returned more than one row".to_string())
+                    }
+                },
+                QueryResult::Error(s) => Err(s),
+                _ => Err("Subquery does not return a value".to_string())
             }
         }
         pub fn new() -> Database {
@@ -636,12 +1683,59 @@ pub mod engine {
                 tables: Vec::new(),
                 table_names: Vec::new(),
                 constants: Vec::new(),
-                calculated: Vec::new()
+                calculated: Vec::new(),
+                path: None,
+                wal: None,
+                generation: 0,
+                skipped: Vec::new()
             }
         }
+        // Open the database stored at path, or start an empty one there if the file does not exist yet.
+        // Changes made since the last checkpoint are recovered from the log next to the file.
+        pub fn open(path: &String) -> Result<Database, String> {
+            let mut db = match std::path::Path::new(path).exists() {
+                true => handle_err!(Database::load_file(path)),
+                false => Database::new()
+            };
+            db.path = Some(path.clone());
+            let (mut wal, records) = handle_err!(WriteAheadLog::open(&(path.clone() + ".wal")));
+            if wal.generation() == db.generation {
+                // Replay records (the log is not attached yet, so they aren't logged again).
+                // Queries are logged before they are checked, so records that fail are skipped just like they failed the first time.
+                for record in records {
+                    match record {
+                        LogRecord::Query(q) => if let QueryResult::Error(s) = db.execute(q.clone()) { db.skipped.push(q + ": " + &s) },
+                        LogRecord::Import(tname, rows) => match db.get_table_index(&tname) {
+                            Ok(table_idx) => db.import_rows(&tname, table_idx, rows),
+                            Err(s) => db.skipped.push("Import into ".to_string() + &tname + ": " + &s)
+                        }
+                    }
+                }
+            }
+            else if wal.generation() < db.generation {
+                // Crashed during a checkpoint, after the snapshot was written; the records are already in it
+                handle_err!(wal.reset(db.generation))
+            }
+            else {
+                return Err("Log file ".to_string() + path + ".wal is newer than the database file")
+            }
+            db.wal = Some(wal);
+            Ok(db)
+        }
         pub fn get_table_index(&self, name: &String) -> Result<usize, String> { match self.table_names.iter().position(|r| *r == *name) { Some(i) => Ok(i), None => Err("Table ".to_string() + name + " does not exist")  } }
         #[allow(dead_code)]
         pub fn get_table_names(&self) -> &Vec<String> { &self.table_names } // Testing
+        // Log records that failed when they were replayed on open
+        pub fn get_skipped(&self) -> &Vec<String> { &self.skipped }
+        // Environment for evaluating an expression against a row: constants, the table's aggregates and the row's fields
+        pub fn row_environment(&self, table: &Table, row: &Vec<Val>) -> Environment {
+            let mut env = self.default_environment();
+            table.push_aggregates(&mut env);
+            for i in 0..row.len() {
+                env.push(&table.get_headers()[i], &row[i]);
+            }
+            env
+        }
         pub fn default_environment(&self) -> Environment {  
             // New environment
             let mut def_env = Environment::new();
@@ -653,4 +1747,11 @@ pub mod engine {
             def_env
         }
     }
+    // The same file can be named by different paths, so files are compared by their canonical path when they exist
+    fn file_key(path: &str) -> String {
+        match std::fs::canonicalize(path) {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(_) => path.to_string()
+        }
+    }
 }
\ No newline at end of file
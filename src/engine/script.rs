@@ -1,4 +1,5 @@
 pub mod env {
+    use std::collections::HashMap;
     use crate::sqlscript::types::types::Val;
 
     #[derive(Clone)]
@@ -35,6 +36,66 @@ pub mod env {
             &self.data
         }
     }
+    // Values of a grouped aggregate by key, in order of each key's first row
+    #[derive(Clone)]
+    pub struct Groups {
+        index: HashMap<String, usize>, // Position of each key's pair
+        pairs: Vec<(Val, Val)>
+    }
+    impl Groups {
+        pub fn new() -> Groups {
+            Groups {
+                index: HashMap::new(),
+                pairs: Vec::new()
+            }
+        }
+        pub fn from_pairs(pairs: Vec<(Val, Val)>) -> Groups {
+            let mut groups = Groups::new();
+            for (key, val) in pairs {
+                groups.set(&key, val);
+            }
+            groups
+        }
+        // Key a value is grouped under, values of one type share a key exactly when they are equal and NaN keys are all grouped together
+        pub fn key(val: &Val) -> String {
+            match val {
+                Val::NumVal(x) => if x.is_nan() { "NaN".to_string() } else { "n".to_string() + &(x + 0.0).to_bits().to_string() },
+                Val::StrVal(s) => "s".to_string() + s,
+                Val::BoolVal(b) => b.to_string(),
+                // Nulls are equal to each other
+                Val::NullVal | Val::UndefVal => "null".to_string(),
+                _ => "NaN".to_string()
+            }
+        }
+        pub fn get(&self, key: &Val) -> Option<&Val> {
+            self.index.get(&Groups::key(key)).map(|idx| &self.pairs[*idx].1)
+        }
+        // Set the value of a key, adding the key if it is new, and give back the value it replaced
+        pub fn set(&mut self, key: &Val, val: Val) -> Option<Val> {
+            match self.index.get(&Groups::key(key)) {
+                Some(idx) => Some(std::mem::replace(&mut self.pairs[*idx].1, val)),
+                None => {
+                    self.index.insert(Groups::key(key), self.pairs.len());
+                    self.pairs.push((key.clone(), val));
+                    None
+                }
+            }
+        }
+        pub fn remove(&mut self, key: &Val) {
+            if let Some(idx) = self.index.remove(&Groups::key(key)) {
+                self.pairs.remove(idx);
+                for pos in self.index.values_mut() {
+                    if *pos > idx { *pos -= 1 }
+                }
+            }
+        }
+        pub fn pairs(&self) -> &Vec<(Val, Val)> {
+            &self.pairs
+        }
+        pub fn len(&self) -> usize {
+            self.pairs.len()
+        }
+    }
     #[derive(Clone)]
     pub struct Environment {
         frames: Vec<Frame>
@@ -108,6 +169,7 @@ pub mod engine {
             Val::UndefVal => Val::BoolVal(false),
             Val::StrVal(x) => Val::BoolVal(x != ""),
             Val::TupVal(t) => Val::BoolVal(t.len() > 0),
+            Val::GroupVal(g) => Val::BoolVal(g.len() > 0),
             Val::ClosureVal(_, _, _) => Val::BoolVal(true)
         }
     }
@@ -135,6 +197,7 @@ pub mod engine {
             },
             Val::ClosureVal(_,_,_) => Val::NumVal(f64::NAN),
             Val::TupVal(_) => Val::NumVal(f64::NAN),
+            Val::GroupVal(_) => Val::NumVal(f64::NAN),
         }
     }
     pub fn extract_num(val: &Val) -> f64 {
@@ -151,7 +214,8 @@ pub mod engine {
             Val::UndefVal => Val::StrVal("undefined".to_string()),
             Val::StrVal(x) => Val::StrVal(x.clone()),
             Val::ClosureVal(_, _, _) => Val::StrVal("[Function]".to_string()),
-            Val::TupVal(_) => Val::StrVal("[Tuple]".to_string())
+            Val::TupVal(_) => Val::StrVal("[Tuple]".to_string()),
+            Val::GroupVal(_) => Val::StrVal("[Groups]".to_string())
         }
     }
     pub fn extract_str(val: &Val) -> String {
@@ -212,6 +276,10 @@ pub mod engine {
     pub fn eval(script: &Expr, env: &mut Environment) -> Result<Val, String> {
         match script {
             Expr::BopExpr(e1, bop, e2) => {
+                // Fields of joined tables are qualified with their table, like a.id
+                if let (BopType::DotBop, Expr::IdentExpr(t), Expr::IdentExpr(f)) = (bop, e1.as_ref(), e2.as_ref()) {
+                    if let Some(v) = env.get(&(t.clone() + "." + f)) { return Ok(v) }
+                }
                 let v1 = handle!(eval(e1.as_ref(), env));
                 let v2 = handle!(eval(e2.as_ref(), env));
                 Ok(match bop {
@@ -233,6 +301,11 @@ pub mod engine {
                     BopType::GteBop => Val::BoolVal(gt(&v1, &v2) || eq(&v1, &v2)),
                     BopType::LtBop => Val::BoolVal(lt(&v1, &v2)),
                     BopType::LteBop => Val::BoolVal(lt(&v1, &v2) || eq(&v1, &v2)),
+                    BopType::InBop => match &v2 {
+                        Val::TupVal(vvec) => Val::BoolVal(vvec.iter().any(|v| eq(&v1, v))),
+                        // A single value in parentheses isn't a tuple
+                        _ => Val::BoolVal(eq(&v1, &v2))
+                    },
                     // Logical
                     BopType::LogAndBop => if extract_bool(&to_bool(&v1)) { v2 } else { v1 },
                     BopType::LogOrBop => if extract_bool(&to_bool(&v1)) { v1 } else { v2 },
@@ -244,6 +317,21 @@ pub mod engine {
                             vvec[v2_num].as_ref().clone()
                         },
                         _ => return Err("Dot operator on non-tuple".to_string())
+                    },
+                    // Value of a key in a grouped aggregate or of the [key, value] pair with a key in a tuple, null if there is none
+                    BopType::IndexBop => match v1 {
+                        Val::GroupVal(groups) => groups.get(&v2).cloned().unwrap_or(Val::NullVal),
+                        Val::TupVal(vvec) => {
+                            let pair = vvec.iter().find(|p| match p.as_ref() {
+                                Val::TupVal(kv) => kv.len() == 2 && eq(&kv[0], &v2),
+                                _ => false
+                            });
+                            match pair.map(|p| p.as_ref()) {
+                                Some(Val::TupVal(kv)) => kv[1].as_ref().clone(),
+                                _ => Val::NullVal
+                            }
+                        },
+                        _ => return Err("Index on non-tuple".to_string())
                     }
                 })
             },
@@ -258,6 +346,8 @@ pub mod engine {
                 let v1 = handle!(eval(e1.as_ref(), env));
                 if extract_bool(&to_bool(&v1)) { eval(e2.as_ref(), env) } else { eval(e3.as_ref(), env) }
             },
+            // Subqueries are replaced by their values before a query is evaluated
+            Expr::QueryExpr(_) => Err("Subqueries can only be used in queries".to_string()),
             Expr::UopExpr(uop, e1) => {
                 let v1 = handle!(eval(e1.as_ref(), env));
                 Ok(match uop {
@@ -362,4 +452,44 @@ pub mod engine {
             std::cmp::Ordering::Equal
         }
     }
+    // Check whether a script refers to any of the given names (shadowing is ignored, so this may over-report)
+    pub fn mentions(script: &Expr, names: &Vec<String>) -> bool {
+        match script {
+            Expr::BopExpr(e1, _, e2) => mentions(e1.as_ref(), names) || mentions(e2.as_ref(), names),
+            Expr::UopExpr(_, e1) => mentions(e1.as_ref(), names),
+            Expr::BlockExpr(b) => mentions_block(b, names),
+            Expr::ValExpr(_) => false,
+            Expr::IdentExpr(id) => names.contains(id),
+            Expr::CallExpr(e1, args) => mentions(e1.as_ref(), names) || args.iter().any(|e| mentions(e.as_ref(), names)),
+            Expr::FunExpr(_, e1) => mentions(e1.as_ref(), names),
+            Expr::CondExpr(e1, e2, e3) => mentions(e1.as_ref(), names) || mentions(e2.as_ref(), names) || mentions(e3.as_ref(), names),
+            Expr::TupExpr(items) => items.iter().any(|e| mentions(e.as_ref(), names)),
+            Expr::QueryExpr(_) => false
+        }
+    }
+    fn mentions_block(block: &Block, names: &Vec<String>) -> bool {
+        match block {
+            Block::StmtBlock(_, e1, b2) => mentions(e1.as_ref(), names) || mentions_block(b2.as_ref(), names),
+            Block::ExprBlock(e1) => mentions(e1.as_ref(), names)
+        }
+    }
+    // Check whether a script holds a subquery anywhere
+    pub fn has_subquery(script: &Expr) -> bool {
+        match script {
+            Expr::BopExpr(e1, _, e2) => has_subquery(e1.as_ref()) || has_subquery(e2.as_ref()),
+            Expr::UopExpr(_, e1) | Expr::FunExpr(_, e1) => has_subquery(e1.as_ref()),
+            Expr::BlockExpr(b) => has_subquery_block(b),
+            Expr::ValExpr(_) | Expr::IdentExpr(_) => false,
+            Expr::CallExpr(e1, args) => has_subquery(e1.as_ref()) || args.iter().any(|e| has_subquery(e.as_ref())),
+            Expr::CondExpr(e1, e2, e3) => has_subquery(e1.as_ref()) || has_subquery(e2.as_ref()) || has_subquery(e3.as_ref()),
+            Expr::TupExpr(items) => items.iter().any(|e| has_subquery(e.as_ref())),
+            Expr::QueryExpr(_) => true
+        }
+    }
+    fn has_subquery_block(block: &Block) -> bool {
+        match block {
+            Block::StmtBlock(_, e1, b2) => has_subquery(e1.as_ref()) || has_subquery_block(b2.as_ref()),
+            Block::ExprBlock(e1) => has_subquery(e1.as_ref())
+        }
+    }
 }
\ No newline at end of file
@@ -837,6 +837,22 @@ mod test_script {
         }
         Ok(())
     }
+    #[test]
+    fn test_in_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "x = 3; (x IN [1, '3', 5]) && !(x IN [null, 4]) && x IN 3".to_string();
+        let mut test_environment = Environment::new();
+        let mut test_parser = Parser::new(test_input);
+        let ast = types::Expr::BlockExpr(match test_parser.parse_script() { Ok(x) => x, _ => panic!("false") });
+        // Evaluate input
+        let test_val = eval(&ast, &mut test_environment).unwrap();
+        // Check output value
+        match test_val {
+            types::Val::BoolVal(true) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -2354,6 +2370,288 @@ mod test_database {
         Ok(())
     }
     #[test]
+    fn compress_str_4() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        // Create table
+        db.execute("CREATE TABLE test_table (field1 str dict, field2 num)".to_string());
+        // Insert values into table
+        db.execute("INSERT INTO test_table VALUES ('rock', 1)".to_string());
+        db.execute("INSERT INTO test_table VALUES ('jazz', 2)".to_string());
+        db.execute("INSERT INTO test_table VALUES (null, 3)".to_string());
+        db.execute("INSERT INTO test_table VALUES ('rock', 4)".to_string());
+        // Dictionary encoding is only for strings
+        match db.execute("COMPRESS test_table (field2) dict".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Recompress back and forth
+        match db.execute("COMPRESS test_table (field1) bitmap".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        db.execute("COMPRESS test_table (field1) dict".to_string());
+        // Perform select query
+        let result = db.execute("SELECT field1 FROM test_table WHERE field1 == 'rock'".to_string());
+        match result {
+            QueryResult::Table(t) => assert_eq!(t.len(), 2),
+            _ => assert!(false)
+        }
+        let result = db.execute("SELECT field1 FROM test_table WHERE field2 == 3".to_string());
+        match result {
+            QueryResult::Table(t) => {
+                for row in t.iter() {
+                    match &row[0] {
+                        Val::NullVal => assert!(true),
+                        _ => assert!(false)
+                    }
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn compress_num_forpack() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        // Create table
+        db.execute("CREATE TABLE test_table (year num forpack, rating num)".to_string());
+        db.execute("CREATE AGGREGATE sum = current + year INIT year INTO test_table".to_string());
+        // Insert values into table
+        db.execute("INSERT INTO test_table VALUES (1999, 4.5)".to_string());
+        db.execute("INSERT INTO test_table VALUES (2004, 3)".to_string());
+        db.execute("INSERT INTO test_table VALUES (2001.5, 5)".to_string());
+        // Recompress the other column too
+        match db.execute("COMPRESS test_table (rating) forpack".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 6004.5),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT rating FROM test_table WHERE year > 2000".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][0], &rows[1][0]) {
+                    (Val::NumVal(3.0), Val::NumVal(5.0)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn compress_num_delta() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        // Create table
+        db.execute("CREATE TABLE events (time num delta, value num)".to_string());
+        // Insert values into table
+        db.execute("INSERT INTO events VALUES (100, 1)".to_string());
+        db.execute("INSERT INTO events VALUES (110, 2)".to_string());
+        db.execute("INSERT INTO events VALUES (120, 3)".to_string());
+        db.execute("INSERT INTO events VALUES (135, 4)".to_string());
+        // Recompress back and forth
+        db.execute("COMPRESS events (time) xor".to_string());
+        match db.execute("COMPRESS events (time) delta".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT value FROM events WHERE time >= 120".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 2);
+                match (&rows[0][0], &rows[1][0]) {
+                    (Val::NumVal(3.0), Val::NumVal(4.0)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn compress_auto() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE events (time num, kind str, flag bool auto)".to_string());
+        // Nothing to sample yet
+        match db.execute("COMPRESS events AUTO".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        for i in 0..20 {
+            db.execute("INSERT INTO events VALUES (".to_string() + &(100 + i * 5).to_string() + ", 'click', true)");
+        }
+        // Every column gets a row per strategy its type implements, with one chosen
+        match db.execute("COMPRESS events AUTO".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 7 + 4 + 3);
+                let chosen: Vec<(String, String)> = rows.iter().filter(|r| match r[3] { Val::BoolVal(b) => b, _ => false }).map(|r| match (&r[0], &r[1]) {
+                    (Val::StrVal(c), Val::StrVal(s)) => (c.clone(), s.clone()),
+                    _ => ("".to_string(), "".to_string())
+                }).collect();
+                assert_eq!(chosen.len(), 3);
+                assert_eq!(chosen[0], ("time".to_string(), "delta".to_string()));
+                assert_eq!(chosen[1].0, "kind");
+                assert!(chosen[1].1 == "runlen" || chosen[1].1 == "dict" || chosen[1].1 == "bitmap");
+                assert_eq!(chosen[2], ("flag".to_string(), "none".to_string()))
+            },
+            _ => assert!(false)
+        }
+        // The chosen strategy is the one the last segment is stored with
+        match db.execute("SHOW STORAGE events".to_string()) {
+            QueryResult::Table(t) => match (&t.iter().next().unwrap()[2], &t.iter().next().unwrap()[7]) {
+                (Val::StrVal(strategy), Val::StrVal(segments)) => {
+                    assert_eq!(strategy, "delta");
+                    assert!(!segments.contains("none"))
+                },
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        // Data is unchanged, and explicit strategies still just succeed
+        match db.execute("SELECT kind FROM events WHERE time == 150".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 1),
+            _ => assert!(false)
+        }
+        match db.execute("COMPRESS events (time, kind) (xor, auto)".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 4),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn show_storage() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE events (time num runlen, kind str, flag bool)".to_string());
+        for i in 0..20 {
+            db.execute("INSERT INTO events VALUES (".to_string() + &(i / 10).to_string() + ", 'click', true)");
+        }
+        // One row per column
+        match db.execute("SHOW STORAGE events".to_string()) {
+            QueryResult::Table(t) => {
+                assert_eq!(t.get_headers(), &vec!["column", "type", "strategy", "rows", "encoded_bytes", "uncompressed_bytes", "ratio", "segments", "nulls"]);
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 3);
+                match (&rows[0][0], &rows[0][1], &rows[0][2], &rows[0][3], &rows[0][4], &rows[0][5], &rows[0][6]) {
+                    (Val::StrVal(c), Val::StrVal(t), Val::StrVal(s), Val::NumVal(n), Val::NumVal(e), Val::NumVal(u), Val::NumVal(r)) => {
+                        assert_eq!((c.as_str(), t.as_str(), s.as_str()), ("time", "num", "runlen"));
+                        assert_eq!(*n, 20.0);
+                        assert!(e < u);
+                        assert_eq!(*r, u / e)
+                    },
+                    _ => assert!(false)
+                }
+                // Uncompressed columns are the baseline
+                match (&rows[1][2], &rows[1][6], &rows[1][7]) {
+                    (Val::StrVal(s), Val::NumVal(r), Val::StrVal(g)) => {
+                        assert_eq!(s, "none");
+                        assert_eq!(*r, 1.0);
+                        assert_eq!(g, "1 none")
+                    },
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Empty tables have no ratio
+        db.execute("CREATE TABLE empty (field1 num xor)".to_string());
+        match db.execute("SHOW STORAGE empty".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][4], &rows[0][6]) {
+                    (Val::NumVal(e), Val::NullVal) => assert_eq!(*e, 0.0),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SHOW STORAGE missing".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn compress_num_alp() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        // Create table
+        db.execute("CREATE TABLE products (price num alp, rating num)".to_string());
+        // Insert values into table
+        db.execute("INSERT INTO products VALUES (19.99, 4.5)".to_string());
+        db.execute("INSERT INTO products VALUES (5.25, 3.8)".to_string());
+        db.execute("INSERT INTO products VALUES (null, 4.9)".to_string());
+        db.execute("INSERT INTO products VALUES (120.5, 2.1)".to_string());
+        // Recompress back and forth
+        db.execute("COMPRESS products (rating) alp".to_string());
+        db.execute("COMPRESS products (price) xor".to_string());
+        match db.execute("COMPRESS products (price) alp".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT price FROM products WHERE rating >= 4.5".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 2);
+                match (&rows[0][0], &rows[1][0]) {
+                    (Val::NumVal(19.99), Val::NullVal) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn compress_bool_runlen() -> Result<(), String> {
+        // Setup, with flags sorted into long runs
+        let csv_path = std::env::temp_dir().join("alexdb_compress_bool_runlen.csv").to_str().unwrap().to_string();
+        let mut csv = "id,active\n".to_string();
+        for i in 0..2000 {
+            csv += &(i.to_string() + "," + if i < 1500 { "true" } else { "false" } + "\n");
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id num, active bool runlen)".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO users");
+        let _ = std::fs::remove_file(&csv_path);
+        // Recompress back and forth
+        db.execute("COMPRESS users (active) bitmap".to_string());
+        match db.execute("COMPRESS users (active) runlen".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("COMPRESS users (active) xor".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT id FROM users WHERE active == false".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 500),
+            _ => assert!(false)
+        }
+        // Auto reports every boolean strategy, and picks run length
+        match db.execute("COMPRESS users (active) auto".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                let strategies: Vec<String> = rows.iter().map(|r| match &r[1] { Val::StrVal(s) => s.clone(), _ => "".to_string() }).collect();
+                assert!(strategies == vec!["none", "runlen", "bitmap"]);
+                match (&rows[0][2], &rows[1][2], &rows[1][3]) {
+                    (Val::NumVal(none), Val::NumVal(runlen), Val::BoolVal(true)) => assert!(runlen < none),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
     fn recompress_num_1() -> Result<(), String> {
         // Setup
         let mut db = Database::new();
@@ -2435,4 +2733,1274 @@ mod test_database {
         }
         Ok(())
     }
-}
\ No newline at end of file
+    #[test]
+    fn save_load_1() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_save_load_1.db").to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.clone() + ".wal");
+        let mut db = Database::new();
+        // Create table with compressed columns, calculated column, aggregate and computation
+        db.execute("CREATE TABLE test_table (field1 num xor, field2 str bitmap, field3 bool)".to_string());
+        db.execute("CREATE CONST max = fun a, b -> if a > b then a else b".to_string());
+        db.execute("CREATE AGGREGATE max_field1 = max(field1, current) INIT field1 INTO test_table".to_string());
+        db.execute("CREATE AGGREGATE count = current + 1 INIT 1 INTO test_table".to_string());
+        db.execute("CREATE COMP half_count = count / 2 INTO test_table".to_string());
+        db.execute("CREATE COLUMN (num runlen) double = field1 * 2 INTO test_table".to_string());
+        // Insert values into table
+        db.execute("INSERT INTO test_table (field1, field2, field3) VALUES (5, 'a', true)".to_string());
+        db.execute("INSERT INTO test_table (field1, field2, field3) VALUES (8, 'b', false)".to_string());
+        // Save to file
+        match db.execute("SAVE '".to_string() + &path + "'") {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Reopen the file and keep inserting
+        let mut db = Database::open(&path)?;
+        db.execute("INSERT INTO test_table (field1, field2, field3) VALUES (3, 'a', null)".to_string());
+        // Check aggregates and computations
+        match db.execute("SELECT AGGREGATE max_field1 FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 8.0),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT COMP half_count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 1.5),
+            _ => assert!(false)
+        }
+        // Check rows, including calculated column
+        match db.execute("SELECT double, field2 FROM test_table WHERE field2 == 'a'".to_string()) {
+            QueryResult::Table(t) => {
+                assert_eq!(t.len(), 2);
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][0], &rows[1][0]) {
+                    (Val::NumVal(10.0), Val::NumVal(6.0)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // SAVE without a path writes back to the opened file
+        match db.execute("SAVE".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // LOAD replaces the contents of another database
+        let mut db2 = Database::new();
+        db2.execute("LOAD '".to_string() + &path + "'");
+        match db2.execute("SELECT AGGREGATE count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 3.0),
+            _ => assert!(false)
+        }
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.clone() + ".wal");
+        Ok(())
+    }
+    #[test]
+    fn save_without_path() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        // In-memory databases need a path to save to
+        match db.execute("SAVE".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Loading a file that doesn't exist fails
+        match db.execute("LOAD 'alexdb_does_not_exist.db'".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn recover_1() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_recover_1.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE test_table (field1 num, field2 str runlen)".to_string());
+        db.execute("CREATE AGGREGATE sum = current + field1 INIT field1 INTO test_table".to_string());
+        db.execute("INSERT INTO test_table (field1, field2) VALUES (5, 'a')".to_string());
+        db.execute("INSERT INTO test_table (field1, field2) VALUES (8, 'b')".to_string());
+        // Crash without saving
+        drop(db);
+        // Simulate a torn write at the end of the log
+        let mut bytes = std::fs::read(&wal_path).unwrap();
+        bytes.extend_from_slice(&[40, 0, 0, 0, 1, 2, 3]);
+        std::fs::write(&wal_path, &bytes).unwrap();
+        // Reopen, everything committed is still there
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 13.0),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT field2 FROM test_table".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 2),
+            _ => assert!(false)
+        }
+        // Log keeps working after the torn tail was dropped
+        db.execute("INSERT INTO test_table (field1, field2) VALUES (1, 'a')".to_string());
+        drop(db);
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 14.0),
+            _ => assert!(false)
+        }
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+    #[test]
+    fn recover_failed_query() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_recover_failed_query.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE test_table (field1 num)".to_string());
+        db.execute("INSERT INTO test_table (field1) VALUES (1)".to_string());
+        // Failing queries are logged before they are checked
+        match db.execute("CREATE TABLE test_table (field1 num)".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("COMPRESS test_table (field2) runlen".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        db.execute("INSERT INTO test_table (field1) VALUES (2)".to_string());
+        drop(db);
+        // Reopen, the failed records are skipped and everything else is recovered
+        let mut db = Database::open(&path)?;
+        assert_eq!(db.get_skipped().len(), 2);
+        match db.execute("SELECT field1 FROM test_table".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 2),
+            _ => assert!(false)
+        }
+        drop(db);
+        // And again, the records are still in the log
+        let db = Database::open(&path)?;
+        assert_eq!(db.get_skipped().len(), 2);
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+    #[test]
+    fn recover_import() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_recover_import.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let csv_path = std::env::temp_dir().join("alexdb_recover_import.csv").to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        std::fs::write(&csv_path, "field1,field2\n1,a\n2,b\n3,\n").unwrap();
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE test_table (field1 num, field2 str)".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO test_table");
+        // Crash, and lose the CSV file too
+        drop(db);
+        let _ = std::fs::remove_file(&csv_path);
+        // Imported rows come back from the log
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT field1 FROM test_table".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 3),
+            _ => assert!(false)
+        }
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+    #[test]
+    fn checkpoint_1() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_checkpoint_1.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE test_table (field1 num)".to_string());
+        db.execute("CREATE AGGREGATE count = current + 1 INIT 1 INTO test_table".to_string());
+        db.execute("INSERT INTO test_table (field1) VALUES (1)".to_string());
+        // Keep a copy of the log as it was before the checkpoint
+        let old_log = std::fs::read(&wal_path).unwrap();
+        // SAVE without a path checkpoints, emptying the log
+        match db.execute("SAVE".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        assert!(std::fs::metadata(&wal_path).unwrap().len() < old_log.len() as u64);
+        drop(db);
+        // Simulate a crash after the snapshot was written but before the log was emptied
+        std::fs::write(&wal_path, &old_log).unwrap();
+        // Old records must not be applied twice
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT AGGREGATE count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 1.0),
+            _ => assert!(false)
+        }
+        // New records after the checkpoint are recovered on top of the snapshot
+        db.execute("INSERT INTO test_table (field1) VALUES (2)".to_string());
+        drop(db);
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT AGGREGATE count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 2.0),
+            _ => assert!(false)
+        }
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (field1 num xor, field2 str runlen)".to_string());
+        db.execute("CREATE CONST max = fun a, b -> if a > b then a else b".to_string());
+        db.execute("CREATE AGGREGATE max_field1 = max(field1, current) INIT field1 INTO test_table".to_string());
+        db.execute("CREATE AGGREGATE count = current + 1 INIT 1 INTO test_table".to_string());
+        db.execute("CREATE COMP half_count = count / 2 INTO test_table".to_string());
+        db.execute("INSERT INTO test_table VALUES (5, 'a')".to_string());
+        db.execute("INSERT INTO test_table VALUES (9, 'b')".to_string());
+        db.execute("INSERT INTO test_table VALUES (7, 'a')".to_string());
+        db.execute("INSERT INTO test_table VALUES (3, 'c')".to_string());
+        // Delete the largest value, where clause can use aggregates like select
+        match db.execute("DELETE FROM test_table WHERE field1 == max_field1".to_string()) {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Aggregates and computations are refolded
+        match db.execute("SELECT AGGREGATE max_field1 FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 7.0),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT COMP half_count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 1.5),
+            _ => assert!(false)
+        }
+        // Deleted rows don't show up in selects, including sorted ones
+        match db.execute("SELECT field1 FROM test_table ORDER BY field1 DESC".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 3);
+                match (&rows[0][0], &rows[1][0], &rows[2][0]) {
+                    (Val::NumVal(7.0), Val::NumVal(5.0), Val::NumVal(3.0)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Inserting after a delete keeps folding from the refolded value
+        db.execute("INSERT INTO test_table VALUES (1, 'd')".to_string());
+        match db.execute("SELECT AGGREGATE count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 4.0),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn delete_2() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (field1 num, field2 bool)".to_string());
+        db.execute("CREATE AGGREGATE sum = current + field1 INIT field1 INTO test_table".to_string());
+        for i in 0..10 {
+            db.execute("INSERT INTO test_table VALUES (".to_string() + &i.to_string() + ", " + if i % 2 == 0 { "true" } else { "false" } + ")");
+        }
+        // Delete a few rows, then enough to compact
+        db.execute("DELETE FROM test_table WHERE field1 < 2".to_string());
+        db.execute("DELETE FROM test_table WHERE field2".to_string());
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 3.0 + 5.0 + 7.0 + 9.0),
+            _ => assert!(false)
+        }
+        // Calculated columns line up with the remaining rows
+        db.execute("CREATE COLUMN (num) double = field1 * 2 INTO test_table".to_string());
+        match db.execute("SELECT double FROM test_table WHERE field1 == 5".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 1);
+                match &rows[0][0] {
+                    Val::NumVal(x) => assert_eq!(*x, 10.0),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Delete everything, aggregates start over
+        db.execute("DELETE FROM test_table".to_string());
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NullVal) => assert!(true),
+            _ => assert!(false)
+        }
+        db.execute("INSERT INTO test_table (field1, field2) VALUES (4, true)".to_string());
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 4.0),
+            _ => assert!(false)
+        }
+        // Unknown table
+        match db.execute("DELETE FROM other_table".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn delete_3() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE sales (region str, amount num)".to_string());
+        db.execute("CREATE AGGREGATE count = current + 1 INIT 1 INTO sales".to_string());
+        db.execute("CREATE AGGREGATE total = amount + current INIT amount INTO sales".to_string());
+        db.execute("CREATE AGGREGATE biggest = if amount > current then amount else current INIT amount INTO sales".to_string());
+        db.execute("CREATE AGGREGATE halves = current + amount / 2 INIT amount / 2 INTO sales".to_string());
+        db.execute("CREATE AGGREGATE regional = current + amount INIT amount GROUP BY region INTO sales".to_string());
+        for (region, amount) in [("north", 5), ("south", 3), ("north", 10), ("east", 7), ("south", 1)] {
+            db.execute(format!("INSERT INTO sales (region, amount) VALUES ('{}', {})", region, amount));
+        }
+        // Counts and sums drop the deleted rows, the rest are folded again
+        db.execute("DELETE FROM sales WHERE amount == 10 || amount == 7".to_string());
+        match db.execute("SCRIPT [count, total, biggest, halves, regional['north'], regional['east']] FROM sales".to_string()) {
+            QueryResult::Value(Val::TupVal(vals)) => match vals.iter().map(|v| v.as_ref()).collect::<Vec<&Val>>()[..] {
+                [Val::NumVal(c), Val::NumVal(t), Val::NumVal(b), Val::NumVal(h), Val::NumVal(n), Val::NullVal] => {
+                    assert_eq!((*c, *t, *b, *h, *n), (3.0, 9.0, 5.0, 4.5, 5.0))
+                },
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        // The stored values match the ones folded from the rows
+        match db.execute("CHECK TABLE sales".to_string()) {
+            QueryResult::Table(t) => {
+                for row in t.iter() {
+                    match (&row[2], &row[3]) {
+                        (Val::BoolVal(true), Val::NullVal) => assert!(true),
+                        _ => assert!(false)
+                    }
+                }
+            },
+            _ => assert!(false)
+        }
+        // Down to a single row, everything is folded from it
+        db.execute("DELETE FROM sales WHERE amount < 5".to_string());
+        match db.execute("SCRIPT [count, total, biggest] FROM sales".to_string()) {
+            QueryResult::Value(Val::TupVal(vals)) => match (vals[0].as_ref(), vals[1].as_ref(), vals[2].as_ref()) {
+                (Val::NumVal(c), Val::NumVal(t), Val::NumVal(b)) => assert_eq!((*c, *t, *b), (1.0, 5.0, 5.0)),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn delete_save_load() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_delete_save_load.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE test_table (field1 num)".to_string());
+        db.execute("CREATE AGGREGATE count = current + 1 INIT 1 INTO test_table".to_string());
+        for i in 0..5 {
+            db.execute("INSERT INTO test_table VALUES (".to_string() + &i.to_string() + ")");
+        }
+        // Tombstones are saved with the table
+        db.execute("DELETE FROM test_table WHERE field1 == 2".to_string());
+        db.execute("SAVE".to_string());
+        // Deletes are recovered from the log
+        db.execute("DELETE FROM test_table WHERE field1 == 3".to_string());
+        drop(db);
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT AGGREGATE count FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 3.0),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM test_table".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 3),
+            _ => assert!(false)
+        }
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+    #[test]
+    fn update_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (field1 num runlen, field2 str bitmap)".to_string());
+        db.execute("CREATE AGGREGATE sum = current + field1 INIT field1 INTO test_table".to_string());
+        db.execute("CREATE COMP double_sum = sum * 2 INTO test_table".to_string());
+        for i in 0..6 {
+            db.execute("INSERT INTO test_table VALUES (1, '".to_string() + if i < 3 { "a" } else { "b" } + "')");
+        }
+        // Update the middle of a run, set expressions see the row and aggregates
+        match db.execute("UPDATE test_table SET field1 = field1 + sum, field2 = field2 + 'c' WHERE field2 == 'a' && field1 == 1".to_string()) {
+            QueryResult::Success(s) => assert_eq!(s, "Updated 3 rows in test_table"),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM test_table".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 6);
+                for (i, row) in rows.iter().enumerate() {
+                    match (&row[0], &row[1]) {
+                        (Val::NumVal(x), Val::StrVal(s)) => {
+                            assert_eq!(*x, if i < 3 { 7.0 } else { 1.0 });
+                            assert_eq!(s, if i < 3 { "ac" } else { "b" })
+                        },
+                        _ => assert!(false)
+                    }
+                }
+            },
+            _ => assert!(false)
+        }
+        // Aggregates and computations are recomputed
+        match db.execute("SELECT AGGREGATE sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 24.0),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT COMP double_sum FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 48.0),
+            _ => assert!(false)
+        }
+        // Matching nothing still succeeds
+        match db.execute("UPDATE test_table SET field1 = 0 WHERE field2 == 'z'".to_string()) {
+            QueryResult::Success(s) => assert_eq!(s, "Updated 0 rows in test_table"),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn update_2() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (field1 num, field2 num)".to_string());
+        db.execute("INSERT INTO test_table VALUES (1, 10)".to_string());
+        db.execute("INSERT INTO test_table VALUES (8, 20)".to_string());
+        db.execute("CREATE COLUMN (num) calc_1 = field1 * 2 INTO test_table".to_string());
+        db.execute("CREATE COLUMN (bool) calc_2 = calc_1 > 10 INTO test_table".to_string());
+        db.execute("CREATE AGGREGATE big = current + (if calc_2 then 1 else 0) INIT (if calc_2 then 1 else 0) INTO test_table".to_string());
+        // Calculated columns depending on the changed field are re-derived in order
+        db.execute("UPDATE test_table SET field1 = 7 WHERE field2 == 10".to_string());
+        match db.execute("SELECT calc_1, calc_2 FROM test_table".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][0], &rows[0][1], &rows[1][0], &rows[1][1]) {
+                    (Val::NumVal(14.0), Val::BoolVal(true), Val::NumVal(16.0), Val::BoolVal(true)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SELECT AGGREGATE big FROM test_table".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 2.0),
+            _ => assert!(false)
+        }
+        // Calculated columns and unknown fields can't be set
+        match db.execute("UPDATE test_table SET calc_1 = 0".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("UPDATE test_table SET field3 = 0".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // A value of the wrong type leaves the table untouched
+        match db.execute("UPDATE test_table SET field2 = field2 + 1, field1 = 'x'".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT field1, field2 FROM test_table".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][0], &rows[0][1], &rows[1][0], &rows[1][1]) {
+                    (Val::NumVal(7.0), Val::NumVal(10.0), Val::NumVal(8.0), Val::NumVal(20.0)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn update_recover() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_update_recover.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE test_table (field1 num, field2 str)".to_string());
+        db.execute("INSERT INTO test_table VALUES (1, 'a')".to_string());
+        db.execute("INSERT INTO test_table VALUES (2, 'b')".to_string());
+        db.execute("UPDATE test_table SET field2 = 'c' WHERE field1 > 1".to_string());
+        // Crash without saving, update is replayed
+        drop(db);
+        let mut db = Database::open(&path)?;
+        match db.execute("SELECT field2 FROM test_table WHERE field2 == 'c'".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 1),
+            _ => assert!(false)
+        }
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+    #[test]
+    fn select_in_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (genre str bitmap, year num runlen, label str dict, live bool)".to_string());
+        for i in 0..40 {
+            let genre = ["'Rock'", "'Jazz'", "'Pop'", "null"][i % 4];
+            let label = if i % 5 == 0 { "null".to_string() } else { "'l".to_string() + &(i % 3).to_string() + "'" };
+            db.execute(format!("INSERT INTO test_table VALUES ({}, {}, {}, {})", genre, 2000 + i / 10, label, i % 2 == 0));
+        }
+        db.execute("DELETE FROM test_table WHERE year == 2003 && genre == 'Pop'".to_string());
+        // Selections match full scans
+        for (where_, expected) in [("genre == 'Rock'", 10), ("'Rock' === genre", 10), ("genre IN ['Rock', 'Pop']", 17), ("genre == 'Rock' || year == 2001", 18), ("genre IN ['Jazz'] && label == 'l1' && live == false", 3), ("year IN [2000, 2003] || label IN ['l2', 'x']", 23), ("genre == 'Metal'", 0)] {
+            let selected = match db.execute("SELECT * FROM test_table WHERE ".to_string() + where_) {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            let scanned = match db.execute("SELECT * FROM test_table WHERE !!(".to_string() + where_ + ")") {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            assert_eq!(selected.len(), expected);
+            assert_eq!(scanned.len(), expected);
+            for (a, b) in selected.iter().zip(scanned.iter()) {
+                for (x, y) in a.iter().zip(b.iter()) {
+                    match (x, y) {
+                        (Val::NumVal(x), Val::NumVal(y)) => assert_eq!(x, y),
+                        (Val::StrVal(x), Val::StrVal(y)) => assert_eq!(x, y),
+                        (Val::BoolVal(x), Val::BoolVal(y)) => assert_eq!(x, y),
+                        (Val::NullVal, Val::NullVal) => assert!(true),
+                        _ => assert!(false)
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn select_null_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (genre str bitmap, year num runlen, live bool)".to_string());
+        for i in 0..40 {
+            let genre = ["'Rock'", "'Jazz'", "null", "null"][i % 4];
+            let year = if i % 5 == 0 { "null".to_string() } else { (2000 + i / 10).to_string() };
+            let live = if i % 6 == 0 { "null" } else { "true" };
+            db.execute(format!("INSERT INTO test_table VALUES ({}, {}, {})", genre, year, live));
+        }
+        db.execute("DELETE FROM test_table WHERE year == 2003".to_string());
+        // Null rows come from the validity bitmaps and match full scans
+        for (where_, expected) in [("genre == null", 16), ("null === genre", 16), ("genre === undefined", 0), ("genre IN ['Rock', null]", 24), ("year == null || live == null", 12), ("genre == null && year == null", 4), ("live == undefined", 6)] {
+            let selected = match db.execute("SELECT * FROM test_table WHERE ".to_string() + where_) {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            let scanned = match db.execute("SELECT * FROM test_table WHERE !!(".to_string() + where_ + ")") {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            assert_eq!(selected.len(), expected);
+            assert_eq!(scanned.len(), expected);
+        }
+        // Null counts are reported for every column, including deleted rows
+        match db.execute("SHOW STORAGE test_table".to_string()) {
+            QueryResult::Table(t) => {
+                let nulls: Vec<Val> = t.iter().map(|row| row[8].clone()).collect();
+                match (&nulls[0], &nulls[1], &nulls[2]) {
+                    (Val::NumVal(g), Val::NumVal(y), Val::NumVal(l)) => assert_eq!((*g, *y, *l), (20.0, 8.0, 7.0)),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn cluster_1() -> Result<(), String> {
+        // Setup, with flags scattered across more than a segment
+        let csv_path = std::env::temp_dir().join("alexdb_cluster_1.csv").to_str().unwrap().to_string();
+        let mut csv = "id,active\n".to_string();
+        for i in 0..5000 {
+            csv += &(i.to_string() + "," + if i % 3 == 0 { "false" } else { "true" } + "\n");
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+        let mut db = Database::new();
+        match db.execute("CREATE TABLE users (id num, active bool runlen) CLUSTER BY missing".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        db.execute("CREATE TABLE users (id num, active bool runlen) CLUSTER BY active".to_string());
+        // Aggregates fold in insertion order, so current always comes from the previously inserted row
+        db.execute("CREATE AGGREGATE last_id = id INTO users".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO users");
+        let _ = std::fs::remove_file(&csv_path);
+        // The full segment is sorted on the cluster key, so the flags form two runs
+        match db.execute("SELECT active FROM users LIMIT 4096".to_string()) {
+            QueryResult::Table(t) => {
+                let flags: Vec<Vec<Val>> = t.iter().collect();
+                assert!(flags[..1366].iter().all(|r| matches!(r[0], Val::BoolVal(false))));
+                assert!(flags[1366..].iter().all(|r| matches!(r[0], Val::BoolVal(true))))
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SHOW STORAGE users".to_string()) {
+            QueryResult::Table(t) => match &t.iter().collect::<Vec<Vec<Val>>>()[1][4] {
+                // Only the open segment is still unsorted, unclustered flags would take about 80000 bytes
+                Val::NumVal(bytes) => assert!(*bytes < 20000.0),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        // Refolding after a delete (which compacts and re-sorts) still goes in insertion order
+        match db.execute("SELECT AGGREGATE last_id FROM users".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 4999.0),
+            _ => assert!(false)
+        }
+        db.execute("DELETE FROM users WHERE id > 3996 || id % 2 == 1".to_string());
+        match db.execute("SELECT AGGREGATE last_id FROM users".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 3996.0),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM users WHERE active == false".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 667),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn zone_prune_1() -> Result<(), String> {
+        // Setup, with enough rows for several zones
+        let csv_path = std::env::temp_dir().join("alexdb_zone_prune_1.csv").to_str().unwrap().to_string();
+        let mut csv = "year,name\n".to_string();
+        for i in 0..5000 {
+            let year = if i % 97 == 0 { "".to_string() } else { (1950 + i / 50).to_string() };
+            csv += &(year + ",n" + &(i / 1000).to_string() + "\n");
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+        let mut db = Database::new();
+        db.execute("CREATE TABLE test_table (year num, name str)".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO test_table");
+        let _ = std::fs::remove_file(&csv_path);
+        // Pruned selects return the same rows as full scans
+        for (where_, expected) in [("year > 2000", 2425), ("2000 < year", 2425), ("year < 5", 52), ("year >= 1990 && year < 2000", 495), ("name == 'n3' && year <= 2015", 307), ("year == 1951", 49)] {
+            let pruned = match db.execute("SELECT * FROM test_table WHERE ".to_string() + where_) {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            let scanned = match db.execute("SELECT * FROM test_table WHERE !!(".to_string() + where_ + ")") {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            assert_eq!(pruned.len(), expected);
+            assert_eq!(scanned.len(), expected);
+            for (a, b) in pruned.iter().zip(scanned.iter()) {
+                match (&a[0], &b[0], &a[1], &b[1]) {
+                    (Val::NumVal(x), Val::NumVal(y), Val::StrVal(s), Val::StrVal(t)) => assert!(x == y && s == t),
+                    (Val::NullVal, Val::NullVal, Val::StrVal(s), Val::StrVal(t)) => assert!(s == t),
+                    _ => assert!(false)
+                }
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn attach_1() -> Result<(), String> {
+        // Setup, with a saved file holding several segments
+        let path = std::env::temp_dir().join("alexdb_attach_1.db").to_str().unwrap().to_string();
+        let saved_path = std::env::temp_dir().join("alexdb_attach_1_saved.db").to_str().unwrap().to_string();
+        let csv_path = std::env::temp_dir().join("alexdb_attach_1.csv").to_str().unwrap().to_string();
+        let mut csv = "id,name,active\n".to_string();
+        for i in 0..10000 {
+            csv += &(i.to_string() + ",n" + &(i % 7).to_string() + "," + if i % 3 == 0 { "true" } else { "" } + "\n");
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id num delta, name str dict, active bool runlen)".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO users");
+        db.execute("CREATE AGGREGATE count = current + 1 INIT 1 INTO users".to_string());
+        db.execute("SAVE '".to_string() + &path + "'");
+        let _ = std::fs::remove_file(&csv_path);
+        // Attach the file's only table under another name
+        let mut db = Database::new();
+        match db.execute("ATTACH '".to_string() + &path + "' AS archive") {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT id, name FROM archive WHERE name == 'n3' && active == true".to_string()) {
+            QueryResult::Table(t) => {
+                assert_eq!(t.len(), 477);
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][0], &rows[476][0]) {
+                    (Val::NumVal(3.0), Val::NumVal(9999.0)) => assert!(true),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM archive WHERE active == null".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 6666),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT AGGREGATE count FROM archive".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 10000.0),
+            _ => assert!(false)
+        }
+        // Attached tables are read-only
+        for q in ["INSERT INTO archive (id) VALUES (1)", "DELETE FROM archive WHERE id < 5", "UPDATE archive SET id = 0", "COMPRESS archive (id) xor"] {
+            match db.execute(q.to_string()) {
+                QueryResult::Error(_) => assert!(true),
+                _ => assert!(false)
+            }
+        }
+        match db.execute("ATTACH '".to_string() + &path + "' AS archive") {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Saving keeps the attachment rather than a copy of the table
+        db.execute("CREATE TABLE notes (text str)".to_string());
+        db.execute("SAVE '".to_string() + &saved_path + "'");
+        assert!(std::fs::metadata(&saved_path).unwrap().len() < std::fs::metadata(&path).unwrap().len());
+        let mut db = Database::new();
+        db.execute("LOAD '".to_string() + &saved_path + "'");
+        match db.execute("SELECT * FROM archive WHERE id >= 9990".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 10),
+            _ => assert!(false)
+        }
+        match db.execute("INSERT INTO archive (id) VALUES (1)".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Files with several tables need the name of the table to attach
+        match db.execute("ATTACH '".to_string() + &saved_path + "' AS both") {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&saved_path);
+        Ok(())
+    }
+    #[test]
+    fn attach_2() -> Result<(), String> {
+        // Setup
+        let paths: Vec<String> = ["a", "b", "c", "d", "e"].iter().map(|n| std::env::temp_dir().join("alexdb_attach_2_".to_string() + n + ".db").to_str().unwrap().to_string()).collect();
+        let (a, b, c, d, e) = (&paths[0], &paths[1], &paths[2], &paths[3], &paths[4]);
+        let mut db = Database::new();
+        db.execute("CREATE TABLE t (id num)".to_string());
+        db.execute("INSERT INTO t (id) VALUES (1)".to_string());
+        for p in [a, c, d] {
+            db.execute("SAVE '".to_string() + p + "'");
+        }
+        // Saving over the file an attached table is mapped from fails and leaves the file as it was
+        let mut db = Database::new();
+        db.execute("ATTACH '".to_string() + a + "' AS u");
+        match db.execute("SAVE '".to_string() + a + "'") {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match Database::new().execute("ATTACH '".to_string() + a + "' AS v") {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // A database can't attach from its own file
+        let mut opened = Database::open(b)?;
+        match opened.execute("ATTACH '".to_string() + b + "' AS u") {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        drop(opened);
+        let _ = std::fs::remove_file(b.clone() + ".wal");
+        // Only the attached table's source is followed, so another table's missing source doesn't matter
+        let mut db = Database::new();
+        db.execute("ATTACH '".to_string() + c + "' AS p");
+        db.execute("SAVE '".to_string() + e + "'");
+        let mut db = Database::new();
+        db.execute("ATTACH '".to_string() + d + "' AS s");
+        db.execute("CREATE TABLE t (id num)".to_string());
+        db.execute("SAVE '".to_string() + c + "'");
+        let _ = std::fs::remove_file(d);
+        let mut db = Database::new();
+        match db.execute("LOAD '".to_string() + e + "'") {
+            QueryResult::Success(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM p".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 0),
+            _ => assert!(false)
+        }
+        // Files whose tables are attached from each other are rejected rather than followed forever
+        let mut db = Database::new();
+        db.execute("ATTACH '".to_string() + a + "' AS x");
+        db.execute("SAVE '".to_string() + b + "'");
+        let mut db = Database::new();
+        db.execute("ATTACH '".to_string() + b + "' AS t");
+        db.execute("SAVE '".to_string() + a + "'");
+        match Database::new().execute("ATTACH '".to_string() + a + "' AS y") {
+            QueryResult::Error(s) => assert!(s.ends_with("is attached from itself")),
+            _ => assert!(false)
+        }
+        match Database::new().execute("LOAD '".to_string() + b + "'") {
+            QueryResult::Error(s) => assert!(s.ends_with("is attached from itself")),
+            _ => assert!(false)
+        }
+        for p in &paths {
+            let _ = std::fs::remove_file(p);
+        }
+        Ok(())
+    }
+    #[test]
+    fn check_table_1() -> Result<(), String> {
+        // Setup, with several segments in different encodings
+        let csv_path = std::env::temp_dir().join("alexdb_check_table_1.csv").to_str().unwrap().to_string();
+        let mut csv = "id,name\n".to_string();
+        for i in 0..10000 {
+            csv += &(i.to_string() + "," + if i % 4 == 0 { "".to_string() } else { "n".to_string() + &(i % 9).to_string() }.as_str() + "\n");
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id num, name str dict)".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO users");
+        let _ = std::fs::remove_file(&csv_path);
+        db.execute("CREATE CONST step = 1".to_string());
+        db.execute("CREATE AGGREGATE count = current + step INIT step INTO users".to_string());
+        db.execute("CREATE COMP half = count / 2 INTO users".to_string());
+        db.execute("COMPRESS users (id, name) (delta, bitmap)".to_string());
+        db.execute("DELETE FROM users WHERE id < 10".to_string());
+        // Every check passes
+        match db.execute("CHECK TABLE users".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 5);
+                let kinds: Vec<String> = rows.iter().map(|r| match &r[1] { Val::StrVal(s) => s.clone(), _ => "".to_string() }).collect();
+                assert!(kinds == vec!["table", "column", "column", "aggregate", "computation"]);
+                for row in rows {
+                    match (&row[2], &row[3]) {
+                        (Val::BoolVal(true), Val::NullVal) => assert!(true),
+                        _ => assert!(false)
+                    }
+                }
+            },
+            _ => assert!(false)
+        }
+        // Changing a constant leaves the stored aggregate behind the one recomputed from the rows
+        db.execute("CREATE CONST step = 2".to_string());
+        match db.execute("CHECK TABLE users".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[2][2], &rows[3][0], &rows[3][2], &rows[3][3], &rows[4][2]) {
+                    (Val::BoolVal(true), Val::StrVal(name), Val::BoolVal(false), Val::StrVal(problem), Val::BoolVal(true)) => {
+                        assert_eq!(name, "count");
+                        assert_eq!(problem, "Stored value 9990 but recomputed 19980")
+                    },
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        match db.execute("CHECK TABLE nobody".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn join_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id num, name str)".to_string());
+        db.execute("CREATE TABLE orders (id num, user num, total num)".to_string());
+        db.execute("CREATE TABLE ranks (low num, title str)".to_string());
+        db.execute("INSERT INTO users (id, name) VALUES (1, 'ann')".to_string());
+        db.execute("INSERT INTO users (id, name) VALUES (2, 'bob')".to_string());
+        db.execute("INSERT INTO users (id, name) VALUES (3, 'cat')".to_string());
+        db.execute("INSERT INTO orders (id, user, total) VALUES (10, 1, 5)".to_string());
+        db.execute("INSERT INTO orders (id, user, total) VALUES (11, 3, 20)".to_string());
+        db.execute("INSERT INTO orders (id, user, total) VALUES (12, 1, 40)".to_string());
+        db.execute("INSERT INTO orders (id, user, total) VALUES (13, 4, 1)".to_string());
+        db.execute("INSERT INTO ranks (low, title) VALUES (0, 'small')".to_string());
+        db.execute("INSERT INTO ranks (low, title) VALUES (10, 'big')".to_string());
+        db.execute("CREATE AGGREGATE biggest = if total > current then total else current INIT total INTO orders".to_string());
+        // Inner join on an equality, with qualified and unqualified fields
+        match db.execute("SELECT name, orders.id, total FROM users JOIN orders ON users.id == orders.user WHERE total > 10 ORDER BY orders.id".to_string()) {
+            QueryResult::Table(t) => {
+                assert!(*t.get_headers() == vec!["name".to_string(), "orders.id".to_string(), "total".to_string()]);
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 2);
+                match (&rows[0][0], &rows[0][1], &rows[1][0], &rows[1][2]) {
+                    (Val::StrVal(a), Val::NumVal(11.0), Val::StrVal(b), Val::NumVal(40.0)) => assert!(a == "cat" && b == "ann"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Every field is qualified when selecting all of them
+        match db.execute("SELECT * FROM orders JOIN users ON user == users.id".to_string()) {
+            QueryResult::Table(t) => {
+                assert_eq!(t.len(), 3);
+                assert!(*t.get_headers() == vec!["orders.id", "orders.user", "orders.total", "users.id", "users.name"]);
+            },
+            _ => assert!(false)
+        }
+        // Ambiguous fields have to be qualified
+        match db.execute("SELECT id FROM users JOIN orders ON users.id == orders.user".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Left join keeps users without orders
+        match db.execute("SELECT name, total FROM users LEFT JOIN orders ON users.id == user".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 4);
+                match (&rows[2][0], &rows[2][1]) {
+                    (Val::StrVal(name), Val::NullVal) => assert_eq!(name, "bob"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Other conditions compare every pair of rows, and see the aggregates of both tables
+        match db.execute("SELECT orders.id, title FROM orders JOIN ranks ON total >= low && total < biggest ORDER BY orders.id".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 4);
+                match (&rows[2][0], &rows[2][1], &rows[3][0]) {
+                    (Val::NumVal(11.0), Val::StrVal(title), Val::NumVal(13.0)) => assert_eq!(title, "big"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Joins chain
+        match db.execute("SELECT name, title FROM users JOIN orders ON users.id == user JOIN ranks ON total >= ranks.low WHERE title === 'big'".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 2),
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM users JOIN users ON users.id == users.id".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn join_hash() -> Result<(), String> {
+        // Setup, with nulls in the keys
+        let csv_path = std::env::temp_dir().join("alexdb_join_hash.csv").to_str().unwrap().to_string();
+        let mut csv = "k,v\n".to_string();
+        for i in 0..300 {
+            csv += &(if i % 11 == 0 { "".to_string() } else { (i % 40).to_string() } + "," + &i.to_string() + "\n");
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+        let mut db = Database::new();
+        db.execute("CREATE TABLE a (k num, v num)".to_string());
+        db.execute("CREATE TABLE b (k num, v num)".to_string());
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO a");
+        db.execute("IMPORT CSV '".to_string() + &csv_path + "' INTO b");
+        let _ = std::fs::remove_file(&csv_path);
+        // A hash join finds the same pairs as comparing every pair of rows
+        for join in ["JOIN", "LEFT JOIN"] {
+            let hashed = match db.execute("SELECT a.v, b.v FROM a ".to_string() + join + " b ON a.k == b.k WHERE a.v < 100") {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            let looped = match db.execute("SELECT a.v, b.v FROM a ".to_string() + join + " b ON a.k == b.k && true WHERE a.v < 100") {
+                QueryResult::Table(t) => t,
+                _ => return Err("Select failed".to_string())
+            };
+            assert_eq!(hashed.len(), looped.len());
+            for (x, y) in hashed.iter().zip(looped.iter()) {
+                match (&x[0], &y[0], &x[1], &y[1]) {
+                    (Val::NumVal(a), Val::NumVal(b), Val::NumVal(c), Val::NumVal(d)) => assert!(a == b && c == d),
+                    _ => assert!(false)
+                }
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn group_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE sales (region str, amount num)".to_string());
+        db.execute("CREATE TABLE regions (name str, manager str)".to_string());
+        for (region, amount) in [("north", "5"), ("south", "3"), ("north", "10"), ("east", "7"), ("south", "1"), ("north", "2")] {
+            db.execute("INSERT INTO sales (region, amount) VALUES ('".to_string() + region + "', " + amount + ")");
+        }
+        db.execute("INSERT INTO sales (region, amount) VALUES (null, 4)".to_string());
+        db.execute("INSERT INTO regions (name, manager) VALUES ('north', 'ann')".to_string());
+        db.execute("INSERT INTO regions (name, manager) VALUES ('south', 'bob')".to_string());
+        db.execute("CREATE AGGREGATE total = current + amount INIT amount INTO sales".to_string());
+        // One row per region, in order of each region's first row
+        match db.execute("SELECT region, current + amount INIT amount AS sum, current + 1 INIT 1 AS count, amount AS last FROM sales WHERE amount < 10 GROUP BY region".to_string()) {
+            QueryResult::Table(t) => {
+                assert!(*t.get_headers() == vec!["region", "sum", "count", "last"]);
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 4);
+                match (&rows[0][0], &rows[0][1], &rows[0][2], &rows[0][3]) {
+                    (Val::StrVal(region), Val::NumVal(7.0), Val::NumVal(2.0), Val::NumVal(2.0)) => assert_eq!(region, "north"),
+                    _ => assert!(false)
+                }
+                match (&rows[2][0], &rows[2][1], &rows[3][0], &rows[3][1]) {
+                    (Val::StrVal(region), Val::NumVal(7.0), Val::NullVal, Val::NumVal(4.0)) => assert_eq!(region, "east"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Folds see the table's aggregates, and can group joined rows
+        match db.execute("SELECT manager, current + amount / total INIT amount / total AS share FROM sales JOIN regions ON region == name GROUP BY manager".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 2);
+                match (&rows[1][0], &rows[1][1]) {
+                    (Val::StrVal(manager), Val::NumVal(share)) => assert!(manager == "bob" && *share == 4.0 / 32.0),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Only the key can be selected besides folds
+        match db.execute("SELECT amount, current + 1 INIT 1 AS count FROM sales GROUP BY region".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Folds with values of different types can't make a column
+        match db.execute("SELECT region, if amount > 4 then 'big' else amount AS size FROM sales GROUP BY region".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn grouped_aggregate_1() -> Result<(), String> {
+        // Setup
+        let path = std::env::temp_dir().join("alexdb_grouped_aggregate_1.db").to_str().unwrap().to_string();
+        let wal_path = path.clone() + ".wal";
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let mut db = Database::open(&path)?;
+        db.execute("CREATE TABLE sales (region str, amount num)".to_string());
+        db.execute("INSERT INTO sales (region, amount) VALUES ('north', 5)".to_string());
+        db.execute("INSERT INTO sales (region, amount) VALUES ('south', 3)".to_string());
+        // Existing rows are folded per key, and inserts keep each key up to date
+        db.execute("CREATE AGGREGATE total = current + amount INIT amount GROUP BY region INTO sales".to_string());
+        db.execute("INSERT INTO sales (region, amount) VALUES ('north', 10)".to_string());
+        db.execute("INSERT INTO sales (region, amount) VALUES ('east', 7)".to_string());
+        match db.execute("SELECT AGGREGATE total FROM sales".to_string()) {
+            QueryResult::Table(t) => {
+                assert!(*t.get_headers() == vec!["region", "value"]);
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 3);
+                match (&rows[0][0], &rows[0][1], &rows[2][1]) {
+                    (Val::StrVal(region), Val::NumVal(15.0), Val::NumVal(7.0)) => assert_eq!(region, "north"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Keys can be looked up from scripts, missing keys are null
+        match db.execute("SCRIPT total['north'] + total['south'] FROM sales".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 18.0),
+            _ => assert!(false)
+        }
+        match db.execute("SCRIPT total['west'] FROM sales".to_string()) {
+            QueryResult::Value(Val::NullVal) => assert!(true),
+            _ => assert!(false)
+        }
+        // A failed insert leaves the groups as they were
+        match db.execute("INSERT INTO sales (region, amount) VALUES ('west', 'x')".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SCRIPT [total['west'], total['north']] FROM sales".to_string()) {
+            QueryResult::Value(Val::TupVal(vals)) => match (vals[0].as_ref(), vals[1].as_ref()) {
+                (Val::NullVal, Val::NumVal(x)) => assert_eq!(*x, 15.0),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SELECT * FROM sales WHERE amount == total[region]".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 2),
+            _ => assert!(false)
+        }
+        // Deletes refold every group
+        db.execute("DELETE FROM sales WHERE amount == 10".to_string());
+        match db.execute("SCRIPT total['north'] FROM sales".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 5.0),
+            _ => assert!(false)
+        }
+        // Groups are saved with the table
+        db.execute("SAVE".to_string());
+        db.execute("INSERT INTO sales (region, amount) VALUES ('south', 1)".to_string());
+        drop(db);
+        let mut db = Database::open(&path)?;
+        match db.execute("SCRIPT total['south'] FROM sales".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 4.0),
+            _ => assert!(false)
+        }
+        // Group field has to be a column
+        match db.execute("CREATE AGGREGATE count = current + 1 INIT 1 GROUP BY city INTO sales".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        Ok(())
+    }
+
+    #[test]
+    fn subquery_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE products (name str, price num)".to_string());
+        db.execute("CREATE TABLE other (price num)".to_string());
+        db.execute("CREATE TABLE stock (product str, count num)".to_string());
+        for (name, price) in [("pen", "2"), ("book", "12"), ("lamp", "30"), ("cup", "6")] {
+            db.execute("INSERT INTO products (name, price) VALUES ('".to_string() + name + "', " + price + ")");
+        }
+        db.execute("INSERT INTO other (price) VALUES (4)".to_string());
+        db.execute("INSERT INTO other (price) VALUES (20)".to_string());
+        db.execute("INSERT INTO stock (product, count) VALUES ('book', 3)".to_string());
+        db.execute("INSERT INTO stock (product, count) VALUES ('cup', 0)".to_string());
+        db.execute("CREATE AGGREGATE avg_price = [current.0 + price, current.1 + 1] INIT [price, 1] INTO other".to_string());
+        db.execute("CREATE COMP avg = avg_price.0 / avg_price.1 INTO other".to_string());
+        // Scalar subqueries are run before the query
+        match db.execute("SELECT name FROM products WHERE price >= (SELECT COMP avg FROM other)".to_string()) {
+            QueryResult::Table(t) => assert_eq!(t.len(), 2),
+            _ => assert!(false)
+        }
+        // A select gives the field of its single row, or null without rows
+        match db.execute("SELECT name FROM products WHERE price == (SELECT price FROM products ORDER BY price DESC LIMIT 1)".to_string()) {
+            QueryResult::Table(t) => match &t.iter().next().unwrap()[0] {
+                Val::StrVal(name) => assert_eq!(name, "lamp"),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SCRIPT (SELECT price FROM other WHERE price > 100)".to_string()) {
+            QueryResult::Value(Val::NullVal) => assert!(true),
+            _ => assert!(false)
+        }
+        match db.execute("SCRIPT (SELECT price FROM other)".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Inserts keep the value of the subquery
+        db.execute("INSERT INTO other (price) VALUES ((SELECT price FROM products WHERE name == 'cup'))".to_string());
+        match db.execute("SELECT COMP avg FROM other".to_string()) {
+            QueryResult::Value(Val::NumVal(x)) => assert_eq!(x, 10.0),
+            _ => assert!(false)
+        }
+        // Select from a subquery, and join it with tables
+        match db.execute("SELECT name FROM (SELECT * FROM products WHERE price > 5) AS pricey WHERE price < 20 ORDER BY name".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 2);
+                match (&rows[0][0], &rows[1][0]) {
+                    (Val::StrVal(a), Val::StrVal(b)) => assert!(a == "book" && b == "cup"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SELECT pricey.name, count FROM (SELECT * FROM products WHERE price > 5) AS pricey LEFT JOIN stock ON pricey.name == product".to_string()) {
+            QueryResult::Table(t) => {
+                assert!(*t.get_headers() == vec!["pricey.name", "count"]);
+                assert_eq!(t.len(), 3)
+            },
+            _ => assert!(false)
+        }
+        match db.execute("SELECT product, current + count * price INIT count * price AS value FROM (SELECT * FROM (SELECT * FROM products) AS p) AS q JOIN stock ON name == product GROUP BY product".to_string()) {
+            QueryResult::Table(t) => match &t.iter().next().unwrap()[1] {
+                Val::NumVal(x) => assert_eq!(*x, 36.0),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        // Only selects that return a table can be selected from
+        match db.execute("SELECT * FROM (SELECT AGGREGATE avg_price FROM other) AS a".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        // Definitions evaluated on every insert can't hold subqueries, constants store their value
+        for q in ["CREATE COLUMN (num) rel = price / (SELECT COMP avg FROM other) INTO products", "CREATE AGGREGATE top = (SELECT COMP avg FROM other) INTO products", "CREATE COMP rel = (SELECT COMP avg FROM other) INTO products"] {
+            match db.execute(q.to_string()) {
+                QueryResult::Error(_) => assert!(true),
+                _ => assert!(false)
+            }
+        }
+        db.execute("CREATE CONST limit = fun x -> x > (SELECT COMP avg FROM other)".to_string());
+        match db.execute("SCRIPT limit(12)".to_string()) {
+            QueryResult::Value(Val::BoolVal(b)) => assert!(b),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn project_1() -> Result<(), String> {
+        // Setup
+        let mut db = Database::new();
+        db.execute("CREATE TABLE t (name str, price num, year num)".to_string());
+        db.execute("CREATE TABLE makers (product str, maker str)".to_string());
+        db.execute("INSERT INTO t (name, price, year) VALUES ('pen', 2, 2001)".to_string());
+        db.execute("INSERT INTO t (name, price, year) VALUES ('lamp', 30, 1999)".to_string());
+        db.execute("INSERT INTO t (name, price, year) VALUES ('cup', 10, null)".to_string());
+        db.execute("INSERT INTO makers (product, maker) VALUES ('lamp', 'lux')".to_string());
+        db.execute("CREATE AGGREGATE total = current + price INIT price INTO t".to_string());
+        // Expressions are evaluated on each selected row, with the types of their values
+        match db.execute("SELECT name, price * 2 AS doubled, &year + '-' AS label, price / total AS share FROM t WHERE price > 5 ORDER BY doubled DESC".to_string()) {
+            QueryResult::Table(t) => {
+                assert!(*t.get_headers() == vec!["name", "doubled", "label", "share"]);
+                assert!(crate::sqlscript::types::types::str_of_coltype(t.get_col_types()[1]) == "num" && crate::sqlscript::types::types::str_of_coltype(t.get_col_types()[2]) == "str");
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                assert_eq!(rows.len(), 2);
+                match (&rows[0][0], &rows[0][1], &rows[0][2], &rows[0][3]) {
+                    (Val::StrVal(name), Val::NumVal(60.0), Val::StrVal(label), Val::NumVal(share)) => assert!(name == "lamp" && label == "1999-" && *share == 30.0 / 42.0),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Declared types convert values, nulls stay null
+        match db.execute("SELECT year AS (str) y, price > 5 AS (num) pricey FROM t".to_string()) {
+            QueryResult::Table(t) => {
+                let rows: Vec<Vec<Val>> = t.iter().collect();
+                match (&rows[0][0], &rows[0][1], &rows[2][0], &rows[1][1]) {
+                    (Val::StrVal(y), Val::NumVal(0.0), Val::NullVal, Val::NumVal(1.0)) => assert_eq!(y, "2001"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Expressions see joined fields
+        match db.execute("SELECT name, maker + ':' + name AS tag FROM t JOIN makers ON name == product".to_string()) {
+            QueryResult::Table(t) => match &t.iter().next().unwrap()[1] {
+                Val::StrVal(tag) => assert_eq!(tag, "lux:lamp"),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        // Values of different types can't make a column
+        match db.execute("SELECT if price > 5 then 'big' else price AS size FROM t".to_string()) {
+            QueryResult::Error(_) => assert!(true),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+}
+
@@ -6,7 +6,9 @@ mod repl;
 use crate::repl::repl::repl::*;
 
 fn main() {
-    match repl_main() {
+    // Optional path to a database file
+    let path = std::env::args().nth(1);
+    match repl_main(path) {
         Ok(_) => std::process::exit(0),
         _ => std::process::exit(1)
     };
@@ -20,6 +20,11 @@ pub mod repl {
                 let mut inners = Vec::new();
                 for val in v { inners.push(pretty_print(val.as_ref().clone())) };
                 "[".to_string() + &inners.join(", ") + "]"
+            },
+            Val::GroupVal(g) => {
+                let mut inners = Vec::new();
+                for (key, val) in g.pairs() { inners.push("[".to_string() + &pretty_print(key.clone()) + ", " + &pretty_print(val.clone()) + "]") };
+                "[".to_string() + &inners.join(", ") + "]"
             }
         }   
     }
@@ -73,9 +78,23 @@ pub mod repl {
         }
     }
 
-    pub fn repl_main() -> Result<()> {
-        // Create database
-        let mut db = Database::new();
+    pub fn repl_main(path: Option<String>) -> Result<()> {
+        // Create database, backed by a file if one was given
+        let mut db = match path {
+            Some(p) => match Database::open(&p) {
+                Ok(db) => {
+                    for s in db.get_skipped() {
+                        println!("Skipped log record: {}", s)
+                    }
+                    db
+                },
+                Err(s) => {
+                    println!("Error: {}", s);
+                    return Ok(())
+                }
+            },
+            None => Database::new()
+        };
         // Create editor
         let mut rl = DefaultEditor::new()?;
         loop {
@@ -60,6 +60,7 @@ pub mod lexer {
         SelectKw,
         FromKw,
         WhereKw,
+        InKw,
         InsertKw,
         IntoKw,
         ValuesKw,
@@ -75,12 +76,28 @@ pub mod lexer {
         InitKw,
         CompKw,
         CompressKw,
+        AutoKw,
         CompressType,
         ScriptKw,
         ExitKw,
         ImportKw,
         ExportKw,
         CSVKw,
+        SaveKw,
+        LoadKw,
+        DeleteKw,
+        UpdateKw,
+        SetKw,
+        ShowKw,
+        StorageKw,
+        ClusterKw,
+        AttachKw,
+        AsKw,
+        CheckKw,
+        JoinKw,
+        LeftKw,
+        OnKw,
+        GroupKw,
         // Type keywords
         NumberKw,
         StrKw,
@@ -121,6 +138,11 @@ pub mod lexer {
     fn compression_value_xor (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::Xor) }
     fn compression_value_bitmap (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::BitMap) }
     fn compression_value_runlen (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::RunLength) }
+    fn compression_value_dict (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::Dict) }
+    fn compression_value_forpack (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::ForPack) }
+    fn compression_value_delta (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::Delta) }
+    fn compression_value_alp (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::Alp) }
+    fn compression_value_auto (_: &str) -> TokenValue { TokenValue::CompressionType(CompressType::Auto) }
     // Associates a kind of token with a regular expression that matches it, a function to derive a value.
     // If token kind is none, won't generate a token
     const TOKEN_MAP: &[(Option<TokenKind>, &str, fn(&str) -> TokenValue)] = &[
@@ -134,6 +156,7 @@ pub mod lexer {
         (Some(TokenKind::SelectKw), reg!(r"SELECT"), none_value),
         (Some(TokenKind::FromKw), reg!(r"FROM"), none_value),
         (Some(TokenKind::WhereKw), reg!(r"WHERE"), none_value),
+        (Some(TokenKind::InKw), reg!(r"IN"), none_value),
         (Some(TokenKind::InsertKw), reg!(r"INSERT"), none_value),
         (Some(TokenKind::IntoKw), reg!(r"INTO"), none_value),
         (Some(TokenKind::ValuesKw), reg!(r"VALUES"), none_value),
@@ -150,15 +173,36 @@ pub mod lexer {
         (Some(TokenKind::InitKw), reg!(r"INIT"), none_value),
         (Some(TokenKind::CompKw), reg!(r"COMP"), none_value),
         (Some(TokenKind::CompressKw), reg!(r"COMPRESS"), none_value),
+        (Some(TokenKind::AutoKw), reg!(r"AUTO"), none_value),
         (Some(TokenKind::CompressType), reg!(r"none"), compression_value_none),
         (Some(TokenKind::CompressType), reg!(r"bitmap"), compression_value_bitmap),
         (Some(TokenKind::CompressType), reg!(r"xor"), compression_value_xor),
         (Some(TokenKind::CompressType), reg!(r"runlen"), compression_value_runlen),
+        (Some(TokenKind::CompressType), reg!(r"dict"), compression_value_dict),
+        (Some(TokenKind::CompressType), reg!(r"forpack"), compression_value_forpack),
+        (Some(TokenKind::CompressType), reg!(r"delta"), compression_value_delta),
+        (Some(TokenKind::CompressType), reg!(r"alp"), compression_value_alp),
+        (Some(TokenKind::CompressType), reg!(r"auto"), compression_value_auto),
         (Some(TokenKind::ScriptKw), reg!(r"SCRIPT"), none_value),
         (Some(TokenKind::ExitKw), reg!(r"EXIT"), none_value),
         (Some(TokenKind::ImportKw), reg!(r"IMPORT"), none_value),
         (Some(TokenKind::ExportKw), reg!(r"EXPORT"), none_value),
         (Some(TokenKind::CSVKw), reg!(r"CSV"), none_value),
+        (Some(TokenKind::SaveKw), reg!(r"SAVE"), none_value),
+        (Some(TokenKind::LoadKw), reg!(r"LOAD"), none_value),
+        (Some(TokenKind::DeleteKw), reg!(r"DELETE"), none_value),
+        (Some(TokenKind::UpdateKw), reg!(r"UPDATE"), none_value),
+        (Some(TokenKind::SetKw), reg!(r"SET"), none_value),
+        (Some(TokenKind::ShowKw), reg!(r"SHOW"), none_value),
+        (Some(TokenKind::StorageKw), reg!(r"STORAGE"), none_value),
+        (Some(TokenKind::ClusterKw), reg!(r"CLUSTER"), none_value),
+        (Some(TokenKind::AttachKw), reg!(r"ATTACH"), none_value),
+        (Some(TokenKind::AsKw), reg!(r"AS"), none_value),
+        (Some(TokenKind::CheckKw), reg!(r"CHECK"), none_value),
+        (Some(TokenKind::JoinKw), reg!(r"JOIN"), none_value),
+        (Some(TokenKind::LeftKw), reg!(r"LEFT"), none_value),
+        (Some(TokenKind::OnKw), reg!(r"ON"), none_value),
+        (Some(TokenKind::GroupKw), reg!(r"GROUP"), none_value),
         // Type keywords
         (Some(TokenKind::NumberKw), reg!(r"num"), num_type_value),
         (Some(TokenKind::StrKw), reg!(r"str"), str_type_value),
@@ -65,6 +65,7 @@ pub mod parser {
                 TokenKind::Lte => Some(types::BopType::LteBop),
                 TokenKind::Eq => Some(types::BopType::EqBop),
                 TokenKind::StrEq => Some(types::BopType::StrEqBop),
+                TokenKind::InKw => Some(types::BopType::InBop),
                 _ => None
             }
         }
@@ -192,12 +193,45 @@ pub mod parser {
                                     // Return identlist none
                                     None
                                 },
-                                _ => Some(handle!(self.identlist()))
+                                _ => Some(handle!(self.selectlist()))
                             };
                             // Expect and pop FROM keyword
                             handle!(self.pop_expect(TokenKind::FromKw));
-                            // Parse single ident
-                            let tableid = handle!(self.ident());
+                            // Parse single ident, or a subquery and its name
+                            let (tableid, subquery) = match self.peek().kind {
+                                TokenKind::LParen => {
+                                    // Pop lparen
+                                    handle!(self.pop());
+                                    if self.peek().kind != TokenKind::SelectKw { perr!(self) }
+                                    let query = handle!(self.query());
+                                    handle!(self.pop_expect(TokenKind::RParen));
+                                    // Expect AS
+                                    handle!(self.pop_expect(TokenKind::AsKw));
+                                    (handle!(self.ident()), Some(Rc::new(query)))
+                                },
+                                _ => (handle!(self.ident()), None)
+                            };
+                            // Parse joins
+                            let mut joins = Vec::new();
+                            loop {
+                                let join_type = match self.peek().kind {
+                                    TokenKind::JoinKw => types::JoinType::Inner,
+                                    TokenKind::LeftKw => {
+                                        // Pop left keyword, JOIN follows
+                                        handle!(self.pop());
+                                        types::JoinType::Left
+                                    },
+                                    _ => break
+                                };
+                                // Pop join keyword
+                                handle!(self.pop_expect(TokenKind::JoinKw));
+                                // Parse joined table
+                                let jtable = handle!(self.ident());
+                                // Expect ON
+                                handle!(self.pop_expect(TokenKind::OnKw));
+                                // Parse join condition
+                                joins.push((join_type, jtable, handle!(self.expr())));
+                            }
                             // Check if where clause
                             let wherescript = match self.peek().kind {
                                 TokenKind::WhereKw => {
@@ -208,14 +242,39 @@ pub mod parser {
                                 },
                                 _ => None
                             };
+                            // Grouped selects end with GROUP BY
+                            if self.peek().kind == TokenKind::GroupKw {
+                                // Pop group keyword
+                                handle!(self.pop());
+                                // Pop expect by keyword
+                                handle!(self.pop_expect(TokenKind::ByKw));
+                                let key = handle!(self.field());
+                                return match ilist {
+                                    Some(items) => Ok(types::Query::SelectGroup(items, (tableid, joins, subquery), wherescript, key)),
+                                    None => Err("Grouped selects need a list of fields and folds".to_string())
+                                }
+                            }
+                            // Otherwise fields and expressions without INIT are selected
+                            let mut fields = None;
+                            if let Some(items) = ilist {
+                                let mut projected = Vec::new();
+                                for item in items {
+                                    projected.push(match item {
+                                        types::SelectItem::Fold(expr, None, col_type, name) => types::SelectItem::Project(expr, col_type, name),
+                                        types::SelectItem::Fold(_, Some(_), _, name) => return Err("Fold ".to_string() + &name + " needs GROUP BY"),
+                                        item => item
+                                    })
+                                }
+                                fields = Some(projected)
+                            }
                             let sortscript = match self.peek().kind {
                                 TokenKind::SortKw => {
                                     // Pop sort keyword
                                     handle!(self.pop());
                                     // Pop expect by keyword
                                     handle!(self.pop_expect(TokenKind::ByKw));
-                                    // Parse ident
-                                    let ident = handle!(self.ident());
+                                    // Parse field
+                                    let ident = handle!(self.field());
                                     // Check if sort type
                                     match self.peek().kind {
                                         TokenKind::SortType => match handle!(self.pop()).value {
@@ -252,7 +311,7 @@ pub mod parser {
                                 _ => None
                             };
                             // Put everything together
-                            Ok(types::Query::Select(ilist, tableid, wherescript, sortscript, limitscript, exportcsv))
+                            Ok(types::Query::Select(fields, (tableid, joins, subquery), wherescript, sortscript, limitscript, exportcsv))
                         }
                     }
                 },
@@ -289,6 +348,11 @@ pub mod parser {
                 TokenKind::CompressKw => {
                     // Parse table name
                     let table = handle!(self.ident());
+                    // Check if automatic compression of every field
+                    if let TokenKind::AutoKw = self.peek().kind {
+                        handle!(self.pop());
+                        return Ok(types::Query::Compress(table, Vec::new(), vec![CompressType::Auto]))
+                    }
                     // Expect LPAREN
                     handle!(self.pop_expect(TokenKind::LParen));
                     // Fields
@@ -373,6 +437,96 @@ pub mod parser {
                     // Put query together
                     Ok(types::Query::ExportCSV(cpath, tname))
                 },
+                TokenKind::SaveKw => {
+                    // Check if path given
+                    match self.peek().kind {
+                        TokenKind::String => match handle!(self.pop()).value {
+                            TokenValue::String(s) => Ok(types::Query::Save(Some(s))),
+                            _ => perr!(self)
+                        },
+                        _ => Ok(types::Query::Save(None))
+                    }
+                },
+                TokenKind::LoadKw => {
+                    // Get file path
+                    let path = match handle!(self.pop()).value {
+                        TokenValue::String(s) => s,
+                        _ => perr!(self)
+                    };
+                    Ok(types::Query::Load(path))
+                },
+                TokenKind::DeleteKw => {
+                    // Expect and pop FROM keyword
+                    handle!(self.pop_expect(TokenKind::FromKw));
+                    // Parse table name
+                    let tname = handle!(self.ident());
+                    // Check if where clause
+                    let wherescript = match self.peek().kind {
+                        TokenKind::WhereKw => {
+                            // Pop where keyword
+                            handle!(self.pop());
+                            // Parse script
+                            Some(handle!(self.expr()))
+                        },
+                        _ => None
+                    };
+                    Ok(types::Query::Delete(tname, wherescript))
+                },
+                TokenKind::UpdateKw => {
+                    // Parse table name
+                    let tname = handle!(self.ident());
+                    // Expect and pop SET keyword
+                    handle!(self.pop_expect(TokenKind::SetKw));
+                    // Parse assignments
+                    let mut assignments = Vec::new();
+                    loop {
+                        // Parse field name
+                        let field = handle!(self.ident());
+                        // Expect and pop assignment
+                        handle!(self.pop_expect(TokenKind::AssignKw));
+                        // Parse value
+                        assignments.push((field, handle!(self.expr())));
+                        // Continue if comma
+                        match self.peek().kind {
+                            TokenKind::Comma => { handle!(self.pop()); },
+                            _ => break
+                        }
+                    }
+                    // Check if where clause
+                    let wherescript = match self.peek().kind {
+                        TokenKind::WhereKw => {
+                            // Pop where keyword
+                            handle!(self.pop());
+                            // Parse script
+                            Some(handle!(self.expr()))
+                        },
+                        _ => None
+                    };
+                    Ok(types::Query::Update(tname, assignments, wherescript))
+                },
+                TokenKind::AttachKw => {
+                    // Get file path
+                    let path = match handle!(self.pop()).value {
+                        TokenValue::String(s) => s,
+                        _ => perr!(self)
+                    };
+                    // Expect AS
+                    handle!(self.pop_expect(TokenKind::AsKw));
+                    // Get table name
+                    Ok(types::Query::Attach(path, handle!(self.ident())))
+                },
+                TokenKind::CheckKw => {
+                    // Expect and pop TABLE keyword
+                    handle!(self.pop_expect(TokenKind::TableKw));
+                    // Parse table name
+                    Ok(types::Query::CheckTable(handle!(self.ident())))
+                },
+                TokenKind::ShowKw => {
+                    // Expect and pop STORAGE keyword
+                    handle!(self.pop_expect(TokenKind::StorageKw));
+                    // Parse table name
+                    Ok(types::Query::ShowStorage(handle!(self.ident())))
+                },
                 TokenKind::CreateKw => {
                     match handle!(self.pop()).kind {
                         TokenKind::TableKw => {
@@ -384,8 +538,19 @@ pub mod parser {
                             let clist = handle!(self.collist());
                             // Expect and pop rparen
                             handle!(self.pop_expect(TokenKind::RParen));
+                            // Parse optional cluster key
+                            let cluster = match self.peek().kind {
+                                TokenKind::ClusterKw => {
+                                    // Pop cluster keyword
+                                    handle!(self.pop());
+                                    // Pop expect by keyword
+                                    handle!(self.pop_expect(TokenKind::ByKw));
+                                    Some(handle!(self.ident()))
+                                },
+                                _ => None
+                            };
                             // Return
-                            Ok(types::Query::CreateTable(tname, clist))
+                            Ok(types::Query::CreateTable(tname, clist, cluster))
                         },
                         TokenKind::CompKw => {
                             // Parse single assignment
@@ -431,12 +596,23 @@ pub mod parser {
                                 },
                                 _ => None
                             };
+                            // Parse GROUP BY
+                            let group = match self.peek().kind {
+                                TokenKind::GroupKw => {
+                                    // Pop GROUP
+                                    handle!(self.pop());
+                                    // Expect and pop BY
+                                    handle!(self.pop_expect(TokenKind::ByKw));
+                                    Some(handle!(self.ident()))
+                                },
+                                _ => None
+                            };
                             // Expect and pop INTO
                             handle!(self.pop_expect(TokenKind::IntoKw));
                             // Parse table name
                             let tname = handle!(self.ident());
                             // Put together
-                            Ok(types::Query::Aggregate(assign.0, assign.1, init, tname))
+                            Ok(types::Query::Aggregate(assign.0, assign.1, init, group, tname))
                         },
                         TokenKind::ConstKw => {
                             // Parse single assignment
@@ -576,7 +752,7 @@ pub mod parser {
         }
         fn expr_level_6(&mut self) -> Result<types::Expr, String> {
             let first = handle!(self.expr_level_7());
-            // Check if postfix (call only postfix) at front
+            // Check if postfix (call or index) at front
             match self.peek().kind {
                 TokenKind::LParen => {
                     // Pop LParen
@@ -591,6 +767,16 @@ pub mod parser {
                     // Construct expression
                     Ok(types::Expr::CallExpr(Rc::new(first), elist))
                 },
+                TokenKind::LBracket => {
+                    // Pop LBracket
+                    handle!(self.pop());
+                    // Parse key
+                    let key = handle!(self.expr());
+                    // Expect RBracket
+                    handle!(self.pop_expect(TokenKind::RBracket));
+                    // Construct expression
+                    Ok(types::Expr::BopExpr(Rc::new(first), types::BopType::IndexBop, Rc::new(key)))
+                },
                 _ => Ok(first)
             }
         }
@@ -625,6 +811,12 @@ pub mod parser {
                 TokenKind::LParen => {
                     // Pop lparen
                     handle!(self.pop());
+                    // Subquery
+                    if self.peek().kind == TokenKind::SelectKw {
+                        let query = handle!(self.query());
+                        handle!(self.pop_expect(TokenKind::RParen));
+                        return Ok(types::Expr::QueryExpr(Rc::new(query)))
+                    }
                     // Parse expr
                     let expr = handle!(self.expr());
                     // Expect rparen, pop it
@@ -750,6 +942,67 @@ pub mod parser {
                 }
             }
         }
+        // Fields and folds of a select
+        fn selectlist(&mut self) -> Result<Vec<types::SelectItem>, String> {
+            let mut items = vec![handle!(self.selectitem())];
+            while self.peek().kind == TokenKind::Comma {
+                // Pop comma
+                handle!(self.pop());
+                items.push(handle!(self.selectitem()))
+            }
+            Ok(items)
+        }
+        // Field, which may be qualified with its table, or fold (<expr> (INIT <expr>)? AS ((<type>))? <name>)
+        fn selectitem(&mut self) -> Result<types::SelectItem, String> {
+            let expr = handle!(self.expr());
+            let init = match self.peek().kind {
+                TokenKind::InitKw => {
+                    // Pop init keyword
+                    handle!(self.pop());
+                    Some(handle!(self.expr()))
+                },
+                _ => None
+            };
+            match self.peek().kind {
+                TokenKind::AsKw => {
+                    // Pop as keyword
+                    handle!(self.pop());
+                    // Check if type is declared
+                    let col_type = match self.peek().kind {
+                        TokenKind::LParen => {
+                            // Pop lparen
+                            handle!(self.pop());
+                            let col_type = handle!(self.parsetype());
+                            // Expect rparen
+                            handle!(self.pop_expect(TokenKind::RParen));
+                            Some(col_type)
+                        },
+                        _ => None
+                    };
+                    Ok(types::SelectItem::Fold(expr, init, col_type, handle!(self.ident())))
+                },
+                _ => match (expr, init) {
+                    (types::Expr::IdentExpr(name), None) => Ok(types::SelectItem::Field(name)),
+                    (types::Expr::BopExpr(t, types::BopType::DotBop, f), None) => match (t.as_ref(), f.as_ref()) {
+                        (types::Expr::IdentExpr(t), types::Expr::IdentExpr(f)) => Ok(types::SelectItem::Field(t.clone() + "." + f)),
+                        _ => perr!(self)
+                    },
+                    _ => perr!(self)
+                }
+            }
+        }
+        // Field name, or field qualified with its table like a.id
+        fn field(&mut self) -> Result<String, String> {
+            let name = handle!(self.ident());
+            match self.peek().kind {
+                TokenKind::Dot => {
+                    // Pop dot
+                    handle!(self.pop());
+                    Ok(name + "." + &handle!(self.ident()))
+                },
+                _ => Ok(name)
+            }
+        }
         fn ident(&mut self) -> Result<String, String> {
             // Extract string from ident
             match self.peek().kind {
@@ -857,7 +857,7 @@ mod parser_tests {
         // Assert correct AST
         match ast {
             // Should be exprscript
-            types::Query::Aggregate(_, _, _, _) => assert!(true),
+            types::Query::Aggregate(_, _, _, None, _) => assert!(true),
             _ => assert!(false)
         }
         Ok(())
@@ -885,7 +885,7 @@ mod parser_tests {
         // Assert correct AST
         match ast {
             // Should be exprscript
-            types::Query::CreateTable(name, _) => assert_eq!(name, "people"),
+            types::Query::CreateTable(name, _, None) => assert_eq!(name, "people"),
             _ => assert!(false)
         }
         Ok(())
@@ -1020,4 +1020,305 @@ mod parser_tests {
         }
         Ok(())
     }
+    #[test]
+    fn parser_delete_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "DELETE FROM table1 WHERE test1 > 5".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Delete(tname, Some(_)) => assert_eq!(tname, "table1"),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_delete_2() -> Result<(), String> {
+        // Setup
+        let test_input: String = "DELETE FROM table1".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Delete(tname, None) => assert_eq!(tname, "table1"),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_update_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "UPDATE table1 SET test1 = test1 + 1, test2 = 'a' WHERE test1 > 5".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Update(tname, sets, Some(_)) => {
+                assert_eq!(tname, "table1");
+                assert_eq!(sets.len(), 2);
+                assert_eq!(sets[0].0, "test1");
+                assert_eq!(sets[1].0, "test2")
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_update_2() -> Result<(), String> {
+        // Setup
+        let test_input: String = "UPDATE table1 SET test1 = null".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Update(tname, sets, None) => {
+                assert_eq!(tname, "table1");
+                assert_eq!(sets.len(), 1)
+            },
+            _ => assert!(false)
+        }
+        // Missing assignment is an error
+        let mut test_parser: Parser = Parser::new("UPDATE table1 SET WHERE test1".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_compress_auto() -> Result<(), String> {
+        // Setup
+        let test_input: String = "COMPRESS table1 AUTO".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Compress(tname, fields, strats) => {
+                assert_eq!(tname, "table1");
+                assert_eq!(fields.len(), 0);
+                assert!(strats == vec![types::CompressType::Auto])
+            },
+            _ => assert!(false)
+        }
+        // Auto also works as a regular strategy
+        let mut test_parser: Parser = Parser::new("CREATE TABLE table1 (test1 num auto, test2 str)".to_string());
+        match test_parser.parse().unwrap() {
+            types::Query::CreateTable(_, cols, _) => assert!(cols[0].2 == Some(types::CompressType::Auto)),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_show_storage() -> Result<(), String> {
+        // Setup
+        let test_input: String = "SHOW STORAGE table1".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::ShowStorage(tname) => assert_eq!(tname, "table1"),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_in_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "SELECT * FROM table1 WHERE genre IN ['Rock', 'Jazz']".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Select(_, _, Some(types::Expr::BopExpr(_, types::BopType::InBop, tup)), _, _, _) => match tup.as_ref() {
+                types::Expr::TupExpr(es) => assert_eq!(es.len(), 2),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_cluster_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "CREATE TABLE users (id num, active bool runlen) CLUSTER BY active".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::CreateTable(tname, cols, Some(key)) => {
+                assert_eq!(tname, "users");
+                assert_eq!(cols.len(), 2);
+                assert_eq!(key, "active")
+            },
+            _ => assert!(false)
+        }
+        // Cluster needs a field
+        let mut test_parser: Parser = Parser::new("CREATE TABLE users (id num) CLUSTER BY".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_attach_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "ATTACH 'archive.db' AS old_users".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Attach(path, tname) => {
+                assert_eq!(path, "archive.db");
+                assert_eq!(tname, "old_users")
+            },
+            _ => assert!(false)
+        }
+        // Attach needs a table name
+        let mut test_parser: Parser = Parser::new("ATTACH 'archive.db'".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_check_1() -> Result<(), String> {
+        // Setup
+        let mut test_parser: Parser = Parser::new("CHECK TABLE users".to_string());
+        // Assert correct AST
+        match test_parser.parse().unwrap() {
+            types::Query::CheckTable(tname) => assert_eq!(tname, "users"),
+            _ => assert!(false)
+        }
+        // Check needs the TABLE keyword
+        let mut test_parser: Parser = Parser::new("CHECK users".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_join_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "SELECT users.name, total FROM users JOIN orders ON users.id == orders.user LEFT JOIN ranks ON total > low ORDER BY orders.id".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Select(Some(fields), (tname, joins, None), None, Some((sort, _)), None, None) => {
+                match (&fields[0], &fields[1]) {
+                    (types::SelectItem::Field(f1), types::SelectItem::Field(f2)) => assert!(f1 == "users.name" && f2 == "total"),
+                    _ => assert!(false)
+                }
+                assert_eq!(tname, "users");
+                assert_eq!(joins.len(), 2);
+                assert!(joins[0].0 == types::JoinType::Inner && joins[1].0 == types::JoinType::Left);
+                assert_eq!(joins[1].1, "ranks");
+                match &joins[0].2 {
+                    types::Expr::BopExpr(e1, types::BopType::EqBop, _) => match e1.as_ref() {
+                        types::Expr::BopExpr(_, types::BopType::DotBop, _) => assert!(true),
+                        _ => assert!(false)
+                    },
+                    _ => assert!(false)
+                }
+                assert_eq!(sort, "orders.id")
+            },
+            _ => assert!(false)
+        }
+        // Joins need a condition
+        let mut test_parser: Parser = Parser::new("SELECT * FROM users JOIN orders".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_group_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "SELECT region, current + amount INIT amount AS sum, current + 1 AS count FROM sales WHERE amount > 0 GROUP BY region".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::SelectGroup(items, (tname, _, None), Some(_), key) => {
+                assert_eq!(items.len(), 3);
+                match (&items[0], &items[1], &items[2]) {
+                    (types::SelectItem::Field(f), types::SelectItem::Fold(_, Some(_), None, sum), types::SelectItem::Fold(_, None, None, count)) => assert!(f == "region" && sum == "sum" && count == "count"),
+                    _ => assert!(false)
+                }
+                assert_eq!(tname, "sales");
+                assert_eq!(key, "region")
+            },
+            _ => assert!(false)
+        }
+        // Folds need GROUP BY
+        let mut test_parser: Parser = Parser::new("SELECT current + 1 INIT 1 AS count FROM sales".to_string());
+        assert!(test_parser.parse().is_err());
+        // Grouped selects can't select every field
+        let mut test_parser: Parser = Parser::new("SELECT * FROM sales GROUP BY region".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_grouped_aggregate_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "CREATE AGGREGATE total = current + amount INIT amount GROUP BY region INTO sales".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Aggregate(name, _, Some(_), Some(group), tname) => assert!(name == "total" && group == "region" && tname == "sales"),
+            _ => assert!(false)
+        }
+        // Index a value by key
+        let mut test_parser: Parser = Parser::new("SELECT * FROM sales WHERE amount == total[region]".to_string());
+        match test_parser.parse().unwrap() {
+            types::Query::Select(_, _, Some(types::Expr::BopExpr(_, types::BopType::EqBop, e)), _, _, _) => match e.as_ref() {
+                types::Expr::BopExpr(_, types::BopType::IndexBop, _) => assert!(true),
+                _ => assert!(false)
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn parser_subquery_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "SELECT name FROM (SELECT * FROM products WHERE price > 5) AS pricey WHERE price > (SELECT AGGREGATE avg_price FROM other)".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Select(_, (name, _, Some(sub)), Some(types::Expr::BopExpr(_, types::BopType::GtBop, e)), _, _, _) => {
+                assert_eq!(name, "pricey");
+                match sub.as_ref() {
+                    types::Query::Select(None, (tname, _, None), Some(_), _, _, _) => assert_eq!(tname, "products"),
+                    _ => assert!(false)
+                }
+                match e.as_ref() {
+                    types::Expr::QueryExpr(q) => match q.as_ref() {
+                        types::Query::SelectAggregate(_, tname) => assert_eq!(tname, "other"),
+                        _ => assert!(false)
+                    },
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        // Selected subqueries need a name
+        let mut test_parser: Parser = Parser::new("SELECT * FROM (SELECT * FROM products)".to_string());
+        assert!(test_parser.parse().is_err());
+        // Only selects can be subqueries
+        let mut test_parser: Parser = Parser::new("SELECT * FROM (DELETE FROM products) AS d".to_string());
+        assert!(test_parser.parse().is_err());
+        Ok(())
+    }
+    #[test]
+    fn parser_project_1() -> Result<(), String> {
+        // Setup
+        let test_input: String = "SELECT name, price * 1.08 AS with_tax, &year + '-' AS (str) label FROM t".to_string();
+        let mut test_parser: Parser = Parser::new(test_input);
+        let ast = test_parser.parse().unwrap();
+        // Assert correct AST
+        match ast {
+            types::Query::Select(Some(fields), _, None, None, None, None) => {
+                assert_eq!(fields.len(), 3);
+                match (&fields[0], &fields[1], &fields[2]) {
+                    (types::SelectItem::Field(f), types::SelectItem::Project(types::Expr::BopExpr(_, types::BopType::TimesBop, _), None, tax), types::SelectItem::Project(_, Some(types::ColType::String), label)) => assert!(f == "name" && tax == "with_tax" && label == "label"),
+                    _ => assert!(false)
+                }
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,21 +1,30 @@
 pub mod types {
     use std::rc::Rc;
-    use crate::engine::script::env::Frame;
+    use crate::engine::script::env::{Frame, Groups};
+    #[derive(Clone)]
     pub enum Query {
-        Select(Option<IdentList>, String, Option<Expr>, Option<(String, SortType)>, Option<Expr>, Option<String>), // SELECT _ FROM _ WHERE _ SORT BY _ LIMIT _ EXPORT CSV _ (where, sort by, limit, and export are optional)
+        Select(Option<Vec<SelectItem>>, Source, Option<Expr>, Option<(String, SortType)>, Option<Expr>, Option<String>), // SELECT _ FROM (_ | (SELECT ...) AS _) (LEFT? JOIN _ ON _)* WHERE _ SORT BY _ LIMIT _ EXPORT CSV _ (joins, where, sort by, limit, and export are optional)
         Insert(String, Option<IdentList>, ExprList), // INSERT INTO _ (_, _, _)? VALUES (_, _, _)
         SelectAggregate(String, String), // SELECT AGGREGATE <name> FROM <table>
         Const(String, Expr), // CONST <name> = <value>
-        Aggregate(String, Expr, Option<Expr>, String), // AGGREGATE <name> = <value> INIT _ INTO <table>
+        Aggregate(String, Expr, Option<Expr>, Option<String>, String), // AGGREGATE <name> = <value> INIT _ (GROUP BY <field>)? INTO <table>
         Column(ColType, Option<CompressType>, String, Expr, String), // COLUMN (type comp?) <name> = <value> INTO <table>
-        CreateTable(String, ColList), // CREATE TABLE <name> (col1 type1 comp1?, col2 type2 comp2?, ...)
+        CreateTable(String, ColList, Option<String>), // CREATE TABLE <name> (col1 type1 comp1?, col2 type2 comp2?, ...) (CLUSTER BY <field>)?
         Comp(String, Expr, String), // CREATE COMP <name> = <value> INTO <table>
         SelectComp(String, String), // SELECT COMP <name> FROM <table>
-        Compress(String, IdentList, CompressList), // COMPRESS <table> (<field>, <field>, ...) ((<strategy>, <strategy>, ...) | <strategy>)
+        Compress(String, IdentList, CompressList), // COMPRESS <table> ((<field>, <field>, ...) ((<strategy>, <strategy>, ...) | <strategy>) | AUTO), AUTO has no fields and means every field
         Script(Expr, Option<String>), // SCRIPT <expr> (FROM <table>)?
         Exit, // EXIT
         ImportCSV(String, String), // IMPORT CSV <path> INTO <table>
         ExportCSV(String, String), // EXPORT CSV <path> FROM <table>
+        Save(Option<String>), // SAVE <path>?
+        Load(String), // LOAD <path>
+        Delete(String, Option<Expr>), // DELETE FROM <table> (WHERE <expr>)?
+        Update(String, Vec<(String, Expr)>, Option<Expr>), // UPDATE <table> SET <field> = <expr>, ... (WHERE <expr>)?
+        ShowStorage(String), // SHOW STORAGE <table>
+        Attach(String, String), // ATTACH <path> AS <table>
+        CheckTable(String), // CHECK TABLE <table>
+        SelectGroup(Vec<SelectItem>, Source, Option<Expr>, String), // SELECT <field or fold>, ... FROM _ (LEFT? JOIN _ ON _)* WHERE _ GROUP BY <field> (joins and where are optional)
     }
     #[derive(Clone)]
     pub enum Expr {
@@ -27,7 +36,8 @@ pub mod types {
         CallExpr(Rc<Expr>, ExprList),
         FunExpr(IdentList, Rc<Expr>),
         CondExpr(Rc<Expr>, Rc<Expr>, Rc<Expr>), // if _ then _ else _
-        TupExpr(ExprList)
+        TupExpr(ExprList),
+        QueryExpr(Rc<Query>) // (SELECT ...), run before the query it is in
     }
     #[derive(Clone)]
     pub enum Block {
@@ -42,7 +52,8 @@ pub mod types {
         UndefVal,
         NullVal,
         ClosureVal(Frame, IdentList, Rc<Expr>),
-        TupVal(Vec<Rc<Val>>)
+        TupVal(Vec<Rc<Val>>),
+        GroupVal(Rc<Groups>) // Value of a grouped aggregate
     }
     #[derive(PartialEq, Debug, Clone, Copy)]
     pub enum BopType {
@@ -59,11 +70,15 @@ pub mod types {
         LogOrBop,
         LogAndBop,
         ModBop,
-        DotBop
+        DotBop,
+        InBop,
+        IndexBop // <grouped aggregate or tuple of [key, value] pairs>[<key>]
     }
     pub type ColList = Vec<(String, ColType, Option<CompressType>)>;
     pub type ExprList = Vec<Rc<Expr>>;
     pub type IdentList = Vec<String>;
+    pub type Join = (JoinType, String, Expr); // LEFT? JOIN <table> ON <expr>
+    pub type Source = (String, Vec<Join>, Option<Rc<Query>>); // Table selected from (or the name of the subquery selected from), the tables joined to it, and the subquery
     #[derive(PartialEq, Debug, Clone, Copy)]
     pub enum UopType {
         NegUop,
@@ -85,12 +100,28 @@ pub mod types {
         Ascending,
         Descending
     }
+    #[derive(Clone)]
+    pub enum SelectItem {
+        Field(String),
+        Fold(Expr, Option<Expr>, Option<ColType>, String), // <expr> (INIT <expr>)? AS ((<type>))? <name>
+        Project(Expr, Option<ColType>, String) // <expr> AS ((<type>))? <name> outside of GROUP BY, the type is inferred when not given
+    }
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum JoinType {
+        Inner,
+        Left // Rows of the left side without a match are kept, with nulls for the right side
+    }
     #[derive(Clone, Copy, PartialEq)]
     pub enum CompressType {
         Uncompressed,
         Xor,
         RunLength,
-        BitMap
+        BitMap,
+        Dict,
+        ForPack,
+        Delta,
+        Alp,
+        Auto // Whichever of the others is estimated to be smallest
     }
     pub type CompressList = Vec<CompressType>;
 
@@ -99,7 +130,19 @@ pub mod types {
             CompressType::Uncompressed => "none".to_string(),
             CompressType::RunLength => "runlen".to_string(),
             CompressType::BitMap => "bitmap".to_string(),
-            CompressType::Xor => "xor".to_string()
+            CompressType::Xor => "xor".to_string(),
+            CompressType::Dict => "dict".to_string(),
+            CompressType::ForPack => "forpack".to_string(),
+            CompressType::Delta => "delta".to_string(),
+            CompressType::Alp => "alp".to_string(),
+            CompressType::Auto => "auto".to_string()
+        }
+    }
+    pub fn str_of_coltype(t: ColType) -> String {
+        match t {
+            ColType::Number => "num".to_string(),
+            ColType::String => "str".to_string(),
+            ColType::Boolean => "bool".to_string()
         }
     }
 }
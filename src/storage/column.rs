@@ -1,9 +1,215 @@
 pub mod generic {
     use bitvec::prelude::*;
-    pub trait ColumnInterface<T: Clone> {
-        fn insert(&mut self, data: Option<T>) -> ();
+    use std::collections::HashMap;
+    use std::hash::Hash;
+    use std::mem::size_of;
+    use crate::sqlscript::types::types::{ ColType, CompressType, BopType, str_of_ctype };
+    use super::super::persist::persist::{ Persist, Reader, checksum };
+    use super::super::roaring::roaring::{ Roaring, RoaringView };
+    use super::super::mapped::mapped::MappedFile;
+    use std::rc::Rc;
+    use std::marker::PhantomData;
+
+    macro_rules! handle{
+        ($e:expr) => {
+            (match $e { Ok(v) => v, Err(s) => return Err(s) })
+        }
+    }
+
+    fn check_index(idx: usize, len: usize) -> Result<(), String> {
+        if idx >= len { return Err(format!("Index {} out of bounds for column of length {}", idx, len)) }
+        Ok(())
+    }
+    fn check_range(start: usize, end: usize, len: usize) -> Result<(), String> {
+        if start > end || end > len { return Err(format!("Invalid range {}..{} for column of length {}", start, end, len)) }
+        Ok(())
+    }
+    // Rows between checkpoints in encodings that can only be decoded sequentially
+    const CHECKPOINT_ROWS: usize = 64;
+    // Encoded bytes of a column value
+    fn value_bytes<T: Persist>(x: &T) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        handle!(x.write(&mut out));
+        Ok(out)
+    }
+    // Memory a value owns outside of itself
+    pub trait HeapSize {
+        fn heap_size(&self) -> usize { 0 }
+    }
+    impl HeapSize for f64 {}
+    impl HeapSize for bool {}
+    impl HeapSize for String {
+        fn heap_size(&self) -> usize { self.len() }
+    }
+    // Memory taken up by the words backing a bitvector
+    fn bitvec_bytes(bits: &BitVec) -> usize {
+        std::mem::size_of_val(bits.as_raw_slice())
+    }
+    // Bits that encodings pack rows into, held in memory or read in place from a mapped file
+    trait Bits {
+        fn bit_len(&self) -> usize;
+        fn bit(&self, i: usize) -> bool;
+    }
+    impl Bits for BitVec {
+        fn bit_len(&self) -> usize { self.len() }
+        fn bit(&self, i: usize) -> bool { self[i] }
+    }
+    impl<B: Bits> Bits for &B {
+        fn bit_len(&self) -> usize { (*self).bit_len() }
+        fn bit(&self, i: usize) -> bool { (*self).bit(i) }
+    }
+    // Bitvector read in place from the bytes it was written to
+    #[derive(Clone, Copy)]
+    struct PackedBits<'a> {
+        bytes: &'a [u8],
+        len: usize
+    }
+    impl<'a> PackedBits<'a> {
+        fn read(r: &mut Reader<'a>) -> Result<PackedBits<'a>, String> {
+            let len = handle!(usize::read(r));
+            let bytes = handle!(r.take(len.div_ceil(8)));
+            Ok(PackedBits { bytes, len })
+        }
+        fn count_ones(&self, start: usize, end: usize) -> usize {
+            (start..end).filter(|i| self.bit(*i)).count()
+        }
+    }
+    impl Bits for PackedBits<'_> {
+        fn bit_len(&self) -> usize { self.len }
+        // Bits past the end read as zero, so corrupt offsets give wrong values rather than a crash
+        fn bit(&self, i: usize) -> bool { self.bytes.get(i / 8).is_some_and(|b| (b >> (i % 8)) & 1 == 1) }
+    }
+    // Values of an encoding read in place from the bytes it was saved to, decoded only as they're asked for
+    pub trait ValuesView<'a, T> {
+        // Values from the one at start on, ending early if the bytes turn out to be corrupt
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<T>> + 'a>;
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            match self.iter_from(idx).next() {
+                Some(x) => Ok(x),
+                None => Err("Corrupt mapped column".to_string())
+            }
+        }
+    }
+    // Rows summarized by each zone of a zone map
+    pub const ZONE_ROWS: usize = 1024;
+    // Smallest value, largest value and number of nulls of ZONE_ROWS consecutive rows
+    pub struct Zone<T> {
+        pub min: Option<T>, // None if the zone has no comparable values
+        pub max: Option<T>,
+        pub nulls: usize
+    }
+    // Statistics kept for every zone of a column, so zones that can't match a comparison can be skipped
+    pub struct ZoneMap<T> {
+        zones: Vec<Zone<T>>,
+        len: usize
+    }
+    impl<T> ZoneMap<T> {
+        pub fn new() -> ZoneMap<T> {
+            ZoneMap { zones: Vec::new(), len: 0 }
+        }
+        // Forget every row from row len on, len has to be at the start of a zone
+        pub fn truncate(&mut self, len: usize) {
+            self.zones.truncate(len / ZONE_ROWS);
+            self.len = len;
+        }
+    }
+    impl<T: Clone + PartialOrd + Persist> ZoneMap<T> {
+        // Write the zones of rows start to end, start has to be at the start of a zone
+        fn write_range(&self, start: usize, end: usize, out: &mut Vec<u8>) -> Result<(), String> {
+            let zones = &self.zones[start / ZONE_ROWS..end.div_ceil(ZONE_ROWS)];
+            handle!(zones.len().write(out));
+            for zone in zones {
+                handle!(zone.min.write(out));
+                handle!(zone.max.write(out));
+                handle!(zone.nulls.write(out))
+            }
+            Ok(())
+        }
+        // Read back the zones of len more rows, written by write_range
+        fn read_range(&mut self, len: usize, r: &mut Reader) -> Result<(), String> {
+            let num_zones = handle!(usize::read(r));
+            if !self.len.is_multiple_of(ZONE_ROWS) || num_zones != len.div_ceil(ZONE_ROWS) { return Err("Corrupt database file: bad zone map length".to_string()) }
+            for _ in 0..num_zones {
+                let min = handle!(Option::<T>::read(r));
+                let max = handle!(Option::<T>::read(r));
+                self.zones.push(Zone { min, max, nulls: handle!(usize::read(r)) })
+            }
+            self.len += len;
+            Ok(())
+        }
+    }
+    impl<T: Clone + PartialOrd> ZoneMap<T> {
+        // Recompute the zones of rows from start on, for as many rows as there are values; start has to be at the start of a zone
+        pub fn rebuild(&mut self, start: usize, values: impl Iterator<Item=Option<T>>) {
+            let mut rebuilt = ZoneMap::new();
+            for item in values {
+                rebuilt.insert(&item)
+            }
+            let first = start / ZONE_ROWS;
+            self.zones.splice(first..first + rebuilt.zones.len(), rebuilt.zones);
+        }
+        pub fn insert(&mut self, data: &Option<T>) {
+            if self.len.is_multiple_of(ZONE_ROWS) { self.zones.push(Zone { min: None, max: None, nulls: 0 }) }
+            self.len += 1;
+            let zone = self.zones.last_mut().unwrap();
+            match data {
+                None => zone.nulls += 1,
+                // Values that aren't comparable with themselves (NaN) never satisfy a comparison, so they're left out
+                Some(x) if x.partial_cmp(x).is_none() => (),
+                Some(x) => {
+                    if zone.min.as_ref().is_none_or(|min| x < min) { zone.min = Some(x.clone()) }
+                    if zone.max.as_ref().is_none_or(|max| x > max) { zone.max = Some(x.clone()) }
+                }
+            }
+        }
+        // Whether any row in the zone could satisfy (value op c), given whether a null row would
+        pub fn may_match(&self, zone_idx: usize, op: BopType, c: &T, null_matches: bool) -> bool {
+            let zone = &self.zones[zone_idx];
+            if zone.nulls > 0 && null_matches { return true }
+            let (min, max) = match (&zone.min, &zone.max) {
+                (Some(min), Some(max)) => (min, max),
+                _ => return !matches!(op, BopType::GtBop | BopType::GteBop | BopType::LtBop | BopType::LteBop | BopType::EqBop | BopType::StrEqBop)
+            };
+            match op {
+                BopType::GtBop => max > c,
+                BopType::GteBop => max >= c,
+                BopType::LtBop => min < c,
+                BopType::LteBop => min <= c,
+                BopType::EqBop | BopType::StrEqBop => min <= c && c <= max,
+                _ => true
+            }
+        }
+    }
+    // Reading the rows of a column, which every column supports, including those left in a mapped file
+    pub trait ReadColumn<T: Clone + PartialEq> {
         fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a>;
         fn len(&self) -> usize;
+        // Value at row idx
+        fn get(&self, idx: usize) -> Result<Option<T>, String>;
+        // Values of rows start to end (exclusive)
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.len()));
+            let mut data = Vec::new();
+            for i in start..end {
+                data.push(handle!(self.get(i)))
+            };
+            Ok(data)
+        }
+        // Write the column in its encoded form
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String>;
+        // Memory taken up by the encoded column, including any indexes kept for random access
+        fn size_in_bytes(&self) -> usize;
+        // Rows whose value is one of values, nulls never match
+        fn select_in(&self, values: &[T]) -> BitVec {
+            self.iter().map(|x| x.is_some_and(|x| values.contains(&x))).collect()
+        }
+        // Rows that are null
+        fn nulls(&self) -> BitVec {
+            self.iter().map(|x| x.is_none()).collect()
+        }
+        fn null_count(&self) -> usize {
+            self.iter().filter(|x| x.is_none()).count()
+        }
         #[allow(dead_code)]
         // Used for testing
         fn uncompress(&self) -> Vec<Option<T>> {
@@ -14,6 +220,15 @@ pub mod generic {
             data
         }
     }
+    // Encodings held in memory, which rows can be added to and changed
+    pub trait ColumnInterface<T: Clone + PartialEq>: ReadColumn<T> {
+        fn insert(&mut self, data: Option<T>) -> Result<(), String>;
+        // Overwrite the value of row idx in place, false if the encoding can't and has to be rebuilt instead
+        fn set(&mut self, idx: usize, _data: Option<T>) -> Result<bool, String> {
+            handle!(check_index(idx, self.len()));
+            Ok(false)
+        }
+    }
     pub struct Uncompressed<T: Clone> {
         data: Vec<Option<T>>
     }
@@ -22,10 +237,13 @@ pub mod generic {
             Uncompressed{ data: Vec::new() }
         }
     }
-    impl<T: Clone> ColumnInterface<T> for Uncompressed<T> {
-        fn insert(&mut self, data: Option<T>) -> () {
-            self.data.push(data)
-        } 
+    impl<T: Clone + PartialOrd + Persist + HeapSize> ReadColumn<T> for Uncompressed<T> {
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.data.len() * size_of::<Option<T>>() + self.data.iter().flatten().map(|x| x.heap_size()).sum::<usize>()
+        }
         fn iter<'a>(&'a self) -> Box<(dyn Iterator<Item = Option<T>> + 'a)>{
             Box::new(UncompressedIterator {
                 column: self,
@@ -35,6 +253,34 @@ pub mod generic {
         fn len(&self) -> usize {
             self.data.len()
         }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.data.len()));
+            Ok(self.data[idx].clone())
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.data.len()));
+            Ok(self.data[start..end].to_vec())
+        }
+    }
+    impl<T: Clone + PartialOrd + Persist + HeapSize> ColumnInterface<T> for Uncompressed<T> {
+        fn insert(&mut self, data: Option<T>) -> Result<(), String> {
+            self.data.push(data);
+            Ok(())
+        }
+        fn set(&mut self, idx: usize, data: Option<T>) -> Result<bool, String> {
+            handle!(check_index(idx, self.data.len()));
+            self.data[idx] = data;
+            Ok(true)
+        }
+    }
+    impl<T: Clone + PartialOrd + Persist> Persist for Uncompressed<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.data.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let data: Vec<Option<T>> = handle!(Vec::read(r));
+            Ok(Uncompressed { data })
+        }
     }
     struct UncompressedIterator<'a, T: Clone> {
         column: &'a Uncompressed<T>,
@@ -43,7 +289,7 @@ pub mod generic {
     impl<'a, T: Clone> Iterator for UncompressedIterator<'a, T> {
         type Item = Option<T>;
         fn next(&mut self) -> Option<Self::Item> {
-            if self.index >= self.column.len() {
+            if self.index >= self.column.data.len() {
                 None
             } else {
                 let data = Some(self.column.data[self.index].clone());
@@ -52,26 +298,44 @@ pub mod generic {
             }
         }
     }
+    // Uncompressed values read in place, one after another
+    struct UncompressedView<'a, T> {
+        items: &'a [u8],
+        len: usize,
+        values: PhantomData<T>
+    }
+    impl<'a, T> UncompressedView<'a, T> {
+        fn read(bytes: &'a [u8], len: usize) -> Result<UncompressedView<'a, T>, String> {
+            let mut r = Reader::new(bytes);
+            if handle!(usize::read(&mut r)) != len { return Err("Corrupt database file: bad uncompressed column size".to_string()) }
+            Ok(UncompressedView { items: r.rest(), len, values: PhantomData })
+        }
+    }
+    impl<'a, T: Persist + 'static> ValuesView<'a, T> for UncompressedView<'a, T> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            let mut r = Reader::new(self.items);
+            for _ in 0..start {
+                if Option::<T>::skip(&mut r).is_err() { return Box::new(std::iter::empty()) }
+            }
+            Box::new(std::iter::from_fn(move || Option::<T>::read(&mut r).ok()).take(self.len.saturating_sub(start)))
+        }
+    }
     pub struct BoolCol {
-        data: BitVec,
-        len: usize
+        validity: BitVec, // Whether each row has a value
+        data: BitVec // Value of each row, false for nulls
     }
     impl BoolCol {
         pub fn new() -> BoolCol {
-            BoolCol{ data: BitVec::new(), len: 0 }
+            BoolCol{ validity: BitVec::new(), data: BitVec::new() }
         }
     }
-    impl ColumnInterface<bool> for BoolCol {
-        fn insert(&mut self, data: Option<bool>) -> () {
-            match data {
-                None => self.data.push(false),
-                Some(x) => {
-                    self.data.push(true);
-                    self.data.push(x)
-                }
-            }
-            self.len += 1;
-        } 
+    impl ReadColumn<bool> for BoolCol {
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            bitvec_bytes(&self.validity) + bitvec_bytes(&self.data)
+        }
         fn iter<'a>(&'a self) -> Box<(dyn Iterator<Item = Option<bool>> + 'a)>{
             Box::new(BoolColIterator {
                 column: self,
@@ -79,7 +343,36 @@ pub mod generic {
             })
         }
         fn len(&self) -> usize {
-            self.len
+            self.validity.len()
+        }
+        fn get(&self, idx: usize) -> Result<Option<bool>, String> {
+            handle!(check_index(idx, self.validity.len()));
+            Ok(if self.validity[idx] { Some(self.data[idx]) } else { None })
+        }
+    }
+    impl ColumnInterface<bool> for BoolCol {
+        fn insert(&mut self, data: Option<bool>) -> Result<(), String> {
+            self.validity.push(data.is_some());
+            self.data.push(data.unwrap_or(false));
+            Ok(())
+        }
+        fn set(&mut self, idx: usize, data: Option<bool>) -> Result<bool, String> {
+            handle!(check_index(idx, self.validity.len()));
+            self.validity.set(idx, data.is_some());
+            self.data.set(idx, data.unwrap_or(false));
+            Ok(true)
+        }
+    }
+    impl Persist for BoolCol {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.validity.write(out));
+            self.data.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let validity = handle!(BitVec::read(r));
+            let data = handle!(BitVec::read(r));
+            if validity.len() != data.len() { return Err("Corrupt database file: bad boolean column size".to_string()) }
+            Ok(BoolCol { validity, data })
         }
     }
     struct BoolColIterator<'a> {
@@ -89,24 +382,41 @@ pub mod generic {
     impl<'a> Iterator for BoolColIterator<'a> {
         type Item = Option<bool>;
         fn next(&mut self) -> Option<Self::Item> {
-            if self.index >= self.column.data.len() {
+            if self.index >= self.column.validity.len() {
                 None
             } else {
-                let control = self.column.data[self.index];
+                let retval = match self.column.validity[self.index] {
+                    false => None,
+                    true => Some(self.column.data[self.index])
+                };
                 self.index += 1;
-                match control {
-                    false => Some(None),
-                    true => {
-                        let retval = Some(Some(self.column.data[self.index]));
-                        self.index += 1;
-                        retval
-                    }
-                }
+                Some(retval)
             }
         }
     }
+    // Boolean column read in place, rows are looked up directly in both bitvectors
+    struct BoolView<'a> {
+        validity: PackedBits<'a>,
+        data: PackedBits<'a>
+    }
+    impl<'a> BoolView<'a> {
+        fn read(bytes: &'a [u8], len: usize) -> Result<BoolView<'a>, String> {
+            let mut r = Reader::new(bytes);
+            let validity = handle!(PackedBits::read(&mut r));
+            let data = handle!(PackedBits::read(&mut r));
+            if validity.len != len || data.len != len { return Err("Corrupt database file: bad boolean column size".to_string()) }
+            Ok(BoolView { validity, data })
+        }
+    }
+    impl<'a> ValuesView<'a, bool> for BoolView<'a> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<bool>> + 'a> {
+            let (validity, data) = (self.validity, self.data);
+            Box::new((start..validity.len).map(move |i| if validity.bit(i) { Some(data.bit(i)) } else { None }))
+        }
+    }
     pub struct RunLength<T: Clone + PartialEq> {
         data: Vec<(Option<T>, usize)>,
+        ends: Vec<usize>, // Row index just past the end of each run
         len: usize,
         size: usize
     }
@@ -114,49 +424,138 @@ pub mod generic {
         pub fn new() -> RunLength<T> {
             RunLength{
                 data: Vec::new(),
+                ends: Vec::new(),
                 len: 0,
                 size: 0
             }
         }    
+        // Index of run containing row idx
+        fn run_of(&self, idx: usize) -> usize {
+            self.ends.partition_point(|end| *end <= idx)
+        }
+    }
+    impl<T: Clone + PartialOrd + Persist + HeapSize> ReadColumn<T> for RunLength<T> {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            Box::new(RunLengthIterator {
+                column: self,
+                index: 0,
+                pos: 0
+            })
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            Ok(self.data[self.run_of(idx)].0.clone())
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.len));
+            if start == end { return Ok(Vec::new()) }
+            // Start partway through the run containing start
+            let run = self.run_of(start);
+            let iter = RunLengthIterator {
+                column: self,
+                index: run,
+                pos: start - (self.ends[run] - self.data[run].1)
+            };
+            Ok(iter.take(end - start).collect())
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.data.len() * size_of::<(Option<T>, usize)>() + self.data.iter().filter_map(|run| run.0.as_ref()).map(|x| x.heap_size()).sum::<usize>() + self.ends.len() * size_of::<usize>()
+        }
+        fn select_in(&self, values: &[T]) -> BitVec {
+            // Each run matches or doesn't as a whole
+            let mut rows = BitVec::with_capacity(self.len);
+            for (x, n) in &self.data {
+                rows.resize(rows.len() + n, x.as_ref().is_some_and(|x| values.contains(x)))
+            }
+            rows
+        }
     }
-    impl<T: Clone + PartialEq> ColumnInterface<T> for RunLength<T> {
-        fn insert(&mut self, data: Option<T>) -> () {
+    impl<T: Clone + PartialOrd + Persist + HeapSize> ColumnInterface<T> for RunLength<T> {
+        fn insert(&mut self, data: Option<T>) -> Result<(), String> {
             // If no data yet, push new tuple
             if self.len == 0 {
                 self.data.push((data, 1));
+                self.ends.push(1);
                 self.size += 1;
             } 
             // Otherwise, compare inserted value to most recent tuple
             else {
                 match (&data, &self.data[self.size - 1].0) {
                     (None, None) => {
-                        self.data[self.size - 1].1 += 1
+                        self.data[self.size - 1].1 += 1;
+                        self.ends[self.size - 1] += 1
                     },
                     (Some(x), Some(y)) => {
                         if x == y { 
-                            self.data[self.size - 1].1 += 1 
+                            self.data[self.size - 1].1 += 1;
+                            self.ends[self.size - 1] += 1
                         } else { 
                             self.data.push((data, 1));
+                            self.ends.push(self.len + 1);
                             self.size += 1;
                         }
                     },
                     (Some(_), None) | (None, Some(_)) => {
                         self.data.push((data, 1));
+                        self.ends.push(self.len + 1);
                         self.size += 1;
                     }
                 }
             }
             self.len += 1;
+            Ok(())
         }
-        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
-            Box::new(RunLengthIterator {
-                column: self,
-                index: 0,
-                pos: 0
-            })
+        fn set(&mut self, idx: usize, data: Option<T>) -> Result<bool, String> {
+            handle!(check_index(idx, self.len));
+            let run = self.run_of(idx);
+            if self.data[run].0 == data { return Ok(true) }
+            // Split the run around idx
+            let run_start = self.ends[run] - self.data[run].1;
+            let (before, after) = (idx - run_start, self.ends[run] - idx - 1);
+            let mut pieces = Vec::new();
+            if before > 0 { pieces.push((self.data[run].0.clone(), before)) }
+            pieces.push((data, 1));
+            if after > 0 { pieces.push((self.data[run].0.clone(), after)) }
+            // The new value joins neighbouring runs of the same value
+            let (mut first, mut last) = (run, run + 1);
+            if before == 0 && run > 0 && self.data[run - 1].0 == pieces[0].0 {
+                first -= 1;
+                pieces[0].1 += self.data[first].1
+            }
+            let end = pieces.len() - 1;
+            if after == 0 && last < self.size && self.data[last].0 == pieces[end].0 {
+                pieces[end].1 += self.data[last].1;
+                last += 1
+            }
+            let mut pos = self.ends[first] - self.data[first].1;
+            let ends: Vec<usize> = pieces.iter().map(|p| { pos += p.1; pos }).collect();
+            self.data.splice(first..last, pieces);
+            self.ends.splice(first..last, ends);
+            self.size = self.data.len();
+            Ok(true)
         }
-        fn len(&self) -> usize {
-            self.len
+    }
+    impl<T: Clone + PartialOrd + Persist> Persist for RunLength<T> {
+        // Only the runs are written, length and size are derived from them
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.data.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let data: Vec<(Option<T>, usize)> = handle!(Vec::read(r));
+            let mut ends = Vec::new();
+            let mut len = 0;
+            for run in &data {
+                len += run.1;
+                ends.push(len)
+            }
+            let size = data.len();
+            Ok(RunLength { data, ends, len, size })
         }
     }
     struct RunLengthIterator<'a, T: Clone + PartialEq> {
@@ -185,81 +584,312 @@ pub mod generic {
             }
         }
     }
+    // Runs read in place, the value of a run is only decoded once a row of it is needed
+    struct RunLengthView<'a, T> {
+        runs: &'a [u8],
+        num_runs: usize,
+        values: PhantomData<T>
+    }
+    impl<'a, T> RunLengthView<'a, T> {
+        fn read(bytes: &'a [u8]) -> Result<RunLengthView<'a, T>, String> {
+            let mut r = Reader::new(bytes);
+            let num_runs = handle!(usize::read(&mut r));
+            Ok(RunLengthView { runs: r.rest(), num_runs, values: PhantomData })
+        }
+    }
+    impl<'a, T: Clone + Persist + 'static> ValuesView<'a, T> for RunLengthView<'a, T> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            let mut r = Reader::new(self.runs);
+            let mut runs_left = self.num_runs;
+            // Skip whole runs before start
+            let mut skipped = 0;
+            let mut value: Option<T> = None;
+            let mut rows_left = 0;
+            while runs_left > 0 {
+                let mut value_r = r.clone();
+                if Option::<T>::skip(&mut r).is_err() { return Box::new(std::iter::empty()) }
+                let n = match usize::read(&mut r) {
+                    Ok(n) => n,
+                    Err(_) => return Box::new(std::iter::empty())
+                };
+                runs_left -= 1;
+                if skipped + n > start {
+                    // The run holding start, which is the first value decoded
+                    value = match Option::<T>::read(&mut value_r) {
+                        Ok(x) => x,
+                        Err(_) => return Box::new(std::iter::empty())
+                    };
+                    rows_left = skipped + n - start;
+                    break
+                }
+                skipped += n
+            }
+            Box::new(std::iter::from_fn(move || {
+                while rows_left == 0 {
+                    if runs_left == 0 { return None }
+                    value = Option::<T>::read(&mut r).ok()?;
+                    rows_left = usize::read(&mut r).ok()?;
+                    runs_left -= 1
+                }
+                rows_left -= 1;
+                Some(value.clone())
+            }))
+        }
+    }
+    // Rows decoded at a time when iterating through a bitmap column
+    const BITMAP_CHUNK_ROWS: usize = 4096;
     pub struct BitMap<T: Clone + PartialEq> {
-        data: Vec<(T, BitVec)>,
-        len: usize,
-        size: usize
+        data: Vec<(T, Roaring)>, // Rows holding each distinct value
+        index: HashMap<Vec<u8>, usize>, // Position in data of each value, keyed by its encoded bytes so that floats can be looked up too
+        len: usize
     }
     impl<T: Clone + PartialEq> BitMap<T> {
         pub fn new() -> BitMap<T> {
             BitMap {
                 data: Vec::new(),
-                len: 0,
-                size: 0
+                index: HashMap::new(),
+                len: 0
             }
         }
     }
-    impl<T: Clone + PartialEq> ColumnInterface<T> for BitMap<T> {
-        fn insert(&mut self, data: Option<T>) -> () {
-            // If no data yet, push new tuple if not pushing null
-            if self.len == 0 {
-                match data {
-                    Some(x) => {
-                        let mut new_vec = BitVec::new();
-                        new_vec.push(true);
-                        self.data.push((x, new_vec));
-                        self.size += 1;
-                    },
-                    None => ()
+    impl<T: Clone + PartialOrd + Persist + HeapSize> ReadColumn<T> for BitMap<T> {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            Box::new(BitMapIterator {
+                column: self,
+                index: 0,
+                chunk: Vec::new().into_iter()
+            })
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            // Value whose bitmap is set at idx, if any
+            Ok(self.data.iter().find(|r| r.1.contains(idx as u32)).map(|r| r.0.clone()))
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.len));
+            // Fill in the rows of each value within the range
+            let mut data = vec![None; end - start];
+            for (x, rows) in &self.data {
+                for i in rows.iter_range(start as u32, end as u32) {
+                    data[i as usize - start] = Some(x.clone())
                 }
-            } 
-            // Otherwise, compare inserted value to existing tuples
-            else {
-                match data {
-                    // If pushing null, push false to everything
-                    None => {
-                        for i in 0..self.size {
-                            self.data[i].1.push(false)
-                        }
-                    },
-                    Some(x) => {
-                        // Locate data in locations
-                        let idx = self.data.iter().position(|r| r.0 == x);
-                        // Does idx exist?
-                        match idx {
-                            Some(i) => {
-                                // Push true to matched value
-                                self.data[i].1.push(true);
-                                // Push false to every other value
-                                for j in 0..self.size {
-                                    if j != i { self.data[j].1.push(false) }
-                                }
-                            },
-                            None => {
-                                // Add new value
-                                self.data.push((x, BitVec::new()));
-                                // Push false to every other value
-                                for j in 0..self.size { 
-                                    self.data[j].1.push(false);
-                                }
-                                // Push self.len falses to new data
-                                for _ in 0..self.len {
-                                    self.data[self.size].1.push(false)
-                                }
-                                // Push one true to new data's bitmap
-                                self.data[self.size].1.push(true);
-                                // Increment size
-                                self.size += 1;
-                            }
-                        }
+            }
+            Ok(data)
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            let values: usize = self.data.iter().map(|(x, rows)| size_of::<(T, Roaring)>() + x.heap_size() + rows.size_in_bytes()).sum();
+            let index: usize = self.index.keys().map(|key| size_of::<(Vec<u8>, usize)>() + key.len()).sum();
+            values + index
+        }
+        fn select_in(&self, values: &[T]) -> BitVec {
+            // Union of the bitmaps of every matching value
+            let mut rows = bitvec![0; self.len];
+            for (x, set) in &self.data {
+                if values.contains(x) {
+                    for i in set.iter() {
+                        rows.set(i as usize, true)
                     }
                 }
             }
-            // Increment length
+            rows
+        }
+    }
+    impl<T: Clone + PartialOrd + Persist + HeapSize> ColumnInterface<T> for BitMap<T> {
+        fn insert(&mut self, data: Option<T>) -> Result<(), String> {
+            // Nulls are rows missing from every bitmap
+            if let Some(x) = data {
+                let key = handle!(value_bytes(&x));
+                let pos = match self.index.get(&key) {
+                    Some(pos) => *pos,
+                    None => {
+                        self.data.push((x, Roaring::new()));
+                        self.index.insert(key, self.data.len() - 1);
+                        self.data.len() - 1
+                    }
+                };
+                self.data[pos].1.push(self.len as u32)
+            }
             self.len += 1;
+            Ok(())
+        }
+        fn set(&mut self, idx: usize, data: Option<T>) -> Result<bool, String> {
+            handle!(check_index(idx, self.len));
+            // Move the row out of the bitmap of its old value, dropping the value once no row holds it
+            if let Some(old) = self.data.iter().position(|r| r.1.contains(idx as u32)) {
+                self.data[old].1.remove(idx as u32);
+                if self.data[old].1.len() == 0 {
+                    self.index.remove(&handle!(value_bytes(&self.data[old].0)));
+                    self.data.swap_remove(old);
+                    if old < self.data.len() { self.index.insert(handle!(value_bytes(&self.data[old].0)), old); }
+                }
+            }
+            // And into the bitmap of its new value
+            if let Some(x) = data {
+                let key = handle!(value_bytes(&x));
+                let pos = match self.index.get(&key) {
+                    Some(pos) => *pos,
+                    None => {
+                        self.data.push((x, Roaring::new()));
+                        self.index.insert(key, self.data.len() - 1);
+                        self.data.len() - 1
+                    }
+                };
+                self.data[pos].1.insert(idx as u32)
+            }
+            Ok(true)
+        }
+    }
+    impl<T: Clone + PartialOrd + Persist + HeapSize> Persist for BitMap<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.data.len().write(out));
+            for (x, rows) in &self.data {
+                handle!(x.write(out));
+                handle!(rows.write(out))
+            }
+            self.len.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let mut col = BitMap::new();
+            for _ in 0..handle!(usize::read(r)) {
+                let x = handle!(T::read(r));
+                col.index.insert(handle!(value_bytes(&x)), col.data.len());
+                col.data.push((x, handle!(Roaring::read(r))));
+            }
+            col.len = handle!(usize::read(r));
+            // Every row of a bitmap has to be a row of the column
+            let end = u32::try_from(col.len).unwrap_or(u32::MAX);
+            if col.data.iter().map(|d| d.1.len()).sum::<usize>() > col.len || col.data.iter().any(|d| d.1.iter_range(end, u32::MAX).next().is_some()) {
+                return Err("Corrupt database file: bitmap rows out of range".to_string())
+            }
+            Ok(col)
+        }
+    }
+    struct BitMapIterator<'a, T: Clone + PartialEq> {
+        column: &'a BitMap<T>,
+        index: usize,
+        chunk: std::vec::IntoIter<Option<T>>
+    }
+    impl<'a, T: Clone + PartialOrd + Persist + HeapSize> Iterator for BitMapIterator<'a, T> {
+        type Item = Option<T>;
+        fn next(&mut self) -> Option<Self::Item> {
+            if let Some(x) = self.chunk.next() { return Some(x) }
+            if self.index >= self.column.len { return None }
+            // Decode the next chunk of rows all at once
+            let end = usize::min(self.index + BITMAP_CHUNK_ROWS, self.column.len);
+            self.chunk = self.column.slice(self.index, end).unwrap().into_iter();
+            self.index = end;
+            self.chunk.next()
+        }
+    }
+    // Bitmap column read in place, walking the bitmaps of its values without building them
+    struct BitMapView<'a, T> {
+        entries: &'a [u8], // Each value followed by its bitmap
+        num_values: usize,
+        len: usize,
+        values: PhantomData<T>
+    }
+    impl<'a, T: Persist> BitMapView<'a, T> {
+        fn read(bytes: &'a [u8], len: usize) -> Result<BitMapView<'a, T>, String> {
+            let mut r = Reader::new(bytes);
+            let num_values = handle!(usize::read(&mut r));
+            Ok(BitMapView { entries: r.rest(), num_values, len, values: PhantomData })
+        }
+        // Values of rows start to end, each value is decoded at most once
+        fn chunk(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> where T: Clone {
+            let mut data = vec![None; end - start];
+            let mut r = Reader::new(self.entries);
+            for _ in 0..self.num_values {
+                let mut value_r = r.clone();
+                handle!(T::skip(&mut r));
+                let rows = handle!(RoaringView::read(&mut r));
+                let mut value = None;
+                for i in rows.iter_range(start as u32, end as u32) {
+                    if value.is_none() { value = Some(handle!(T::read(&mut value_r))) }
+                    data[i as usize - start] = value.clone()
+                }
+            }
+            Ok(data)
+        }
+    }
+    impl<'a, T: Clone + Persist + 'static> ValuesView<'a, T> for BitMapView<'a, T> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            let view = BitMapView { entries: self.entries, num_values: self.num_values, len: self.len, values: PhantomData };
+            Box::new((start..self.len).step_by(BITMAP_CHUNK_ROWS)
+                .map_while(move |i| view.chunk(i, usize::min(i + BITMAP_CHUNK_ROWS, view.len)).ok())
+                .flatten())
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            // Value whose bitmap is set at idx, if any
+            let mut r = Reader::new(self.entries);
+            for _ in 0..self.num_values {
+                let mut value_r = r.clone();
+                handle!(T::skip(&mut r));
+                if handle!(RoaringView::read(&mut r)).contains(idx as u32) { return Ok(Some(handle!(T::read(&mut value_r)))) }
+            }
+            Ok(None)
+        }
+    }
+    // Write the lowest width bits of value to the end of data
+    fn push_bits(data: &mut BitVec, value: u64, width: usize) {
+        for i in 0..width {
+            data.push((value >> i) & 1 == 1)
+        }
+    }
+    // Read width bits starting at start
+    fn read_bits(data: &impl Bits, start: usize, width: usize) -> u64 {
+        let mut value: u64 = 0;
+        for i in 0..width {
+            value |= (data.bit(start + i) as u64) << i
+        }
+        value
+    }
+    // Number of bits needed to store value
+    fn bit_width(value: u64) -> usize {
+        (64 - value.leading_zeros()) as usize
+    }
+    pub struct DictCol<T: Clone + Eq + Hash> {
+        dict: Vec<T>, // Value of each code, code 0 (null) is not stored
+        codes: HashMap<T, u64>,
+        data: BitVec, // Codes packed into width bits each
+        width: usize,
+        len: usize
+    }
+    impl<T: Clone + Eq + Hash> DictCol<T> {
+        pub fn new() -> DictCol<T> {
+            DictCol {
+                dict: Vec::new(),
+                codes: HashMap::new(),
+                data: BitVec::new(),
+                width: 0,
+                len: 0
+            }
+        }
+        // Look up the code of row idx in the dictionary
+        fn value_of(&self, idx: usize) -> Option<T> {
+            match read_bits(&self.data, idx * self.width, self.width) as usize {
+                0 => None,
+                c => Some(self.dict[c - 1].clone())
+            }
+        }
+        // Widen every stored code to the new width
+        fn repack(&mut self, width: usize) {
+            let mut new_data = BitVec::with_capacity(self.len * width);
+            for i in 0..self.len {
+                push_bits(&mut new_data, read_bits(&self.data, i * self.width, self.width), width)
+            }
+            self.data = new_data;
+            self.width = width;
         }
+    }
+    impl<T: Clone + Eq + Hash + PartialOrd + Persist + HeapSize> ReadColumn<T> for DictCol<T> {
         fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
-            Box::new(BitMapIterator {
+            Box::new(DictColIterator {
                 column: self,
                 index: 0
             })
@@ -267,54 +897,359 @@ pub mod generic {
         fn len(&self) -> usize {
             self.len
         }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            Ok(self.value_of(idx))
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            // Every value is held twice, once in the dictionary and once as a key of the code lookup
+            self.dict.iter().map(|x| size_of::<T>() + size_of::<(T, u64)>() + 2 * x.heap_size()).sum::<usize>() + bitvec_bytes(&self.data)
+        }
+        fn select_in(&self, values: &[T]) -> BitVec {
+            // Compare codes instead of values
+            let codes: Vec<u64> = values.iter().filter_map(|x| self.codes.get(x)).copied().collect();
+            (0..self.len).map(|i| codes.contains(&read_bits(&self.data, i * self.width, self.width))).collect()
+        }
     }
-    struct BitMapIterator<'a, T: Clone + PartialEq> {
-        column: &'a BitMap<T>,
+    impl<T: Clone + Eq + Hash + PartialOrd + Persist + HeapSize> ColumnInterface<T> for DictCol<T> {
+        fn insert(&mut self, data: Option<T>) -> Result<(), String> {
+            let code = match data {
+                // Null is code zero
+                None => 0,
+                Some(x) => match self.codes.get(&x) {
+                    Some(c) => *c,
+                    None => {
+                        // Assign next code to new value
+                        self.dict.push(x.clone());
+                        let c = self.dict.len() as u64;
+                        self.codes.insert(x, c);
+                        // Grow code width if new code doesn't fit
+                        if bit_width(c) > self.width { self.repack(bit_width(c)) }
+                        c
+                    }
+                }
+            };
+            push_bits(&mut self.data, code, self.width);
+            self.len += 1;
+            Ok(())
+        }
+    }
+    impl<T: Clone + Eq + Hash + PartialOrd + Persist + HeapSize> Persist for DictCol<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.dict.write(out));
+            handle!(self.data.write(out));
+            self.len.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let dict: Vec<T> = handle!(Vec::read(r));
+            let data = handle!(BitVec::read(r));
+            let len = handle!(usize::read(r));
+            // Width and code map are derived from the dictionary
+            let width = bit_width(dict.len() as u64);
+            if data.len() != len * width { return Err("Corrupt database file: bad dictionary column size".to_string()) }
+            let mut codes = HashMap::new();
+            for (i, x) in dict.iter().enumerate() {
+                codes.insert(x.clone(), (i + 1) as u64);
+            }
+            Ok(DictCol { dict, codes, data, width, len })
+        }
+    }
+    struct DictColIterator<'a, T: Clone + Eq + Hash> {
+        column: &'a DictCol<T>,
         index: usize
     }
-    impl<'a, T: Clone + PartialEq> Iterator for BitMapIterator<'a, T> {
+    impl<'a, T: Clone + Eq + Hash> Iterator for DictColIterator<'a, T> {
         type Item = Option<T>;
         fn next(&mut self) -> Option<Self::Item> {
-            if self.index >= self.column.len() {
+            if self.index >= self.column.len {
                 None
             } else {
-                // Set data to none
-                let mut data = None;
-                // Check if any bitmaps are true
-                for j in 0..self.column.data.len() {
-                    if self.column.data[j].1[self.index] == true {
-                        data = Some(self.column.data[j].0.clone());
-                        break;
-                    }
-                }
-                // Increment index
+                let value = self.column.value_of(self.index);
                 self.index += 1;
-                // Return data
-                Some(data)
+                Some(value)
             }
         }
     }
-    pub struct XorCol {
-        data: BitVec,
+    // Dictionary column read in place, codes are read straight from the packed bits
+    struct DictView<'a, T> {
+        dict: &'a [u8],
+        dict_len: usize,
+        data: PackedBits<'a>,
+        width: usize,
         len: usize,
-        prev_value: Option<u64>,
+        values: PhantomData<T>
+    }
+    impl<'a, T: Persist> DictView<'a, T> {
+        fn read(bytes: &'a [u8], len: usize) -> Result<DictView<'a, T>, String> {
+            let mut r = Reader::new(bytes);
+            let dict_len = handle!(usize::read(&mut r));
+            let dict = r.rest();
+            for _ in 0..dict_len {
+                handle!(T::skip(&mut r))
+            }
+            let data = handle!(PackedBits::read(&mut r));
+            let width = bit_width(dict_len as u64);
+            if handle!(usize::read(&mut r)) != len || len.checked_mul(width) != Some(data.len) { return Err("Corrupt database file: bad dictionary column size".to_string()) }
+            Ok(DictView { dict, dict_len, data, width, len, values: PhantomData })
+        }
+        // Byte offset of each dictionary value
+        fn offsets(&self) -> Result<Vec<usize>, String> {
+            let mut r = Reader::new(self.dict);
+            let mut offsets = Vec::new();
+            for _ in 0..self.dict_len {
+                offsets.push(r.pos());
+                handle!(T::skip(&mut r))
+            }
+            Ok(offsets)
+        }
+    }
+    impl<'a, T: Persist + 'static> ValuesView<'a, T> for DictView<'a, T> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            let offsets = match self.offsets() {
+                Ok(offsets) => offsets,
+                Err(_) => return Box::new(std::iter::empty())
+            };
+            let (dict, data, width) = (self.dict, self.data, self.width);
+            Box::new((start..self.len).map_while(move |i| match read_bits(&data, i * width, width) as usize {
+                0 => Some(None),
+                c => Some(Some(T::read(&mut Reader::new(&dict[*offsets.get(c - 1)?..])).ok()?))
+            }))
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            let code = read_bits(&self.data, idx * self.width, self.width) as usize;
+            if code == 0 { return Ok(None) }
+            if code > self.dict_len { return Err("Corrupt dictionary column".to_string()) }
+            let mut r = Reader::new(self.dict);
+            for _ in 1..code {
+                handle!(T::skip(&mut r))
+            }
+            T::read(&mut r).map(Some)
+        }
+    }
+    // Number of values packed together under one reference value
+    const FORPACK_BLOCK: usize = 128;
+    // Largest magnitude treated as an integer, so differences between two values stay exact
+    const INTEGRAL_MAX: f64 = 4503599627370496.0; // 2^52
+    // Values that can be stored as an integer offset (integral, in range, and not -0.0)
+    fn integral(x: f64) -> bool {
+        x.fract() == 0.0 && x.abs() < INTEGRAL_MAX && !(x == 0.0 && x.is_sign_negative())
+    }
+    pub struct ForPack {
+        blocks: Vec<(f64, usize)>, // Minimum and offset width of each full block
+        block_starts: Vec<usize>, // Bit index where each block starts
+        data: BitVec, // Offsets from block minimums, plus one; zero marks an exception
+        exceptions: Vec<(usize, Option<f64>)>, // Row index and value of nulls and non-integral values
+        tail: Vec<Option<f64>>, // Values not yet in a full block
+        len: usize
+    }
+    impl ForPack {
+        pub fn new() -> ForPack {
+            ForPack {
+                blocks: Vec::new(),
+                block_starts: Vec::new(),
+                data: BitVec::new(),
+                exceptions: Vec::new(),
+                tail: Vec::new(),
+                len: 0
+            }
+        }
+        // Pack the tail into a new block
+        fn seal(&mut self) {
+            let start = self.len - self.tail.len();
+            // Find range of packable values
+            let mut min = f64::INFINITY;
+            let mut max = f64::NEG_INFINITY;
+            for x in self.tail.iter().flatten() {
+                if integral(*x) {
+                    min = min.min(*x);
+                    max = max.max(*x);
+                }
+            }
+            let (min, width) = if min > max { (0.0, 0) } else { (min, bit_width((max - min) as u64 + 1)) };
+            // Write offsets, or record exceptions
+            self.block_starts.push(self.data.len());
+            for (i, item) in self.tail.iter().enumerate() {
+                match item {
+                    Some(x) if integral(*x) => push_bits(&mut self.data, (*x - min) as u64 + 1, width),
+                    _ => {
+                        push_bits(&mut self.data, 0, width);
+                        self.exceptions.push((start + i, *item))
+                    }
+                }
+            }
+            self.blocks.push((min, width));
+            self.tail.clear();
+        }
+    }
+    impl ReadColumn<f64> for ForPack {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            Box::new(ForPackIterator {
+                column: self,
+                index: 0,
+                bit_index: 0,
+                exception_index: 0
+            })
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<f64>, String> {
+            handle!(check_index(idx, self.len));
+            let block = idx / FORPACK_BLOCK;
+            // Value still in tail
+            if block >= self.blocks.len() { return Ok(self.tail[idx - self.blocks.len() * FORPACK_BLOCK]) }
+            // Read offset from packed block
+            let (min, width) = self.blocks[block];
+            match read_bits(&self.data, self.block_starts[block] + (idx % FORPACK_BLOCK) * width, width) {
+                0 => match self.exceptions.binary_search_by_key(&idx, |e| e.0) {
+                    Ok(i) => Ok(self.exceptions[i].1),
+                    Err(_) => Err("Missing exception in forpack column".to_string())
+                },
+                o => Ok(Some(min + (o - 1) as f64))
+            }
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.blocks.len() * size_of::<(f64, usize)>() + self.block_starts.len() * size_of::<usize>() + bitvec_bytes(&self.data)
+                + self.exceptions.len() * size_of::<(usize, Option<f64>)>() + self.tail.len() * size_of::<Option<f64>>()
+        }
+    }
+    impl ColumnInterface<f64> for ForPack {
+        fn insert(&mut self, data: Option<f64>) -> Result<(), String> {
+            self.tail.push(data);
+            self.len += 1;
+            if self.tail.len() == FORPACK_BLOCK { self.seal() }
+            Ok(())
+        }
+    }
+    impl Persist for ForPack {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.blocks.write(out));
+            handle!(self.data.write(out));
+            handle!(self.exceptions.write(out));
+            self.tail.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let blocks: Vec<(f64, usize)> = handle!(Vec::read(r));
+            let data = handle!(BitVec::read(r));
+            let exceptions = handle!(Vec::read(r));
+            let tail: Vec<Option<f64>> = handle!(Vec::read(r));
+            // Check that the blocks cover the packed data exactly
+            let mut block_starts = Vec::new();
+            let mut bits = 0;
+            for block in &blocks {
+                block_starts.push(bits);
+                bits += block.1 * FORPACK_BLOCK
+            }
+            if bits != data.len() { return Err("Corrupt database file: bad forpack column size".to_string()) }
+            let len = blocks.len() * FORPACK_BLOCK + tail.len();
+            Ok(ForPack { blocks, block_starts, data, exceptions, tail, len })
+        }
+    }
+    struct ForPackIterator<'a> {
+        column: &'a ForPack,
+        index: usize,
+        bit_index: usize,
+        exception_index: usize
+    }
+    impl<'a> Iterator for ForPackIterator<'a> {
+        type Item = Option<f64>;
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.index >= self.column.len {
+                return None
+            }
+            let block = self.index / FORPACK_BLOCK;
+            let value = if block < self.column.blocks.len() {
+                // Read offset from packed block
+                let (min, width) = self.column.blocks[block];
+                let offset = read_bits(&self.column.data, self.bit_index, width);
+                self.bit_index += width;
+                match offset {
+                    0 => {
+                        let x = self.column.exceptions[self.exception_index].1;
+                        self.exception_index += 1;
+                        x
+                    },
+                    o => Some(min + (o - 1) as f64)
+                }
+            } else {
+                // Value still in tail
+                self.column.tail[self.index - self.column.blocks.len() * FORPACK_BLOCK]
+            };
+            self.index += 1;
+            Some(value)
+        }
+    }
+    pub struct XorCol {
+        data: BitVec,
+        len: usize,
+        prev_value: Option<u64>,
         // Following only need to be u8, but for some reason rust uses u32 for these values
         prev_num_leading: u32,
         prev_num_trailing: u32,
+        checkpoints: Vec<(usize, Option<u64>, u32, u32)> // Decoder state every CHECKPOINT_ROWS rows
     }
     impl XorCol {
         pub fn new() -> XorCol {
             XorCol {
                 data: BitVec::new(),
-                len: 9,
+                len: 0,
                 prev_value: None,
                 prev_num_leading: 0,
-                prev_num_trailing: 0
+                prev_num_trailing: 0,
+                checkpoints: Vec::new()
             }
         }
+        // Iterator starting at the checkpoint before row idx
+        fn iter_from(&self, idx: usize) -> XorColIterator<&BitVec> {
+            let (index, base_value, prev_leading, prev_trailing) = self.checkpoints[idx / CHECKPOINT_ROWS];
+            XorColIterator { data: &self.data, base_value, index, prev_leading, prev_trailing }
+        }
+    }
+    impl ReadColumn<f64> for XorCol {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            Box::new(XorColIterator {
+                data: &self.data,
+                base_value: None,
+                index: 0,
+                prev_leading: 0,
+                prev_trailing: 0
+            })
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<f64>, String> {
+            handle!(check_index(idx, self.len));
+            match self.iter_from(idx).nth(idx % CHECKPOINT_ROWS) {
+                Some(x) => Ok(x),
+                None => Err("Corrupt xor column".to_string())
+            }
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<f64>>, String> {
+            handle!(check_range(start, end, self.len));
+            if start == end { return Ok(Vec::new()) }
+            Ok(self.iter_from(start).skip(start % CHECKPOINT_ROWS).take(end - start).collect())
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            bitvec_bytes(&self.data) + self.checkpoints.len() * size_of::<(usize, Option<u64>, u32, u32)>()
+        }
     }
     impl ColumnInterface<f64> for XorCol {
-        fn insert(&mut self, data: Option<f64>) -> () {
+        fn insert(&mut self, data: Option<f64>) -> Result<(), String> {
+            // Record decoder state every so often
+            if self.len % CHECKPOINT_ROWS == 0 {
+                self.checkpoints.push((self.data.len(), self.prev_value, self.prev_num_leading, self.prev_num_trailing))
+            }
             // Increment length
             self.len += 1;
             // Insert value
@@ -384,35 +1319,59 @@ pub mod generic {
                     self.prev_value = Some(x)
                 }
             }
+            Ok(())
         }
-        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
-            Box::new(XorColIterator {
-                column: self,
-                base_value: None,
-                index: 0,
-                prev_leading: 0,
-                prev_trailing: 0
-            })
+    }
+    impl Persist for XorCol {
+        // The encoder state is written too so that inserts can continue after loading
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.data.write(out));
+            handle!(self.len.write(out));
+            handle!(self.prev_value.write(out));
+            handle!(self.prev_num_leading.write(out));
+            self.prev_num_trailing.write(out)
         }
-        fn len(&self) -> usize {
-            self.len
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let data = handle!(BitVec::read(r));
+            let len = handle!(usize::read(r));
+            let prev_value = handle!(Option::<u64>::read(r));
+            let prev_num_leading = handle!(u32::read(r));
+            let prev_num_trailing = handle!(u32::read(r));
+            let mut col = XorCol {
+                data,
+                len,
+                prev_value,
+                prev_num_leading,
+                prev_num_trailing,
+                checkpoints: Vec::new()
+            };
+            // Decode once to find checkpoints
+            let mut checkpoints = Vec::new();
+            let mut iter = XorColIterator { data: &col.data, base_value: None, index: 0, prev_leading: 0, prev_trailing: 0 };
+            for i in 0..len {
+                if i % CHECKPOINT_ROWS == 0 { checkpoints.push((iter.index, iter.base_value, iter.prev_leading, iter.prev_trailing)) }
+                if iter.index >= col.data.len() { return Err("Corrupt database file: bad xor column size".to_string()) }
+                iter.next();
+            }
+            col.checkpoints = checkpoints;
+            Ok(col)
         }
     }
-    struct XorColIterator<'a> {
-        column: &'a XorCol,
+    struct XorColIterator<D: Bits> {
+        data: D,
         base_value: Option<u64>,
         index: usize,
         prev_leading: u32,
         prev_trailing: u32
     }
-    impl<'a> Iterator for XorColIterator<'a> {
+    impl<D: Bits> Iterator for XorColIterator<D> {
         type Item = Option<f64>;
         fn next(&mut self) -> Option<Self::Item> {
-            if self.index >= self.column.data.len() {
+            if self.index >= self.data.bit_len() {
                 None
             } else {
                 // Read first bit stored at index
-                let first_bit: bool = self.column.data[self.index];
+                let first_bit: bool = self.data.bit(self.index);
                 self.index += 1;
                 match first_bit {
                     // If first bit is a zero, return none
@@ -423,29 +1382,31 @@ pub mod generic {
                             // Have base value
                             Some(x) => {
                                 // Check if is the same or not
-                                let same_val = ! self.column.data[self.index];
+                                let same_val = ! self.data.bit(self.index);
                                 self.index += 1;
                                 // If same value, return value again
                                 if same_val {
                                     Some(Some(f64::from_bits(x)))
                                 } else {
                                     // Determine 'control bit'
-                                    let control_bit = self.column.data[self.index];
+                                    let control_bit = self.data.bit(self.index);
                                     self.index += 1;
                                     // Control bit true or false?
                                     if control_bit == true {
                                         // Get the length of the number of leading zeros
                                         let mut num_leading_zeros: u32 = 0;
                                         for i in 0..6 {
-                                            num_leading_zeros = num_leading_zeros | (self.column.data[self.index] as u32) << i;
+                                            num_leading_zeros = num_leading_zeros | (self.data.bit(self.index) as u32) << i;
                                             self.index += 1;
                                         }
                                         // Get length of the meaningful XORed value
                                         let mut meaningful_size: u32 = 0;
                                         for i in 0..7 {
-                                            meaningful_size = meaningful_size | (self.column.data[self.index] as u32) << i;
+                                            meaningful_size = meaningful_size | (self.data.bit(self.index) as u32) << i;
                                             self.index += 1;
                                         }
+                                        // Only corrupt data has more than 64 bits
+                                        if meaningful_size + num_leading_zeros > 64 { return None }
                                         // Calcualte the number of trailing zeros
                                         let num_trailing_zeros: u32 = 64 - meaningful_size - num_leading_zeros;
                                         // Update iterator
@@ -460,7 +1421,7 @@ pub mod generic {
                                     }
                                     // Push inverse of meaningful XORed bits
                                     for i in 0..meaningful_size {
-                                        new_value = new_value | (((((self.column.data[self.index] as u64) << (i + self.prev_trailing)) ^ x) & (1 << (i + self.prev_trailing))));
+                                        new_value = new_value | (((((self.data.bit(self.index) as u64) << (i + self.prev_trailing)) ^ x) & (1 << (i + self.prev_trailing))));
                                         self.index += 1;
                                     }
                                     // Push upper bits of base value
@@ -478,7 +1439,7 @@ pub mod generic {
                                 // Read base value (next 64 bits)
                                 let mut base_value_bits: u64 = 0;
                                 for i in 0..64 {
-                                    base_value_bits = base_value_bits | ((self.column.data[self.index] as u64) << i);
+                                    base_value_bits = base_value_bits | ((self.data.bit(self.index) as u64) << i);
                                     self.index += 1;
                                 };
                                 // Store base value
@@ -492,9 +1453,1247 @@ pub mod generic {
             }
         }
     }
+    // Xor column read in place, always decoded from its first row since every value depends on the one before
+    struct XorView<'a> {
+        data: PackedBits<'a>,
+        len: usize
+    }
+    impl<'a> XorView<'a> {
+        fn read(bytes: &'a [u8], len: usize) -> Result<XorView<'a>, String> {
+            let mut r = Reader::new(bytes);
+            let data = handle!(PackedBits::read(&mut r));
+            if handle!(usize::read(&mut r)) != len { return Err("Corrupt database file: bad xor column size".to_string()) }
+            Ok(XorView { data, len })
+        }
+    }
+    impl<'a> ValuesView<'a, f64> for XorView<'a> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            Box::new(XorColIterator { data: self.data, base_value: None, index: 0, prev_leading: 0, prev_trailing: 0 }.take(self.len).skip(start))
+        }
+    }
+    // Zig-zag varint in 8 bit groups (7 value bits and a continuation bit)
+    fn push_varint(data: &mut BitVec, value: u64) {
+        let mut v = value;
+        loop {
+            let group = v & 0x7f;
+            v >>= 7;
+            push_bits(data, group | if v != 0 { 0x80 } else { 0 }, 8);
+            if v == 0 { break }
+        }
+    }
+    fn read_varint(data: &impl Bits, start: &mut usize) -> u64 {
+        let mut value: u64 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let group = read_bits(data, *start, 8);
+            *start += 8;
+            // Groups past the width of a u64 can only come from corrupt data
+            value |= (group & 0x7f).checked_shl(shift).unwrap_or(0);
+            shift += 7;
+            if group & 0x80 == 0 { return value }
+        }
+    }
+    fn zigzag(x: i64) -> u64 {
+        ((x << 1) ^ (x >> 63)) as u64
+    }
+    fn unzigzag(x: u64) -> i64 {
+        ((x >> 1) as i64) ^ -((x & 1) as i64)
+    }
+    pub struct DeltaCol {
+        data: BitVec,
+        len: usize,
+        prev_value: Option<f64>, // Last value, when it can be used as a base for deltas
+        prev_delta: i64,
+        checkpoints: Vec<(usize, f64, i64)> // Decoder state (bit index, value, delta) every CHECKPOINT_ROWS rows
+    }
+    impl DeltaCol {
+        pub fn new() -> DeltaCol {
+            DeltaCol {
+                data: BitVec::new(),
+                len: 0,
+                prev_value: None,
+                prev_delta: 0,
+                checkpoints: Vec::new()
+            }
+        }
+        // Iterator starting at the checkpoint before row idx
+        fn iter_from(&self, idx: usize) -> DeltaColIterator<&BitVec> {
+            let (bit_index, prev_value, prev_delta) = self.checkpoints[idx / CHECKPOINT_ROWS];
+            DeltaColIterator { data: &self.data, bit_index, prev_value, prev_delta }
+        }
+    }
+    impl ReadColumn<f64> for DeltaCol {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            Box::new(DeltaColIterator {
+                data: &self.data,
+                bit_index: 0,
+                prev_value: 0.0,
+                prev_delta: 0
+            })
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<f64>, String> {
+            handle!(check_index(idx, self.len));
+            match self.iter_from(idx).nth(idx % CHECKPOINT_ROWS) {
+                Some(x) => Ok(x),
+                None => Err("Corrupt delta column".to_string())
+            }
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<f64>>, String> {
+            handle!(check_range(start, end, self.len));
+            if start == end { return Ok(Vec::new()) }
+            Ok(self.iter_from(start).skip(start % CHECKPOINT_ROWS).take(end - start).collect())
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            bitvec_bytes(&self.data) + self.checkpoints.len() * size_of::<(usize, f64, i64)>()
+        }
+    }
+    impl ColumnInterface<f64> for DeltaCol {
+        fn insert(&mut self, data: Option<f64>) -> Result<(), String> {
+            // Record decoder state every so often
+            if self.len % CHECKPOINT_ROWS == 0 {
+                self.checkpoints.push((self.data.len(), self.prev_value.unwrap_or(0.0), self.prev_delta))
+            }
+            // Increment length
+            self.len += 1;
+            match (data, self.prev_value) {
+                // Integral value following an integral value
+                (Some(x), Some(y)) if integral(x) => {
+                    let delta = (x - y) as i64;
+                    let dod = delta - self.prev_delta;
+                    if dod == 0 {
+                        // Same delta as before, single '0' bit
+                        self.data.push(false)
+                    } else {
+                        // Otherwise '1' followed by the delta of deltas
+                        self.data.push(true);
+                        push_varint(&mut self.data, zigzag(dod))
+                    }
+                    self.prev_value = Some(x);
+                    self.prev_delta = delta;
+                },
+                // Null, '1' followed by the escape (a zero varint) and a '0' bit
+                (None, _) => {
+                    self.data.push(true);
+                    push_varint(&mut self.data, 0);
+                    self.data.push(false)
+                },
+                // First value or not integral, '1' followed by the escape, a '1' bit and the whole float
+                (Some(x), _) => {
+                    self.data.push(true);
+                    push_varint(&mut self.data, 0);
+                    self.data.push(true);
+                    push_bits(&mut self.data, f64::to_bits(x), 64);
+                    // Start over from this value
+                    self.prev_value = if integral(x) { Some(x) } else { None };
+                    self.prev_delta = 0;
+                }
+            }
+            Ok(())
+        }
+    }
+    impl Persist for DeltaCol {
+        // The encoder state is written too so that inserts can continue after loading
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.data.write(out));
+            handle!(self.len.write(out));
+            handle!(self.prev_value.write(out));
+            (self.prev_delta as u64).write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let data = handle!(BitVec::read(r));
+            let len = handle!(usize::read(r));
+            let prev_value = handle!(Option::<f64>::read(r));
+            let prev_delta = handle!(u64::read(r)) as i64;
+            let mut col = DeltaCol { data, len, prev_value, prev_delta, checkpoints: Vec::new() };
+            // Decode once to find checkpoints
+            let mut checkpoints = Vec::new();
+            let mut iter = DeltaColIterator { data: &col.data, bit_index: 0, prev_value: 0.0, prev_delta: 0 };
+            for i in 0..len {
+                if i % CHECKPOINT_ROWS == 0 { checkpoints.push((iter.bit_index, iter.prev_value, iter.prev_delta)) }
+                if iter.bit_index >= col.data.len() { return Err("Corrupt database file: bad delta column size".to_string()) }
+                iter.next();
+            }
+            col.checkpoints = checkpoints;
+            Ok(col)
+        }
+    }
+    struct DeltaColIterator<D: Bits> {
+        data: D,
+        bit_index: usize,
+        prev_value: f64,
+        prev_delta: i64
+    }
+    impl<D: Bits> Iterator for DeltaColIterator<D> {
+        type Item = Option<f64>;
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.bit_index >= self.data.bit_len() {
+                return None
+            }
+            // Read control bit
+            let control_bit = self.data.bit(self.bit_index);
+            self.bit_index += 1;
+            let dod = match control_bit {
+                false => 0,
+                true => match read_varint(&self.data, &mut self.bit_index) {
+                    // Escape
+                    0 => {
+                        let has_value = self.data.bit(self.bit_index);
+                        self.bit_index += 1;
+                        if !has_value { return Some(None) }
+                        let x = f64::from_bits(read_bits(&self.data, self.bit_index, 64));
+                        self.bit_index += 64;
+                        self.prev_value = x;
+                        self.prev_delta = 0;
+                        return Some(Some(x))
+                    },
+                    z => unzigzag(z)
+                }
+            };
+            // Apply delta of deltas
+            self.prev_delta = self.prev_delta.wrapping_add(dod);
+            self.prev_value += self.prev_delta as f64;
+            Some(Some(self.prev_value))
+        }
+    }
+    // Delta column read in place, always decoded from its first row since every value depends on the ones before
+    struct DeltaView<'a> {
+        data: PackedBits<'a>,
+        len: usize
+    }
+    impl<'a> DeltaView<'a> {
+        fn read(bytes: &'a [u8], len: usize) -> Result<DeltaView<'a>, String> {
+            let mut r = Reader::new(bytes);
+            let data = handle!(PackedBits::read(&mut r));
+            if handle!(usize::read(&mut r)) != len { return Err("Corrupt database file: bad delta column size".to_string()) }
+            Ok(DeltaView { data, len })
+        }
+    }
+    impl<'a> ValuesView<'a, f64> for DeltaView<'a> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            Box::new(DeltaColIterator { data: self.data, bit_index: 0, prev_value: 0.0, prev_delta: 0 }.take(self.len).skip(start))
+        }
+    }
+    // Rows in each segment of a column, segments are compressed on their own
+    pub const SEGMENT_ROWS: usize = 4 * ZONE_ROWS;
+    // Number of values encoded together under one exponent and factor
+    const ALP_BLOCK: usize = 1024;
+    // Values of a block tried when choosing its exponent and factor
+    const ALP_SAMPLES: usize = 32;
+    // Rough size of a value stored as an exception, used to weigh them against wider offsets
+    const ALP_EXCEPTION_BITS: usize = 128;
+    // Powers of ten up to the largest exponent tried, all exactly representable
+    const POW10: [f64; 19] = [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16, 1e17, 1e18];
+    // Integer a value becomes with exponent e and factor f, as long as it decodes back to exactly the same bits
+    fn alp_encode(x: f64, e: usize, f: usize) -> Option<f64> {
+        // Adding zero turns -0.0 into 0.0, so -0.0 never decodes back and is kept as an exception
+        let n = (x * POW10[e] / POW10[f]).round() + 0.0;
+        if n.is_nan() || n.abs() >= INTEGRAL_MAX || alp_decode(n, e, f).to_bits() != x.to_bits() { return None }
+        Some(n)
+    }
+    // Dividing by an exact power of ten gives the closest double to the decimal, so parsed decimals come back exactly
+    fn alp_decode(n: f64, e: usize, f: usize) -> f64 {
+        n * POW10[f] / POW10[e]
+    }
+    pub struct AlpCol {
+        blocks: Vec<((u8, u8), (f64, usize))>, // Exponent, factor, minimum and offset width of each full block
+        block_starts: Vec<usize>, // Bit index where each block starts
+        data: BitVec, // Offsets of the encoded integers from block minimums, plus one; zero marks an exception
+        exceptions: Vec<(usize, Option<f64>)>, // Row index and value of nulls and values that don't round-trip
+        tail: Vec<Option<f64>>, // Values not yet in a full block
+        len: usize
+    }
+    impl AlpCol {
+        pub fn new() -> AlpCol {
+            AlpCol {
+                blocks: Vec::new(),
+                block_starts: Vec::new(),
+                data: BitVec::new(),
+                exceptions: Vec::new(),
+                tail: Vec::new(),
+                len: 0
+            }
+        }
+        // Exponent and factor that store a sample of the tail in the fewest bits
+        fn choose(&self) -> (usize, usize) {
+            let sample: Vec<f64> = self.tail.iter().step_by(ALP_BLOCK / ALP_SAMPLES).flatten().copied().collect();
+            let mut best = (0, 0);
+            let mut best_cost = usize::MAX;
+            for e in 0..POW10.len() {
+                for f in 0..=e {
+                    let encoded: Vec<f64> = sample.iter().filter_map(|x| alp_encode(*x, e, f)).collect();
+                    let min = encoded.iter().copied().fold(f64::INFINITY, f64::min);
+                    let max = encoded.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                    let width = if min > max { 0 } else { bit_width((max - min) as u64 + 1) };
+                    let cost = sample.len() * width + (sample.len() - encoded.len()) * ALP_EXCEPTION_BITS;
+                    // Ties go to the smaller exponent
+                    if cost < best_cost {
+                        best = (e, f);
+                        best_cost = cost
+                    }
+                }
+            }
+            best
+        }
+        // Encode the tail into a new block
+        fn seal(&mut self) {
+            let start = self.len - self.tail.len();
+            let (e, f) = self.choose();
+            let encoded: Vec<Option<f64>> = self.tail.iter().map(|x| x.and_then(|x| alp_encode(x, e, f))).collect();
+            // Find range of encoded integers
+            let min = encoded.iter().flatten().copied().fold(f64::INFINITY, f64::min);
+            let max = encoded.iter().flatten().copied().fold(f64::NEG_INFINITY, f64::max);
+            let (min, width) = if min > max { (0.0, 0) } else { (min, bit_width((max - min) as u64 + 1)) };
+            // Write offsets, or record exceptions
+            self.block_starts.push(self.data.len());
+            for (i, n) in encoded.iter().enumerate() {
+                match n {
+                    Some(n) => push_bits(&mut self.data, (*n - min) as u64 + 1, width),
+                    None => {
+                        push_bits(&mut self.data, 0, width);
+                        self.exceptions.push((start + i, self.tail[i]))
+                    }
+                }
+            }
+            self.blocks.push(((e as u8, f as u8), (min, width)));
+            self.tail.clear();
+        }
+        // Value of an offset read from a block
+        fn decode(&self, block: usize, offset: u64, idx: usize) -> Result<Option<f64>, String> {
+            let ((e, f), (min, _)) = self.blocks[block];
+            match offset {
+                0 => match self.exceptions.binary_search_by_key(&idx, |x| x.0) {
+                    Ok(i) => Ok(self.exceptions[i].1),
+                    Err(_) => Err("Missing exception in alp column".to_string())
+                },
+                o => Ok(Some(alp_decode(min + (o - 1) as f64, e as usize, f as usize)))
+            }
+        }
+    }
+    impl ReadColumn<f64> for AlpCol {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            Box::new(AlpColIterator {
+                column: self,
+                index: 0,
+                bit_index: 0,
+                exception_index: 0
+            })
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<f64>, String> {
+            handle!(check_index(idx, self.len));
+            let block = idx / ALP_BLOCK;
+            // Value still in tail
+            if block >= self.blocks.len() { return Ok(self.tail[idx - self.blocks.len() * ALP_BLOCK]) }
+            // Read offset from packed block
+            let width = self.blocks[block].1.1;
+            self.decode(block, read_bits(&self.data, self.block_starts[block] + (idx % ALP_BLOCK) * width, width), idx)
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.blocks.len() * size_of::<((u8, u8), (f64, usize))>() + self.block_starts.len() * size_of::<usize>() + bitvec_bytes(&self.data)
+                + self.exceptions.len() * size_of::<(usize, Option<f64>)>() + self.tail.len() * size_of::<Option<f64>>()
+        }
+    }
+    impl ColumnInterface<f64> for AlpCol {
+        fn insert(&mut self, data: Option<f64>) -> Result<(), String> {
+            self.tail.push(data);
+            self.len += 1;
+            if self.tail.len() == ALP_BLOCK { self.seal() }
+            Ok(())
+        }
+    }
+    impl Persist for AlpCol {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.blocks.write(out));
+            handle!(self.data.write(out));
+            handle!(self.exceptions.write(out));
+            self.tail.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let blocks: Vec<((u8, u8), (f64, usize))> = handle!(Vec::read(r));
+            let data = handle!(BitVec::read(r));
+            let exceptions = handle!(Vec::read(r));
+            let tail: Vec<Option<f64>> = handle!(Vec::read(r));
+            // Check that exponents are in range and the blocks cover the packed data exactly
+            let mut block_starts = Vec::new();
+            let mut bits = 0;
+            for ((e, f), (_, width)) in &blocks {
+                if *e as usize >= POW10.len() || f > e { return Err("Corrupt database file: bad alp exponent".to_string()) }
+                block_starts.push(bits);
+                bits += width * ALP_BLOCK
+            }
+            if bits != data.len() { return Err("Corrupt database file: bad alp column size".to_string()) }
+            let len = blocks.len() * ALP_BLOCK + tail.len();
+            Ok(AlpCol { blocks, block_starts, data, exceptions, tail, len })
+        }
+    }
+    struct AlpColIterator<'a> {
+        column: &'a AlpCol,
+        index: usize,
+        bit_index: usize,
+        exception_index: usize
+    }
+    impl<'a> Iterator for AlpColIterator<'a> {
+        type Item = Option<f64>;
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.index >= self.column.len {
+                return None
+            }
+            let block = self.index / ALP_BLOCK;
+            let value = if block < self.column.blocks.len() {
+                // Read offset from packed block
+                let ((e, f), (min, width)) = self.column.blocks[block];
+                let offset = read_bits(&self.column.data, self.bit_index, width);
+                self.bit_index += width;
+                match offset {
+                    0 => {
+                        let x = self.column.exceptions[self.exception_index].1;
+                        self.exception_index += 1;
+                        x
+                    },
+                    o => Some(alp_decode(min + (o - 1) as f64, e as usize, f as usize))
+                }
+            } else {
+                // Value still in tail
+                self.column.tail[self.index - self.column.blocks.len() * ALP_BLOCK]
+            };
+            self.index += 1;
+            Some(value)
+        }
+    }
+    // ForPack or Alp column read in place: blocks of offsets from each block's minimum, then the exceptions and the tail
+    #[derive(Clone)]
+    struct BlocksView<'a> {
+        block_rows: usize,
+        blocks: Vec<(usize, usize, f64, usize, usize)>, // Exponent, factor, minimum, offset width and first bit of each block
+        data: PackedBits<'a>,
+        exceptions: &'a [u8],
+        num_exceptions: usize,
+        tail: &'a [u8],
+        len: usize
+    }
+    impl<'a> BlocksView<'a> {
+        // Only the block headers are read, those of ForPack have no exponent or factor so both are zero
+        fn read(bytes: &'a [u8], len: usize, block_rows: usize, alp: bool) -> Result<BlocksView<'a>, String> {
+            let mut r = Reader::new(bytes);
+            let mut blocks = Vec::new();
+            let mut bits = 0;
+            for _ in 0..handle!(usize::read(&mut r)) {
+                let (e, f) = if alp { (handle!(u8::read(&mut r)) as usize, handle!(u8::read(&mut r)) as usize) } else { (0, 0) };
+                let min = handle!(f64::read(&mut r));
+                let width = handle!(usize::read(&mut r));
+                if e >= POW10.len() || f > e || width > 64 { return Err("Corrupt database file: bad block header".to_string()) }
+                blocks.push((e, f, min, width, bits));
+                bits += width * block_rows
+            }
+            let data = handle!(PackedBits::read(&mut r));
+            let num_exceptions = handle!(usize::read(&mut r));
+            let exceptions = r.rest();
+            for _ in 0..num_exceptions {
+                handle!(<(usize, Option<f64>)>::skip(&mut r))
+            }
+            let tail_len = handle!(usize::read(&mut r));
+            if bits != data.len || (blocks.len() * block_rows).checked_add(tail_len) != Some(len) { return Err("Corrupt database file: bad packed column size".to_string()) }
+            Ok(BlocksView { block_rows, blocks, data, exceptions, num_exceptions, tail: r.rest(), len })
+        }
+    }
+    impl<'a> ValuesView<'a, f64> for BlocksView<'a> {
+        fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item=Option<f64>> + 'a> {
+            let mut iter = BlocksViewIterator {
+                view: self.clone(),
+                index: start,
+                exceptions: Reader::new(self.exceptions),
+                exceptions_left: self.num_exceptions,
+                tail: Reader::new(self.tail)
+            };
+            // Exceptions and tail values before start are skipped
+            while iter.exceptions_left > 0 && usize::read(&mut iter.exceptions.clone()).is_ok_and(|row| row < start) {
+                if iter.next_exception().is_none() { return Box::new(std::iter::empty()) }
+            }
+            for _ in self.blocks.len() * self.block_rows..start {
+                if Option::<f64>::skip(&mut iter.tail).is_err() { return Box::new(std::iter::empty()) }
+            }
+            Box::new(iter)
+        }
+    }
+    struct BlocksViewIterator<'a> {
+        view: BlocksView<'a>,
+        index: usize,
+        exceptions: Reader<'a>,
+        exceptions_left: usize,
+        tail: Reader<'a>
+    }
+    impl BlocksViewIterator<'_> {
+        fn next_exception(&mut self) -> Option<(usize, Option<f64>)> {
+            if self.exceptions_left == 0 { return None }
+            self.exceptions_left -= 1;
+            <(usize, Option<f64>)>::read(&mut self.exceptions).ok()
+        }
+    }
+    impl Iterator for BlocksViewIterator<'_> {
+        type Item = Option<f64>;
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.index >= self.view.len { return None }
+            let value = match self.view.blocks.get(self.index / self.view.block_rows).copied() {
+                Some((e, f, min, width, first_bit)) => match read_bits(&self.view.data, first_bit + (self.index % self.view.block_rows) * width, width) {
+                    // Exceptions are in row order, so the next one is this row's
+                    0 => match self.next_exception() {
+                        Some((row, x)) if row == self.index => x,
+                        _ => return None
+                    },
+                    o => Some(alp_decode(min + (o - 1) as f64, e, f))
+                },
+                // Value still in tail
+                None => Option::<f64>::read(&mut self.tail).ok()?
+            };
+            self.index += 1;
+            Some(value)
+        }
+    }
+    // Types a column can hold, along with the encodings each implements
+    pub trait Encodable: Clone + PartialOrd + Persist + HeapSize + 'static {
+        fn uncompressed() -> Box<dyn ColumnInterface<Self>>;
+        // Empty column of an encoding
+        fn encoding(strategy: CompressType) -> Result<Box<dyn ColumnInterface<Self>>, String>;
+        // Read back a column of an encoding
+        fn load_encoding(strategy: CompressType, r: &mut Reader) -> Result<Box<dyn ColumnInterface<Self>>, String>;
+        // Read len values of an encoding in place from the bytes it was saved to
+        fn view<'a>(strategy: CompressType, bytes: &'a [u8], len: usize) -> Result<Box<dyn ValuesView<'a, Self> + 'a>, String>;
+    }
+    impl Encodable for f64 {
+        fn uncompressed() -> Box<dyn ColumnInterface<f64>> { Box::new(Uncompressed::<f64>::new()) }
+        fn encoding(strategy: CompressType) -> Result<Box<dyn ColumnInterface<f64>>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => f64::uncompressed(),
+                CompressType::RunLength => Box::new(RunLength::<f64>::new()),
+                CompressType::BitMap => Box::new(BitMap::<f64>::new()),
+                CompressType::Xor => Box::new(XorCol::new()),
+                CompressType::ForPack => Box::new(ForPack::new()),
+                CompressType::Delta => Box::new(DeltaCol::new()),
+                CompressType::Alp => Box::new(AlpCol::new()),
+                _ => return Err("Number columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+        fn load_encoding(strategy: CompressType, r: &mut Reader) -> Result<Box<dyn ColumnInterface<f64>>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => Box::new(handle!(Uncompressed::<f64>::read(r))),
+                CompressType::RunLength => Box::new(handle!(RunLength::<f64>::read(r))),
+                CompressType::BitMap => Box::new(handle!(BitMap::<f64>::read(r))),
+                CompressType::Xor => Box::new(handle!(XorCol::read(r))),
+                CompressType::ForPack => Box::new(handle!(ForPack::read(r))),
+                CompressType::Delta => Box::new(handle!(DeltaCol::read(r))),
+                CompressType::Alp => Box::new(handle!(AlpCol::read(r))),
+                _ => return Err("Number columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+        fn view<'a>(strategy: CompressType, bytes: &'a [u8], len: usize) -> Result<Box<dyn ValuesView<'a, f64> + 'a>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => Box::new(handle!(UncompressedView::<f64>::read(bytes, len))),
+                CompressType::RunLength => Box::new(handle!(RunLengthView::<f64>::read(bytes))),
+                CompressType::BitMap => Box::new(handle!(BitMapView::<f64>::read(bytes, len))),
+                CompressType::Xor => Box::new(handle!(XorView::read(bytes, len))),
+                CompressType::ForPack => Box::new(handle!(BlocksView::read(bytes, len, FORPACK_BLOCK, false))),
+                CompressType::Delta => Box::new(handle!(DeltaView::read(bytes, len))),
+                CompressType::Alp => Box::new(handle!(BlocksView::read(bytes, len, ALP_BLOCK, true))),
+                _ => return Err("Number columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+    }
+    impl Encodable for bool {
+        fn uncompressed() -> Box<dyn ColumnInterface<bool>> { Box::new(BoolCol::new()) }
+        fn encoding(strategy: CompressType) -> Result<Box<dyn ColumnInterface<bool>>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => bool::uncompressed(),
+                CompressType::RunLength => Box::new(RunLength::<bool>::new()),
+                CompressType::BitMap => Box::new(BitMap::<bool>::new()),
+                _ => return Err("Boolean columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+        fn load_encoding(strategy: CompressType, r: &mut Reader) -> Result<Box<dyn ColumnInterface<bool>>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => Box::new(handle!(BoolCol::read(r))),
+                CompressType::RunLength => Box::new(handle!(RunLength::<bool>::read(r))),
+                CompressType::BitMap => Box::new(handle!(BitMap::<bool>::read(r))),
+                _ => return Err("Boolean columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+        fn view<'a>(strategy: CompressType, bytes: &'a [u8], len: usize) -> Result<Box<dyn ValuesView<'a, bool> + 'a>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => Box::new(handle!(BoolView::read(bytes, len))),
+                CompressType::RunLength => Box::new(handle!(RunLengthView::<bool>::read(bytes))),
+                CompressType::BitMap => Box::new(handle!(BitMapView::<bool>::read(bytes, len))),
+                _ => return Err("Boolean columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+    }
+    impl Encodable for String {
+        fn uncompressed() -> Box<dyn ColumnInterface<String>> { Box::new(Uncompressed::<String>::new()) }
+        fn encoding(strategy: CompressType) -> Result<Box<dyn ColumnInterface<String>>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => String::uncompressed(),
+                CompressType::RunLength => Box::new(RunLength::<String>::new()),
+                CompressType::BitMap => Box::new(BitMap::<String>::new()),
+                CompressType::Dict => Box::new(DictCol::<String>::new()),
+                _ => return Err("String columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+        fn load_encoding(strategy: CompressType, r: &mut Reader) -> Result<Box<dyn ColumnInterface<String>>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => Box::new(handle!(Uncompressed::<String>::read(r))),
+                CompressType::RunLength => Box::new(handle!(RunLength::<String>::read(r))),
+                CompressType::BitMap => Box::new(handle!(BitMap::<String>::read(r))),
+                CompressType::Dict => Box::new(handle!(DictCol::<String>::read(r))),
+                _ => return Err("String columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+        fn view<'a>(strategy: CompressType, bytes: &'a [u8], len: usize) -> Result<Box<dyn ValuesView<'a, String> + 'a>, String> {
+            Ok(match strategy {
+                CompressType::Uncompressed => Box::new(handle!(UncompressedView::<String>::read(bytes, len))),
+                CompressType::RunLength => Box::new(handle!(RunLengthView::<String>::read(bytes))),
+                CompressType::BitMap => Box::new(handle!(BitMapView::<String>::read(bytes, len))),
+                CompressType::Dict => Box::new(handle!(DictView::<String>::read(bytes, len))),
+                _ => return Err("String columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
+            })
+        }
+    }
+    // Keeps track of which rows are null, so that the encoding underneath only ever stores values
+    pub struct Nullable<T: Encodable> {
+        validity: Option<BitVec>, // Set for rows holding a value, left out until the first null
+        values: Box<dyn ColumnInterface<T>>,
+        len: usize
+    }
+    impl<T: Encodable> Nullable<T> {
+        // Wrap an empty encoding
+        pub fn new(values: Box<dyn ColumnInterface<T>>) -> Nullable<T> {
+            Nullable {
+                validity: None,
+                values,
+                len: 0
+            }
+        }
+        // Position of row idx among the stored values
+        fn rank(&self, idx: usize) -> usize {
+            match &self.validity {
+                Some(bits) => bits[..idx].count_ones(),
+                None => idx
+            }
+        }
+        // Read back a column written by save, strategy is needed to know the encoding of the values
+        pub fn load(strategy: CompressType, r: &mut Reader) -> Result<Nullable<T>, String> {
+            let validity = handle!(Option::<BitVec>::read(r));
+            let num_values = handle!(usize::read(r));
+            let size = handle!(usize::read(r));
+            let mut values_r = Reader::new(handle!(r.take(size)));
+            let values = handle!(T::load_encoding(strategy, &mut values_r));
+            if values.len() != num_values || !values_r.rest().is_empty() { return Err("Corrupt database file: bad segment size".to_string()) }
+            let len = match &validity {
+                Some(bits) => {
+                    if bits.count_ones() != values.len() { return Err("Corrupt database file: mismatched validity bitmap".to_string()) }
+                    bits.len()
+                },
+                None => values.len()
+            };
+            Ok(Nullable { validity, values, len })
+        }
+    }
+    impl<T: Encodable> ReadColumn<T> for Nullable<T> {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            match &self.validity {
+                Some(bits) => {
+                    let mut vals = self.values.iter();
+                    Box::new(bits.iter().by_vals().map(move |valid| if valid { vals.next().flatten() } else { None }))
+                },
+                None => self.values.iter()
+            }
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            if let Some(bits) = &self.validity {
+                if !bits[idx] { return Ok(None) }
+            }
+            self.values.get(self.rank(idx))
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.len));
+            match &self.validity {
+                Some(bits) => {
+                    // Values of the rows in range are stored next to each other
+                    let first = self.rank(start);
+                    let mut vals = handle!(self.values.slice(first, first + bits[start..end].count_ones())).into_iter();
+                    Ok(bits[start..end].iter().by_vals().map(|valid| if valid { vals.next().flatten() } else { None }).collect())
+                },
+                None => self.values.slice(start, end)
+            }
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.values.size_in_bytes() + self.validity.as_ref().map_or(0, bitvec_bytes)
+        }
+        fn select_in(&self, values: &[T]) -> BitVec {
+            let selected = self.values.select_in(values);
+            match &self.validity {
+                Some(bits) => {
+                    // Spread the selection of stored values back out over every row
+                    let mut vals = selected.iter().by_vals();
+                    bits.iter().by_vals().map(|valid| valid && vals.next().unwrap_or(false)).collect()
+                },
+                None => selected
+            }
+        }
+        fn nulls(&self) -> BitVec {
+            match &self.validity {
+                Some(bits) => !bits.clone(),
+                None => bitvec![0; self.len]
+            }
+        }
+        fn null_count(&self) -> usize {
+            self.validity.as_ref().map_or(0, |bits| bits.count_zeros())
+        }
+    }
+    impl<T: Encodable> ColumnInterface<T> for Nullable<T> {
+        fn insert(&mut self, data: Option<T>) -> Result<(), String> {
+            match data {
+                Some(x) => {
+                    if let Some(bits) = &mut self.validity { bits.push(true) }
+                    handle!(self.values.insert(Some(x)))
+                },
+                None => {
+                    // Every row before the first null holds a value
+                    let len = self.len;
+                    self.validity.get_or_insert_with(|| bitvec![1; len]).push(false)
+                }
+            }
+            self.len += 1;
+            Ok(())
+        }
+        fn set(&mut self, idx: usize, data: Option<T>) -> Result<bool, String> {
+            handle!(check_index(idx, self.len));
+            let valid = self.validity.as_ref().is_none_or(|bits| bits[idx]);
+            match (valid, data) {
+                (false, None) => Ok(true),
+                // Only values are stored, so the encoding can overwrite a value with another one
+                (true, Some(x)) => self.values.set(self.rank(idx), Some(x)),
+                // Rows gaining or losing a value shift every stored value after them
+                _ => Ok(false)
+            }
+        }
+    }
+    impl<T: Encodable> Persist for Nullable<T> {
+        // The values are preceded by their count and size in bytes, so that a mapped file can be read without decoding them
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.validity.write(out));
+            handle!(self.values.len().write(out));
+            let size_pos = out.len();
+            handle!(0usize.write(out));
+            handle!(self.values.save(out));
+            let size = (out.len() - size_pos - size_of::<u64>()) as u64;
+            out[size_pos..size_pos + size_of::<u64>()].copy_from_slice(&size.to_le_bytes());
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            Nullable::load(CompressType::Uncompressed, r)
+        }
+    }
+    // Validity, number of values and values of a segment written by Nullable's save, read in place
+    fn mapped_parts<'a>(r: &mut Reader<'a>) -> Result<(Option<PackedBits<'a>>, usize, &'a [u8]), String> {
+        let validity = match handle!(u8::read(r)) {
+            0 => None,
+            1 => Some(handle!(PackedBits::read(r))),
+            t => return Err("Corrupt database file: unknown option tag ".to_string() + &t.to_string())
+        };
+        let num_values = handle!(usize::read(r));
+        let size = handle!(usize::read(r));
+        Ok((validity, num_values, handle!(r.take(size))))
+    }
+    type MappedParts<'a, T> = (Option<PackedBits<'a>>, Box<dyn ValuesView<'a, T> + 'a>);
+    // Segment of an attached table, left encoded in the mapped file and decoded straight from it as it's read
+    pub struct Mapped<T: Encodable> {
+        source: Rc<MappedFile>,
+        strategy: CompressType,
+        start: usize, // Bytes of the file holding the segment
+        end: usize,
+        len: usize,
+        values: PhantomData<T>
+    }
+    impl<T: Encodable> Mapped<T> {
+        // Find the bytes of a segment written by Nullable's save, without reading its values
+        pub fn load(source: &Rc<MappedFile>, strategy: CompressType, r: &mut Reader) -> Result<Mapped<T>, String> {
+            handle!(T::encoding(strategy));
+            let start = r.pos();
+            let (validity, num_values, _) = handle!(mapped_parts(r));
+            let len = match validity {
+                Some(bits) => {
+                    if bits.count_ones(0, bits.len) != num_values { return Err("Corrupt database file: mismatched validity bitmap".to_string()) }
+                    bits.len
+                },
+                None => num_values
+            };
+            Ok(Mapped { source: source.clone(), strategy, start, end: r.pos(), len, values: PhantomData })
+        }
+        // Validity bitmap and values of the segment
+        fn parts(&self) -> Result<MappedParts<'_, T>, String> {
+            let (validity, num_values, values) = handle!(mapped_parts(&mut Reader::new(&self.source.bytes()[self.start..self.end])));
+            Ok((validity, handle!(T::view(self.strategy, values, num_values))))
+        }
+        // Decode the whole segment into memory, so that it can be changed
+        pub fn decode(&self) -> Result<Nullable<T>, String> {
+            Nullable::load(self.strategy, &mut Reader::new(&self.source.bytes()[self.start..self.end]))
+        }
+    }
+    impl<T: Encodable> ReadColumn<T> for Mapped<T> {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            // A segment that can't be read has no rows, which check reports
+            let (validity, values) = match self.parts() {
+                Ok(parts) => parts,
+                Err(_) => return Box::new(std::iter::empty())
+            };
+            let mut vals = values.iter_from(0);
+            match validity {
+                Some(bits) => Box::new((0..bits.len).map_while(move |i| if bits.bit(i) { vals.next() } else { Some(None) })),
+                None => vals
+            }
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            let (validity, values) = handle!(self.parts());
+            match validity {
+                Some(bits) if !bits.bit(idx) => Ok(None),
+                Some(bits) => values.get(bits.count_ones(0, idx)),
+                None => values.get(idx)
+            }
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.len));
+            let (validity, values) = handle!(self.parts());
+            // Values of the rows in range are stored next to each other
+            let (first, num_values) = match validity {
+                Some(bits) => (bits.count_ones(0, start), bits.count_ones(start, end)),
+                None => (start, end - start)
+            };
+            let vals: Vec<Option<T>> = values.iter_from(first).take(num_values).collect();
+            if vals.len() != num_values { return Err("Corrupt mapped column".to_string()) }
+            match validity {
+                Some(bits) => {
+                    let mut vals = vals.into_iter();
+                    Ok((start..end).map(|i| if bits.bit(i) { vals.next().flatten() } else { None }).collect())
+                },
+                None => Ok(vals)
+            }
+        }
+        // The segment is written as it is in the mapped file
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.extend_from_slice(&self.source.bytes()[self.start..self.end]);
+            Ok(())
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.end - self.start
+        }
+        fn nulls(&self) -> BitVec {
+            match mapped_parts(&mut Reader::new(&self.source.bytes()[self.start..self.end])) {
+                Ok((Some(bits), _, _)) => (0..bits.len).map(|i| !bits.bit(i)).collect(),
+                _ => bitvec![0; self.len]
+            }
+        }
+        fn null_count(&self) -> usize {
+            match mapped_parts(&mut Reader::new(&self.source.bytes()[self.start..self.end])) {
+                Ok((Some(bits), _, _)) => bits.len - bits.count_ones(0, bits.len),
+                _ => 0
+            }
+        }
+    }
+    // Rows of a segment, either held in memory or left in a mapped file until they're changed
+    enum Segment<T: Encodable> {
+        Owned(Nullable<T>),
+        Mapped(Mapped<T>)
+    }
+    impl<T: Encodable> Segment<T> {
+        fn rows(&self) -> &dyn ReadColumn<T> {
+            match self {
+                Segment::Owned(seg) => seg,
+                Segment::Mapped(seg) => seg
+            }
+        }
+        // Segment to change, decoding it into memory first if it's mapped
+        fn owned(&mut self) -> Result<&mut Nullable<T>, String> {
+            if let Segment::Mapped(seg) = self {
+                let decoded = handle!(seg.decode());
+                *self = Segment::Owned(decoded)
+            }
+            match self {
+                Segment::Owned(seg) => Ok(seg),
+                Segment::Mapped(_) => Err("Mapped segment was not decoded".to_string())
+            }
+        }
+    }
+    // Checksum of a segment's values, which doesn't depend on their encoding, and the number of values decoded
+    fn values_checksum<T: Encodable>(seg: &dyn ReadColumn<T>) -> Result<(u64, usize), String> {
+        let mut bytes = Vec::new();
+        let mut n = 0;
+        for val in seg.iter() {
+            bytes.append(&mut handle!(value_bytes(&val)));
+            n += 1;
+        }
+        Ok((checksum(&bytes), n))
+    }
+    pub struct Segmented<T: Encodable> {
+        segments: Vec<(CompressType, Segment<T>)>, // Every segment is full except the last, which takes new rows
+        checksums: Vec<u64>, // Checksum of each full segment, and of the last one until it changes
+        strategy: CompressType, // Encoding of new segments
+        len: usize,
+        zones: ZoneMap<T>
+    }
+    impl<T: Encodable> Segmented<T> {
+        pub fn new() -> Segmented<T> {
+            Segmented {
+                segments: vec![(CompressType::Uncompressed, Segment::Owned(Nullable::new(T::uncompressed())))],
+                checksums: Vec::new(),
+                strategy: CompressType::Uncompressed,
+                len: 0,
+                zones: ZoneMap::new()
+            }
+        }
+        // Decode every segment, checking it against its length and checksum, and return the number of rows decoded
+        pub fn check(&self) -> Result<usize, String> {
+            let mut len = 0;
+            for (seg_idx, seg) in self.segments.iter().enumerate() {
+                let (sum, n) = handle!(values_checksum(seg.1.rows()));
+                if n != seg.1.rows().len() { return Err(format!("Segment {} decodes to {} rows instead of {}", seg_idx, n, seg.1.rows().len())) }
+                if self.checksums.get(seg_idx).is_some_and(|s| *s != sum) { return Err(format!("Segment {} does not match its checksum", seg_idx)) }
+                len += n;
+            }
+            if len != self.len { return Err(format!("Segments hold {} rows instead of {}", len, self.len)) }
+            Ok(len)
+        }
+        // Per-zone statistics of the column, saved with each segment
+        pub fn zones(&self) -> &ZoneMap<T> {
+            &self.zones
+        }
+        // Encoding of every segment, in row order
+        pub fn segment_strats(&self) -> Vec<CompressType> {
+            self.segments.iter().map(|seg| seg.0).collect()
+        }
+        // Encode every segment, and new segments, with a strategy
+        pub fn recompress(&mut self, strategy: CompressType) -> Result<(), String> {
+            // Fail before changing anything if the strategy isn't implemented
+            handle!(T::encoding(strategy));
+            for seg_idx in 0..self.segments.len() {
+                handle!(self.recompress_segment(seg_idx, strategy))
+            }
+            self.strategy = strategy;
+            Ok(())
+        }
+        // Encode a single segment with a strategy, leaving the rest of the column alone
+        pub fn recompress_segment(&mut self, seg_idx: usize, strategy: CompressType) -> Result<(), String> {
+            if self.segments[seg_idx].0 == strategy { return Ok(()) }
+            let mut new_seg = Nullable::new(handle!(T::encoding(strategy)));
+            let seg = self.segments[seg_idx].1.rows();
+            for item in handle!(seg.slice(0, seg.len())) {
+                handle!(new_seg.insert(item))
+            }
+            self.segments[seg_idx] = (strategy, Segment::Owned(new_seg));
+            Ok(())
+        }
+        // Encode each full segment with whichever candidate makes it smallest (earlier candidates win ties),
+        // and the last segment and new segments with a strategy
+        pub fn recompress_auto(&mut self, candidates: &[CompressType], strategy: CompressType) -> Result<(), String> {
+            handle!(T::encoding(strategy));
+            let last = self.segments.len() - 1;
+            for seg_idx in 0..last {
+                let seg = self.segments[seg_idx].1.rows();
+                let vals = handle!(seg.slice(0, seg.len()));
+                let mut best: Option<(CompressType, Nullable<T>)> = None;
+                for candidate in candidates {
+                    // Skip strategies the type doesn't implement
+                    let mut new_seg = match T::encoding(*candidate) {
+                        Ok(encoding) => Nullable::new(encoding),
+                        Err(_) => continue
+                    };
+                    for val in &vals {
+                        handle!(new_seg.insert(val.clone()))
+                    }
+                    if best.as_ref().is_none_or(|b| new_seg.size_in_bytes() < b.1.size_in_bytes()) { best = Some((*candidate, new_seg)) }
+                }
+                if let Some((choice, new_seg)) = best {
+                    if choice != self.segments[seg_idx].0 { self.segments[seg_idx] = (choice, Segment::Owned(new_seg)) }
+                }
+            }
+            handle!(self.recompress_segment(last, strategy));
+            self.strategy = strategy;
+            Ok(())
+        }
+        // Overwrite rows, given as (row index, value) in any order. Only segments holding an updated row are touched, and each keeps its strategy
+        pub fn update(&mut self, updates: &[(usize, Option<T>)]) -> Result<(), String> {
+            for (idx, _) in updates {
+                handle!(check_index(*idx, self.len))
+            }
+            let mut updates: Vec<&(usize, Option<T>)> = updates.iter().collect();
+            updates.sort_by_key(|u| u.0);
+            for seg_updates in updates.chunk_by(|u1, u2| u1.0 / SEGMENT_ROWS == u2.0 / SEGMENT_ROWS) {
+                let seg_idx = seg_updates[0].0 / SEGMENT_ROWS;
+                let seg_start = seg_idx * SEGMENT_ROWS;
+                // Overwrite in place for as long as the encoding can
+                let mut done = 0;
+                if let Segment::Owned(seg) = &mut self.segments[seg_idx].1 {
+                    while done < seg_updates.len() && handle!(seg.set(seg_updates[done].0 - seg_start, seg_updates[done].1.clone())) {
+                        done += 1
+                    }
+                }
+                // Otherwise re-encode the segment once, with the rest of its updates and the same strategy
+                if done < seg_updates.len() {
+                    let (strategy, seg) = &self.segments[seg_idx];
+                    let mut vals = handle!(seg.rows().slice(0, seg.rows().len()));
+                    for (idx, data) in &seg_updates[done..] {
+                        vals[idx - seg_start] = data.clone()
+                    }
+                    let mut new_seg = Nullable::new(handle!(T::encoding(*strategy)));
+                    for item in vals {
+                        handle!(new_seg.insert(item))
+                    }
+                    self.segments[seg_idx].1 = Segment::Owned(new_seg);
+                }
+                // The segment's checksum and zones are brought up to date
+                if seg_idx < self.checksums.len() { self.checksums[seg_idx] = handle!(values_checksum(self.segments[seg_idx].1.rows())).0 }
+                self.zones.rebuild(seg_start, self.segments[seg_idx].1.rows().iter());
+            }
+            Ok(())
+        }
+        // Move the rows of the last segment around, so that row i becomes the row at order[i]
+        pub fn reorder_last_segment(&mut self, order: &[usize]) -> Result<(), String> {
+            let last = self.segments.len() - 1;
+            let vals = handle!(self.segments[last].1.rows().slice(0, self.segments[last].1.rows().len()));
+            if order.len() != vals.len() { return Err("Reordering does not cover the last segment".to_string()) }
+            let strategy = self.segments[last].0;
+            let mut new_seg = Nullable::new(handle!(T::encoding(strategy)));
+            self.checksums.truncate(last);
+            // Zones of the segment are rebuilt too
+            self.zones.truncate(self.len - vals.len());
+            for i in order {
+                handle!(new_seg.insert(vals[*i].clone()));
+                self.zones.insert(&vals[*i])
+            }
+            self.segments[last] = (strategy, Segment::Owned(new_seg));
+            Ok(())
+        }
+        pub fn insert(&mut self, data: Option<T>) -> Result<(), String> {
+            // Start a new segment once the last one is full, full segments are never touched again
+            let last = self.segments.len() - 1;
+            if self.segments[last].1.rows().len() == SEGMENT_ROWS {
+                if self.checksums.len() == last { self.checksums.push(handle!(values_checksum(self.segments[last].1.rows())).0) }
+                self.segments.push((self.strategy, Segment::Owned(Nullable::new(handle!(T::encoding(self.strategy))))))
+            }
+            let last = self.segments.len() - 1;
+            let seg = handle!(self.segments[last].1.owned());
+            handle!(seg.insert(data.clone()));
+            self.zones.insert(&data);
+            // The last segment's checksum no longer holds once it changes
+            self.checksums.truncate(last);
+            self.len += 1;
+            Ok(())
+        }
+    }
+    impl<T: Encodable> ReadColumn<T> for Segmented<T> {
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Option<T>> + 'a> {
+            Box::new(self.segments.iter().flat_map(|seg| seg.1.rows().iter()))
+        }
+        fn len(&self) -> usize {
+            self.len
+        }
+        fn get(&self, idx: usize) -> Result<Option<T>, String> {
+            handle!(check_index(idx, self.len));
+            self.segments[idx / SEGMENT_ROWS].1.rows().get(idx % SEGMENT_ROWS)
+        }
+        fn slice(&self, start: usize, end: usize) -> Result<Vec<Option<T>>, String> {
+            handle!(check_range(start, end, self.len));
+            // Slice each segment the range covers
+            let mut data = Vec::new();
+            let mut pos = start;
+            while pos < end {
+                let seg_idx = pos / SEGMENT_ROWS;
+                let seg_start = seg_idx * SEGMENT_ROWS;
+                let seg_end = usize::min(end, seg_start + SEGMENT_ROWS);
+                data.append(&mut handle!(self.segments[seg_idx].1.rows().slice(pos - seg_start, seg_end - seg_start)));
+                pos = seg_end;
+            }
+            Ok(data)
+        }
+        fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            self.write(out)
+        }
+        fn size_in_bytes(&self) -> usize {
+            self.segments.iter().map(|seg| seg.1.rows().size_in_bytes()).sum()
+        }
+        fn select_in(&self, values: &[T]) -> BitVec {
+            let mut rows = BitVec::with_capacity(self.len);
+            for seg in &self.segments {
+                rows.extend_from_bitslice(&seg.1.rows().select_in(values))
+            }
+            rows
+        }
+        fn nulls(&self) -> BitVec {
+            let mut rows = BitVec::with_capacity(self.len);
+            for seg in &self.segments {
+                rows.extend_from_bitslice(&seg.1.rows().nulls())
+            }
+            rows
+        }
+        fn null_count(&self) -> usize {
+            self.segments.iter().map(|seg| seg.1.rows().null_count()).sum()
+        }
+    }
+    impl<T: Encodable> Persist for Segmented<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.strategy.write(out));
+            handle!(self.segments.len().write(out));
+            for (seg_idx, seg) in self.segments.iter().enumerate() {
+                handle!(seg.0.write(out));
+                let sum = match self.checksums.get(seg_idx) {
+                    Some(sum) => *sum,
+                    None => handle!(values_checksum(seg.1.rows())).0
+                };
+                handle!(sum.write(out));
+                handle!(seg.1.rows().save(out));
+                handle!(self.zones.write_range(seg_idx * SEGMENT_ROWS, seg_idx * SEGMENT_ROWS + seg.1.rows().len(), out))
+            }
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let strategy = handle!(CompressType::read(r));
+            handle!(T::encoding(strategy));
+            let num_segments = handle!(usize::read(r));
+            if num_segments == 0 { return Err("Corrupt database file: column without segments".to_string()) }
+            let mut segments = Vec::new();
+            let mut checksums = Vec::new();
+            let mut zones = ZoneMap::new();
+            let mut len = 0;
+            for seg_idx in 0..num_segments {
+                let seg_strategy = handle!(CompressType::read(r));
+                checksums.push(handle!(u64::read(r)));
+                // Segments of a mapped file are left in it, and only decoded as they're read
+                let seg = match r.source() {
+                    Some(source) => Segment::Mapped(handle!(Mapped::load(source, seg_strategy, r))),
+                    None => Segment::Owned(handle!(Nullable::load(seg_strategy, r)))
+                };
+                let seg_len = seg.rows().len();
+                // Only the last segment may be partly full
+                if seg_len > SEGMENT_ROWS || (seg_idx + 1 < num_segments && seg_len != SEGMENT_ROWS) { return Err("Corrupt database file: bad segment length".to_string()) }
+                // Zone statistics were saved after the segment, so they aren't rebuilt from its values
+                handle!(zones.read_range(seg_len, r));
+                len += seg_len;
+                segments.push((seg_strategy, seg));
+            }
+            Ok(Segmented { segments, checksums, strategy, len, zones })
+        }
+    }
     pub enum Column {
-        Number(Box<dyn ColumnInterface<f64>>),
-        Boolean(Box<dyn ColumnInterface<bool>>),
-        String(Box<dyn ColumnInterface<String>>)
+        Number(Box<Segmented<f64>>),
+        Boolean(Box<Segmented<bool>>),
+        String(Box<Segmented<String>>)
+    }
+    impl Column {
+        // Write column type followed by the segments of the column
+        pub fn save(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                Column::Number(cb) => {
+                    handle!(ColType::Number.write(out));
+                    cb.as_ref().save(out)
+                },
+                Column::Boolean(cb) => {
+                    handle!(ColType::Boolean.write(out));
+                    cb.as_ref().save(out)
+                },
+                Column::String(cb) => {
+                    handle!(ColType::String.write(out));
+                    cb.as_ref().save(out)
+                }
+            }
+        }
+        // Memory taken up by the encoded column
+        pub fn size_in_bytes(&self) -> usize {
+            match self {
+                Column::Number(cb) => cb.as_ref().size_in_bytes(),
+                Column::Boolean(cb) => cb.as_ref().size_in_bytes(),
+                Column::String(cb) => cb.as_ref().size_in_bytes()
+            }
+        }
+        // Encoding of every segment
+        pub fn segment_strats(&self) -> Vec<CompressType> {
+            match self {
+                Column::Number(cb) => cb.segment_strats(),
+                Column::Boolean(cb) => cb.segment_strats(),
+                Column::String(cb) => cb.segment_strats()
+            }
+        }
+        // Encode a column with a strategy, or fail without changing it if the type doesn't implement the strategy
+        pub fn recompress(&mut self, strategy: CompressType) -> Result<(), String> {
+            match self {
+                Column::Number(cb) => cb.recompress(strategy),
+                Column::Boolean(cb) => cb.recompress(strategy),
+                Column::String(cb) => cb.recompress(strategy)
+            }
+        }
+        // Encode each full segment with whichever candidate suits it best, and the rest of the column with a strategy
+        pub fn recompress_auto(&mut self, candidates: &[CompressType], strategy: CompressType) -> Result<(), String> {
+            match self {
+                Column::Number(cb) => cb.recompress_auto(candidates, strategy),
+                Column::Boolean(cb) => cb.recompress_auto(candidates, strategy),
+                Column::String(cb) => cb.recompress_auto(candidates, strategy)
+            }
+        }
+        // Rows that are null, read from the validity bitmaps without decoding any values
+        pub fn nulls(&self) -> BitVec {
+            match self {
+                Column::Number(cb) => cb.as_ref().nulls(),
+                Column::Boolean(cb) => cb.as_ref().nulls(),
+                Column::String(cb) => cb.as_ref().nulls()
+            }
+        }
+        pub fn null_count(&self) -> usize {
+            match self {
+                Column::Number(cb) => cb.as_ref().null_count(),
+                Column::Boolean(cb) => cb.as_ref().null_count(),
+                Column::String(cb) => cb.as_ref().null_count()
+            }
+        }
+        pub fn reorder_last_segment(&mut self, order: &[usize]) -> Result<(), String> {
+            match self {
+                Column::Number(cb) => cb.reorder_last_segment(order),
+                Column::Boolean(cb) => cb.reorder_last_segment(order),
+                Column::String(cb) => cb.reorder_last_segment(order)
+            }
+        }
+        // Decode the whole column, checking every segment, and return the number of rows
+        pub fn check(&self) -> Result<usize, String> {
+            match self {
+                Column::Number(cb) => cb.check(),
+                Column::Boolean(cb) => cb.check(),
+                Column::String(cb) => cb.check()
+            }
+        }
+        // Read back a column written by save, every segment records its own encoding
+        pub fn load(r: &mut Reader) -> Result<Column, String> {
+            Ok(match handle!(ColType::read(r)) {
+                ColType::Number => Column::Number(Box::new(handle!(Segmented::read(r)))),
+                ColType::Boolean => Column::Boolean(Box::new(handle!(Segmented::read(r)))),
+                ColType::String => Column::String(Box::new(handle!(Segmented::read(r))))
+            })
+        }
     }
 }
\ No newline at end of file
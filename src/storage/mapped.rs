@@ -0,0 +1,23 @@
+pub mod mapped {
+    use memmap2::Mmap;
+
+    // A database file mapped read-only into memory
+    pub struct MappedFile {
+        map: Mmap
+    }
+    impl MappedFile {
+        pub fn open(path: &String) -> Result<MappedFile, String> {
+            let file = match std::fs::File::open(path) {
+                Ok(f) => f,
+                Err(_) => return Err("Error opening ".to_string() + path)
+            };
+            // Attached files are only ever read, never modified through the map
+            let map = match unsafe { Mmap::map(&file) } {
+                Ok(m) => m,
+                Err(_) => return Err("Error mapping ".to_string() + path)
+            };
+            Ok(MappedFile { map })
+        }
+        pub fn bytes(&self) -> &[u8] { &self.map }
+    }
+}
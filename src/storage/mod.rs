@@ -1,3 +1,7 @@
 pub mod table;
 pub mod column;
+pub mod roaring;
+pub mod persist;
+pub mod wal;
+pub mod mapped;
 mod test;
\ No newline at end of file
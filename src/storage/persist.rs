@@ -0,0 +1,511 @@
+pub mod persist {
+    use bitvec::prelude::*;
+    use std::rc::Rc;
+    use crate::sqlscript::types::types::*;
+    use crate::engine::script::env::{Frame, Groups};
+    use super::super::mapped::mapped::MappedFile;
+
+    macro_rules! handle{
+        ($e:expr) => {
+            (match $e { Ok(v) => v, Err(s) => return Err(s) })
+        }
+    }
+
+    // Every database file starts with the magic bytes followed by the format version
+    pub const MAGIC: &[u8; 6] = b"ALEXDB";
+    pub const FORMAT_VERSION: u32 = 13;
+
+    #[derive(Clone)]
+    pub struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+        source: Option<&'a Rc<MappedFile>> // Set when reading straight from a mapped file
+    }
+    impl<'a> Reader<'a> {
+        pub fn new(data: &'a [u8]) -> Reader<'a> {
+            Reader { data, pos: 0, source: None }
+        }
+        pub fn mapped(file: &'a Rc<MappedFile>) -> Reader<'a> {
+            Reader { data: file.bytes(), pos: 0, source: Some(file) }
+        }
+        pub fn pos(&self) -> usize { self.pos }
+        pub fn source(&self) -> Option<&'a Rc<MappedFile>> { self.source }
+        // Every byte not read yet
+        pub fn rest(&self) -> &'a [u8] { &self.data[self.pos..] }
+        // Take the next n bytes from the stream
+        pub fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+            if n > self.data.len() - self.pos { return Err("Unexpected end of database file".to_string()) }
+            let bytes = &self.data[self.pos..(self.pos + n)];
+            self.pos += n;
+            Ok(bytes)
+        }
+    }
+
+    // Types that can be written to and read back from a database file
+    pub trait Persist: Sized {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String>;
+        fn read(r: &mut Reader) -> Result<Self, String>;
+        // Move past a value without building it
+        fn skip(r: &mut Reader) -> Result<(), String> {
+            Self::read(r).map(|_| ())
+        }
+    }
+
+    // FNV-1a hash, used to detect torn or corrupted records
+    pub fn checksum(bytes: &[u8]) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    fn bad_tag(tag: u8, kind: &str) -> String {
+        format!("Corrupt database file: unknown {} tag {}", kind, tag)
+    }
+
+    impl Persist for u8 {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.push(*self);
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> { Ok(handle!(r.take(1))[0]) }
+    }
+    impl Persist for u16 {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.extend_from_slice(&self.to_le_bytes());
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let mut bytes = [0u8; 2];
+            bytes.copy_from_slice(handle!(r.take(2)));
+            Ok(u16::from_le_bytes(bytes))
+        }
+    }
+    impl Persist for u32 {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.extend_from_slice(&self.to_le_bytes());
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(handle!(r.take(4)));
+            Ok(u32::from_le_bytes(bytes))
+        }
+    }
+    impl Persist for u64 {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.extend_from_slice(&self.to_le_bytes());
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(handle!(r.take(8)));
+            Ok(u64::from_le_bytes(bytes))
+        }
+    }
+    impl Persist for usize {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> { (*self as u64).write(out) }
+        fn read(r: &mut Reader) -> Result<Self, String> { Ok(handle!(u64::read(r)) as usize) }
+    }
+    impl Persist for f64 {
+        // Stored as raw bits so that every value (including NaN and -0.0) round-trips exactly
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> { self.to_bits().write(out) }
+        fn read(r: &mut Reader) -> Result<Self, String> { Ok(f64::from_bits(handle!(u64::read(r)))) }
+    }
+    impl Persist for bool {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> { (*self as u8).write(out) }
+        fn read(r: &mut Reader) -> Result<Self, String> { Ok(handle!(u8::read(r)) != 0) }
+    }
+    impl Persist for String {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.len().write(out));
+            out.extend_from_slice(self.as_bytes());
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let len = handle!(usize::read(r));
+            match String::from_utf8(handle!(r.take(len)).to_vec()) {
+                Ok(s) => Ok(s),
+                Err(_) => Err("Corrupt database file: invalid string".to_string())
+            }
+        }
+        fn skip(r: &mut Reader) -> Result<(), String> {
+            let len = handle!(usize::read(r));
+            r.take(len).map(|_| ())
+        }
+    }
+    impl<T: Persist> Persist for Option<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                None => {
+                    out.push(0);
+                    Ok(())
+                },
+                Some(x) => {
+                    out.push(1);
+                    x.write(out)
+                }
+            }
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(None),
+                1 => Ok(Some(handle!(T::read(r)))),
+                t => Err(bad_tag(t, "option"))
+            }
+        }
+        fn skip(r: &mut Reader) -> Result<(), String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(()),
+                1 => T::skip(r),
+                t => Err(bad_tag(t, "option"))
+            }
+        }
+    }
+    impl<T: Persist> Persist for Vec<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.len().write(out));
+            for item in self { handle!(item.write(out)) }
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let len = handle!(usize::read(r));
+            let mut data = Vec::new();
+            for _ in 0..len { data.push(handle!(T::read(r))) }
+            Ok(data)
+        }
+    }
+    impl<T: Persist> Persist for Rc<T> {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> { self.as_ref().write(out) }
+        fn read(r: &mut Reader) -> Result<Self, String> { Ok(Rc::new(handle!(T::read(r)))) }
+    }
+    impl<A: Persist, B: Persist> Persist for (A, B) {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.0.write(out));
+            self.1.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let a = handle!(A::read(r));
+            let b = handle!(B::read(r));
+            Ok((a, b))
+        }
+        fn skip(r: &mut Reader) -> Result<(), String> {
+            handle!(A::skip(r));
+            B::skip(r)
+        }
+    }
+    impl Persist for BitVec {
+        // Bit count followed by the bits packed eight to a byte
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.len().write(out));
+            let mut byte: u8 = 0;
+            for (i, bit) in self.iter().by_vals().enumerate() {
+                if bit { byte |= 1 << (i % 8) }
+                if i % 8 == 7 {
+                    out.push(byte);
+                    byte = 0;
+                }
+            }
+            if self.len() % 8 != 0 { out.push(byte) }
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let len = handle!(usize::read(r));
+            let bytes = handle!(r.take(len.div_ceil(8)));
+            let mut bits = BitVec::with_capacity(len);
+            for i in 0..len {
+                bits.push((bytes[i / 8] >> (i % 8)) & 1 == 1)
+            }
+            Ok(bits)
+        }
+    }
+    impl Persist for ColType {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.push(match self {
+                ColType::Number => 0,
+                ColType::String => 1,
+                ColType::Boolean => 2
+            });
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(ColType::Number),
+                1 => Ok(ColType::String),
+                2 => Ok(ColType::Boolean),
+                t => Err(bad_tag(t, "column type"))
+            }
+        }
+    }
+    impl Persist for CompressType {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.push(match self {
+                CompressType::Uncompressed => 0,
+                CompressType::Xor => 1,
+                CompressType::RunLength => 2,
+                CompressType::BitMap => 3,
+                CompressType::Dict => 4,
+                CompressType::ForPack => 5,
+                CompressType::Delta => 6,
+                CompressType::Auto => 7,
+                CompressType::Alp => 8
+            });
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(CompressType::Uncompressed),
+                1 => Ok(CompressType::Xor),
+                2 => Ok(CompressType::RunLength),
+                3 => Ok(CompressType::BitMap),
+                4 => Ok(CompressType::Dict),
+                5 => Ok(CompressType::ForPack),
+                6 => Ok(CompressType::Delta),
+                7 => Ok(CompressType::Auto),
+                8 => Ok(CompressType::Alp),
+                t => Err(bad_tag(t, "compression type"))
+            }
+        }
+    }
+    impl Persist for BopType {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.push(match self {
+                BopType::PlusBop => 0,
+                BopType::MinusBop => 1,
+                BopType::TimesBop => 2,
+                BopType::DivBop => 3,
+                BopType::GtBop => 4,
+                BopType::GteBop => 5,
+                BopType::LtBop => 6,
+                BopType::LteBop => 7,
+                BopType::EqBop => 8,
+                BopType::StrEqBop => 9,
+                BopType::LogOrBop => 10,
+                BopType::LogAndBop => 11,
+                BopType::ModBop => 12,
+                BopType::DotBop => 13,
+                BopType::InBop => 14,
+                BopType::IndexBop => 15
+            });
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(BopType::PlusBop),
+                1 => Ok(BopType::MinusBop),
+                2 => Ok(BopType::TimesBop),
+                3 => Ok(BopType::DivBop),
+                4 => Ok(BopType::GtBop),
+                5 => Ok(BopType::GteBop),
+                6 => Ok(BopType::LtBop),
+                7 => Ok(BopType::LteBop),
+                8 => Ok(BopType::EqBop),
+                9 => Ok(BopType::StrEqBop),
+                10 => Ok(BopType::LogOrBop),
+                11 => Ok(BopType::LogAndBop),
+                12 => Ok(BopType::ModBop),
+                13 => Ok(BopType::DotBop),
+                14 => Ok(BopType::InBop),
+                15 => Ok(BopType::IndexBop),
+                t => Err(bad_tag(t, "operator"))
+            }
+        }
+    }
+    impl Persist for UopType {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            out.push(match self {
+                UopType::NegUop => 0,
+                UopType::NotUop => 1,
+                UopType::NumUop => 2,
+                UopType::StrUop => 3,
+                UopType::BoolUop => 4,
+                UopType::FloorUop => 5,
+                UopType::CeilUop => 6
+            });
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(UopType::NegUop),
+                1 => Ok(UopType::NotUop),
+                2 => Ok(UopType::NumUop),
+                3 => Ok(UopType::StrUop),
+                4 => Ok(UopType::BoolUop),
+                5 => Ok(UopType::FloorUop),
+                6 => Ok(UopType::CeilUop),
+                t => Err(bad_tag(t, "operator"))
+            }
+        }
+    }
+    impl Persist for Expr {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                Expr::BopExpr(e1, bop, e2) => {
+                    out.push(0);
+                    handle!(e1.write(out));
+                    handle!(bop.write(out));
+                    e2.write(out)
+                },
+                Expr::UopExpr(uop, e1) => {
+                    out.push(1);
+                    handle!(uop.write(out));
+                    e1.write(out)
+                },
+                Expr::BlockExpr(b) => {
+                    out.push(2);
+                    b.write(out)
+                },
+                Expr::ValExpr(v) => {
+                    out.push(3);
+                    v.write(out)
+                },
+                Expr::IdentExpr(id) => {
+                    out.push(4);
+                    id.write(out)
+                },
+                Expr::CallExpr(e1, el) => {
+                    out.push(5);
+                    handle!(e1.write(out));
+                    el.write(out)
+                },
+                Expr::FunExpr(il, e1) => {
+                    out.push(6);
+                    handle!(il.write(out));
+                    e1.write(out)
+                },
+                Expr::CondExpr(e1, e2, e3) => {
+                    out.push(7);
+                    handle!(e1.write(out));
+                    handle!(e2.write(out));
+                    e3.write(out)
+                },
+                Expr::TupExpr(el) => {
+                    out.push(8);
+                    el.write(out)
+                },
+                Expr::QueryExpr(_) => Err("Subqueries cannot be saved, only their values".to_string())
+            }
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            Ok(match handle!(u8::read(r)) {
+                0 => {
+                    let e1 = handle!(Rc::<Expr>::read(r));
+                    let bop = handle!(BopType::read(r));
+                    Expr::BopExpr(e1, bop, handle!(Rc::<Expr>::read(r)))
+                },
+                1 => {
+                    let uop = handle!(UopType::read(r));
+                    Expr::UopExpr(uop, handle!(Rc::<Expr>::read(r)))
+                },
+                2 => Expr::BlockExpr(handle!(Block::read(r))),
+                3 => Expr::ValExpr(handle!(Val::read(r))),
+                4 => Expr::IdentExpr(handle!(String::read(r))),
+                5 => {
+                    let e1 = handle!(Rc::<Expr>::read(r));
+                    Expr::CallExpr(e1, handle!(ExprList::read(r)))
+                },
+                6 => {
+                    let il = handle!(IdentList::read(r));
+                    Expr::FunExpr(il, handle!(Rc::<Expr>::read(r)))
+                },
+                7 => {
+                    let e1 = handle!(Rc::<Expr>::read(r));
+                    let e2 = handle!(Rc::<Expr>::read(r));
+                    Expr::CondExpr(e1, e2, handle!(Rc::<Expr>::read(r)))
+                },
+                8 => Expr::TupExpr(handle!(ExprList::read(r))),
+                t => return Err(bad_tag(t, "expression"))
+            })
+        }
+    }
+    impl Persist for Block {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                Block::ExprBlock(e1) => {
+                    out.push(0);
+                    e1.write(out)
+                },
+                Block::StmtBlock(id, e1, b2) => {
+                    out.push(1);
+                    handle!(id.write(out));
+                    handle!(e1.write(out));
+                    b2.write(out)
+                }
+            }
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            Ok(match handle!(u8::read(r)) {
+                0 => Block::ExprBlock(handle!(Rc::<Expr>::read(r))),
+                1 => {
+                    let id = handle!(String::read(r));
+                    let e1 = handle!(Rc::<Expr>::read(r));
+                    Block::StmtBlock(id, e1, handle!(Rc::<Block>::read(r)))
+                },
+                t => return Err(bad_tag(t, "block"))
+            })
+        }
+    }
+    impl Persist for Frame {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> { self.data().write(out) }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let mut frame = Frame::new();
+            for (name, val) in handle!(Vec::<(String, Val)>::read(r)) {
+                frame.push(&name, &val)
+            }
+            Ok(frame)
+        }
+    }
+    impl Persist for Val {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                Val::NumVal(x) => {
+                    out.push(0);
+                    x.write(out)
+                },
+                Val::StrVal(s) => {
+                    out.push(1);
+                    s.write(out)
+                },
+                Val::BoolVal(b) => {
+                    out.push(2);
+                    b.write(out)
+                },
+                Val::UndefVal => 3u8.write(out),
+                Val::NullVal => 4u8.write(out),
+                Val::ClosureVal(fr, il, body) => {
+                    out.push(5);
+                    handle!(fr.write(out));
+                    handle!(il.write(out));
+                    body.write(out)
+                },
+                Val::TupVal(vals) => {
+                    out.push(6);
+                    vals.write(out)
+                },
+                Val::GroupVal(groups) => {
+                    out.push(7);
+                    groups.pairs().write(out)
+                }
+            }
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            Ok(match handle!(u8::read(r)) {
+                0 => Val::NumVal(handle!(f64::read(r))),
+                1 => Val::StrVal(handle!(String::read(r))),
+                2 => Val::BoolVal(handle!(bool::read(r))),
+                3 => Val::UndefVal,
+                4 => Val::NullVal,
+                5 => {
+                    let fr = handle!(Frame::read(r));
+                    let il = handle!(IdentList::read(r));
+                    Val::ClosureVal(fr, il, handle!(Rc::<Expr>::read(r)))
+                },
+                6 => Val::TupVal(handle!(Vec::<Rc<Val>>::read(r))),
+                7 => Val::GroupVal(Rc::new(Groups::from_pairs(handle!(Vec::<(Val, Val)>::read(r))))),
+                t => return Err(bad_tag(t, "value"))
+            })
+        }
+    }
+}
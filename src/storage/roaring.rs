@@ -0,0 +1,384 @@
+pub mod roaring {
+    use std::mem::size_of;
+    use super::super::persist::persist::{ Persist, Reader };
+
+    macro_rules! handle{
+        ($e:expr) => {
+            (match $e { Ok(v) => v, Err(s) => return Err(s) })
+        }
+    }
+
+    // Largest array container, past this a bitset takes less space
+    const ARRAY_MAX: usize = 4096;
+    // Words in a bitset container, one bit for each of the 65536 low values
+    const BITSET_WORDS: usize = 1024;
+
+    // Set bits sharing the same high 16 bits, stored as their low 16 bits
+    enum Container {
+        Array(Vec<u16>), // Sorted values
+        Bitset(Vec<u64>),
+        Run(Vec<(u16, u16)>) // Start of each run and its length minus one
+    }
+    impl Container {
+        fn len(&self) -> usize {
+            match self {
+                Container::Array(vals) => vals.len(),
+                Container::Bitset(words) => words.iter().map(|w| w.count_ones() as usize).sum(),
+                Container::Run(runs) => runs.iter().map(|r| r.1 as usize + 1).sum()
+            }
+        }
+        fn size_in_bytes(&self) -> usize {
+            match self {
+                Container::Array(vals) => vals.len() * size_of::<u16>(),
+                Container::Bitset(words) => words.len() * size_of::<u64>(),
+                Container::Run(runs) => runs.len() * size_of::<(u16, u16)>()
+            }
+        }
+        fn contains(&self, low: u16) -> bool {
+            match self {
+                Container::Array(vals) => vals.binary_search(&low).is_ok(),
+                Container::Bitset(words) => (words[low as usize / 64] >> (low % 64)) & 1 == 1,
+                Container::Run(runs) => {
+                    // Last run starting at or before low
+                    let i = runs.partition_point(|r| r.0 <= low);
+                    i > 0 && low - runs[i - 1].0 <= runs[i - 1].1
+                }
+            }
+        }
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=u16> + 'a> {
+            match self {
+                Container::Array(vals) => Box::new(vals.iter().copied()),
+                Container::Bitset(words) => Box::new((0..=u16::MAX).filter(|low| (words[*low as usize / 64] >> (low % 64)) & 1 == 1)),
+                Container::Run(runs) => Box::new(runs.iter().flat_map(|r| r.0..=r.0 + r.1))
+            }
+        }
+        // Add a value larger than any already set
+        fn push(&mut self, low: u16) {
+            match self {
+                Container::Array(vals) => {
+                    vals.push(low);
+                    if vals.len() > ARRAY_MAX { *self = self.to_bitset() }
+                },
+                Container::Bitset(words) => words[low as usize / 64] |= 1 << (low % 64),
+                Container::Run(runs) => {
+                    match runs.last_mut() {
+                        Some(r) if r.0 as usize + r.1 as usize + 1 == low as usize => r.1 += 1,
+                        _ => {
+                            runs.push((low, 0));
+                            // Runs only pay off while they're long, checked every time the number of runs doubles
+                            if runs.len() >= 16 && runs.len().is_power_of_two() && runs.len() * size_of::<(u16, u16)>() > self.len() * size_of::<u16>() { *self = self.to_array() }
+                        }
+                    }
+                }
+            }
+        }
+        // Add a value anywhere, returning whether it was new
+        fn insert(&mut self, low: u16) -> bool {
+            match self {
+                Container::Array(vals) => match vals.binary_search(&low) {
+                    Ok(_) => return false,
+                    Err(i) => {
+                        vals.insert(i, low);
+                        if vals.len() > ARRAY_MAX { *self = self.to_bitset() }
+                    }
+                },
+                Container::Bitset(words) => {
+                    if (words[low as usize / 64] >> (low % 64)) & 1 == 1 { return false }
+                    words[low as usize / 64] |= 1 << (low % 64)
+                },
+                Container::Run(runs) => {
+                    // Run starting just after low, and the one before it
+                    let i = runs.partition_point(|r| r.0 <= low);
+                    if i > 0 && low - runs[i - 1].0 <= runs[i - 1].1 { return false }
+                    let extends_prev = i > 0 && runs[i - 1].0 as usize + runs[i - 1].1 as usize + 1 == low as usize;
+                    let extends_next = i < runs.len() && runs[i].0 as usize == low as usize + 1;
+                    match (extends_prev, extends_next) {
+                        // Low joins two runs into one
+                        (true, true) => {
+                            runs[i - 1].1 += runs[i].1 + 2;
+                            runs.remove(i);
+                        },
+                        (true, false) => runs[i - 1].1 += 1,
+                        (false, true) => runs[i] = (low, runs[i].1 + 1),
+                        (false, false) => runs.insert(i, (low, 0))
+                    }
+                }
+            }
+            true
+        }
+        // Remove a value, returning whether it was there
+        fn remove(&mut self, low: u16) -> bool {
+            match self {
+                Container::Array(vals) => match vals.binary_search(&low) {
+                    Ok(i) => { vals.remove(i); },
+                    Err(_) => return false
+                },
+                Container::Bitset(words) => {
+                    if (words[low as usize / 64] >> (low % 64)) & 1 == 0 { return false }
+                    words[low as usize / 64] &= !(1 << (low % 64))
+                },
+                Container::Run(runs) => {
+                    let i = runs.partition_point(|r| r.0 <= low);
+                    if i == 0 || low - runs[i - 1].0 > runs[i - 1].1 { return false }
+                    let (start, len) = runs[i - 1];
+                    let end = start + len;
+                    // Shrink the run from either end, or split it in two around low
+                    if len == 0 { runs.remove(i - 1); }
+                    else if low == start { runs[i - 1] = (start + 1, len - 1) }
+                    else if low == end { runs[i - 1].1 -= 1 }
+                    else {
+                        runs[i - 1].1 = low - start - 1;
+                        runs.insert(i, (low + 1, end - low - 1))
+                    }
+                }
+            }
+            true
+        }
+        fn to_array(&self) -> Container {
+            Container::Array(self.iter().collect())
+        }
+        fn to_bitset(&self) -> Container {
+            let mut words = vec![0u64; BITSET_WORDS];
+            for low in self.iter() {
+                words[low as usize / 64] |= 1 << (low % 64)
+            }
+            Container::Bitset(words)
+        }
+        fn to_runs(&self) -> Container {
+            let mut runs: Vec<(u16, u16)> = Vec::new();
+            for low in self.iter() {
+                match runs.last_mut() {
+                    Some(r) if r.0 as usize + r.1 as usize + 1 == low as usize => r.1 += 1,
+                    _ => runs.push((low, 0))
+                }
+            }
+            Container::Run(runs)
+        }
+        // Whichever form of the same values is smallest
+        fn optimized(&self) -> Container {
+            let len = self.len();
+            let runs = self.to_runs();
+            if runs.size_in_bytes() < usize::min(len * size_of::<u16>(), BITSET_WORDS * size_of::<u64>()) { runs }
+            else if len > ARRAY_MAX { self.to_bitset() }
+            else { self.to_array() }
+        }
+    }
+    impl Persist for Container {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                Container::Array(vals) => {
+                    handle!(0u8.write(out));
+                    vals.write(out)
+                },
+                Container::Bitset(words) => {
+                    handle!(1u8.write(out));
+                    words.write(out)
+                },
+                Container::Run(runs) => {
+                    handle!(2u8.write(out));
+                    runs.write(out)
+                }
+            }
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(Container::Array(handle!(Vec::read(r)))),
+                1 => {
+                    let words: Vec<u64> = handle!(Vec::read(r));
+                    if words.len() != BITSET_WORDS { return Err("Corrupt database file: bad bitset container".to_string()) }
+                    Ok(Container::Bitset(words))
+                },
+                2 => Ok(Container::Run(handle!(Vec::read(r)))),
+                t => Err("Corrupt database file: unknown container tag ".to_string() + &t.to_string())
+            }
+        }
+    }
+    // Search over n sorted items, returning the first for which pred is false
+    fn partition_point(n: usize, pred: impl Fn(usize) -> bool) -> usize {
+        let (mut lo, mut hi) = (0, n);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(mid) { lo = mid + 1 } else { hi = mid }
+        }
+        lo
+    }
+    // Little-endian u16 number i of bytes
+    fn u16_at(bytes: &[u8], i: usize) -> u16 {
+        u16::from_le_bytes([bytes[2 * i], bytes[2 * i + 1]])
+    }
+    // Container read in place from the bytes it was written to
+    enum ContainerView<'a> {
+        Array(&'a [u8]), // Sorted values as u16s
+        Bitset(&'a [u8]), // Words as u64s, so bit i of the container is bit i % 8 of byte i / 8
+        Run(&'a [u8]) // Start and length minus one of each run as u16s
+    }
+    impl<'a> ContainerView<'a> {
+        fn read(r: &mut Reader<'a>) -> Result<ContainerView<'a>, String> {
+            let tag = handle!(u8::read(r));
+            let n = handle!(usize::read(r));
+            let item_size = match tag {
+                0 => size_of::<u16>(),
+                1 if n == BITSET_WORDS => size_of::<u64>(),
+                1 => return Err("Corrupt database file: bad bitset container".to_string()),
+                2 => size_of::<(u16, u16)>(),
+                t => return Err("Corrupt database file: unknown container tag ".to_string() + &t.to_string())
+            };
+            let bytes = match n.checked_mul(item_size) {
+                Some(size) => handle!(r.take(size)),
+                None => return Err("Unexpected end of database file".to_string())
+            };
+            Ok(match tag {
+                0 => ContainerView::Array(bytes),
+                1 => ContainerView::Bitset(bytes),
+                _ => ContainerView::Run(bytes)
+            })
+        }
+        fn contains(&self, low: u16) -> bool {
+            match self {
+                ContainerView::Array(vals) => {
+                    let i = partition_point(vals.len() / 2, |i| u16_at(vals, i) < low);
+                    i < vals.len() / 2 && u16_at(vals, i) == low
+                },
+                ContainerView::Bitset(words) => (words[low as usize / 8] >> (low % 8)) & 1 == 1,
+                ContainerView::Run(runs) => {
+                    // Last run starting at or before low
+                    let i = partition_point(runs.len() / 4, |i| u16_at(runs, 2 * i) <= low);
+                    i > 0 && low - u16_at(runs, 2 * i - 2) <= u16_at(runs, 2 * i - 1)
+                }
+            }
+        }
+        fn iter(&self) -> Box<dyn Iterator<Item=u16> + 'a> {
+            match *self {
+                ContainerView::Array(vals) => Box::new(vals.chunks_exact(2).map(|v| u16::from_le_bytes([v[0], v[1]]))),
+                ContainerView::Bitset(words) => Box::new((0..=u16::MAX).filter(move |low| (words[*low as usize / 8] >> (low % 8)) & 1 == 1)),
+                ContainerView::Run(runs) => Box::new(runs.chunks_exact(4).flat_map(|r| {
+                    let start = u16::from_le_bytes([r[0], r[1]]);
+                    start..=start.saturating_add(u16::from_le_bytes([r[2], r[3]]))
+                }))
+            }
+        }
+    }
+    // Compressed set of row indexes, split into containers by their high 16 bits
+    pub struct Roaring {
+        containers: Vec<(u16, Container)>, // Sorted by high bits
+        len: usize
+    }
+    impl Roaring {
+        pub fn new() -> Roaring {
+            Roaring {
+                containers: Vec::new(),
+                len: 0
+            }
+        }
+        // Number of set indexes
+        pub fn len(&self) -> usize {
+            self.len
+        }
+        // Add an index larger than any already set
+        pub fn push(&mut self, idx: u32) {
+            let (high, low) = ((idx >> 16) as u16, idx as u16);
+            if self.containers.last().is_none_or(|c| c.0 != high) {
+                // The previous container is done, so shrink it as much as possible
+                if let Some(c) = self.containers.last_mut() { c.1 = c.1.optimized() }
+                // Containers start out as runs, since rows holding the same value are often next to each other
+                self.containers.push((high, Container::Run(Vec::new())));
+            }
+            self.containers.last_mut().unwrap().1.push(low);
+            self.len += 1;
+        }
+        // Set an index anywhere, not just past the end
+        pub fn insert(&mut self, idx: u32) {
+            let (high, low) = ((idx >> 16) as u16, idx as u16);
+            let pos = match self.containers.binary_search_by_key(&high, |c| c.0) {
+                Ok(pos) => pos,
+                Err(pos) => {
+                    self.containers.insert(pos, (high, Container::Array(Vec::new())));
+                    pos
+                }
+            };
+            if self.containers[pos].1.insert(low) { self.len += 1 }
+        }
+        // Clear an index, dropping its container once empty
+        pub fn remove(&mut self, idx: u32) {
+            let (high, low) = ((idx >> 16) as u16, idx as u16);
+            if let Ok(pos) = self.containers.binary_search_by_key(&high, |c| c.0) {
+                if self.containers[pos].1.remove(low) { self.len -= 1 }
+                if self.containers[pos].1.len() == 0 { self.containers.remove(pos); }
+            }
+        }
+        pub fn contains(&self, idx: u32) -> bool {
+            let high = (idx >> 16) as u16;
+            match self.containers.binary_search_by_key(&high, |c| c.0) {
+                Ok(i) => self.containers[i].1.contains(idx as u16),
+                Err(_) => false
+            }
+        }
+        // Set indexes in increasing order
+        pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=u32> + 'a> {
+            Box::new(self.containers.iter().flat_map(|(high, c)| c.iter().map(move |low| ((*high as u32) << 16) | low as u32)))
+        }
+        // Set indexes from start to end (exclusive), in increasing order
+        pub fn iter_range<'a>(&'a self, start: u32, end: u32) -> Box<dyn Iterator<Item=u32> + 'a> {
+            // Skip containers entirely before the range
+            let first = self.containers.partition_point(|c| ((c.0 as u32) << 16) | 0xFFFF < start);
+            Box::new(self.containers[first..].iter()
+                .take_while(move |c| ((c.0 as u32) << 16) < end)
+                .flat_map(|(high, c)| c.iter().map(move |low| ((*high as u32) << 16) | low as u32))
+                .skip_while(move |idx| *idx < start)
+                .take_while(move |idx| *idx < end))
+        }
+        pub fn size_in_bytes(&self) -> usize {
+            self.containers.iter().map(|c| size_of::<(u16, Container)>() + c.1.size_in_bytes()).sum()
+        }
+    }
+    impl Persist for Roaring {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.containers.len().write(out));
+            for (high, c) in &self.containers {
+                handle!(high.write(out));
+                handle!(c.optimized().write(out))
+            }
+            Ok(())
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let mut containers = Vec::new();
+            let mut len = 0;
+            for _ in 0..handle!(usize::read(r)) {
+                let high = handle!(u16::read(r));
+                let c = handle!(Container::read(r));
+                len += c.len();
+                containers.push((high, c));
+            }
+            Ok(Roaring { containers, len })
+        }
+    }
+    // Roaring bitmap read in place from the bytes it was written to, for columns left in a mapped file
+    pub struct RoaringView<'a> {
+        containers: Vec<(u16, ContainerView<'a>)> // Sorted by high bits
+    }
+    impl<'a> RoaringView<'a> {
+        pub fn read(r: &mut Reader<'a>) -> Result<RoaringView<'a>, String> {
+            let mut containers = Vec::new();
+            for _ in 0..handle!(usize::read(r)) {
+                let high = handle!(u16::read(r));
+                containers.push((high, handle!(ContainerView::read(r))))
+            }
+            Ok(RoaringView { containers })
+        }
+        pub fn contains(&self, idx: u32) -> bool {
+            match self.containers.binary_search_by_key(&((idx >> 16) as u16), |c| c.0) {
+                Ok(i) => self.containers[i].1.contains(idx as u16),
+                Err(_) => false
+            }
+        }
+        // Set indexes from start to end (exclusive), in increasing order
+        pub fn iter_range(&self, start: u32, end: u32) -> Box<dyn Iterator<Item=u32> + '_> {
+            let first = self.containers.partition_point(|c| ((c.0 as u32) << 16) | 0xFFFF < start);
+            Box::new(self.containers[first..].iter()
+                .take_while(move |c| ((c.0 as u32) << 16) < end)
+                .flat_map(|(high, c)| c.iter().map(move |low| ((*high as u32) << 16) | low as u32))
+                .skip_while(move |idx| *idx < start)
+                .take_while(move |idx| *idx < end))
+        }
+    }
+}
@@ -1,7 +1,10 @@
 pub mod table {
+    use bitvec::prelude::*;
     use super::super::column::generic::*;
-    use crate::sqlscript::types::types::{ ColType, Val, Expr, CompressType, str_of_ctype };
+    use crate::sqlscript::types::types::{ ColType, Val, Expr, CompressType, BopType };
     use crate::engine::script::env::Environment;
+    use super::super::persist::persist::{ Persist, Reader };
+    use std::rc::Rc;
 
     macro_rules! handle{
         ($e:expr) => {
@@ -9,6 +12,30 @@ pub mod table {
         }
     }
 
+    // Rows sampled when estimating compressed sizes, taken as evenly spaced chunks so runs are kept intact
+    const AUTO_SAMPLE_ROWS: usize = 1024;
+    const AUTO_SAMPLE_CHUNKS: usize = 8;
+    // Strategies auto chooses between, earlier ones win ties
+    const AUTO_CANDIDATES: [CompressType; 8] = [CompressType::Uncompressed, CompressType::RunLength, CompressType::BitMap, CompressType::Dict, CompressType::ForPack, CompressType::Delta, CompressType::Alp, CompressType::Xor];
+
+    // Aggregate values replaced by a fold, along with the key of each grouped aggregate
+    pub type ReplacedAggregates = Vec<(Option<Val>, Option<Val>)>;
+    // A row read through a selection, along with its physical index
+    pub type SelectedRow = Result<(usize, Vec<Val>), String>;
+
+    // Order of cluster keys: nulls first, then increasing values (numbers use total_cmp, so NaNs go last)
+    fn cluster_ordering(v1: &Val, v2: &Val) -> std::cmp::Ordering {
+        match (v1, v2) {
+            (Val::NumVal(x), Val::NumVal(y)) => x.total_cmp(y),
+            (Val::StrVal(x), Val::StrVal(y)) => x.cmp(y),
+            (Val::BoolVal(x), Val::BoolVal(y)) => x.cmp(y),
+            (Val::NullVal, Val::NullVal) => std::cmp::Ordering::Equal,
+            (Val::NullVal, _) => std::cmp::Ordering::Less,
+            (_, Val::NullVal) => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal
+        }
+    }
+
     enum IterCont<'a> {
         Number(Box<dyn Iterator<Item=Option<f64>> + 'a>),
         Boolean(Box<dyn Iterator<Item=Option<bool>> + 'a>),
@@ -19,9 +46,14 @@ pub mod table {
         table: Vec<Column>,
         headers: Vec<String>,
         size: usize,
-        aggregates: Vec<(String, Val, Expr, Option<Expr>)>,
+        deleted: BitVec, // Tombstone for each row, rows are only removed when compacting
+        num_deleted: usize,
+        aggregates: Vec<(String, Val, Expr, Option<Expr>, Option<String>)>, // Grouped aggregates hold the value of each key
         computations: Vec<(String, Val, Expr)>,
         compression_strats: Vec<CompressType>,
+        cluster_key: Option<usize>, // Column rows are kept sorted on
+        insert_order: Vec<usize>, // When each row was inserted, relative to the others, only kept for clustered tables
+        source: Option<(String, String)> // File and table name an attached table is mapped from
     }
     impl Table {
         pub fn new() -> Table {
@@ -29,9 +61,14 @@ pub mod table {
                 table: Vec::new(),
                 headers: Vec::new(),
                 size: 0,
+                deleted: BitVec::new(),
+                num_deleted: 0,
                 aggregates: Vec::new(),
                 computations: Vec::new(),
                 compression_strats: Vec::new(),
+                cluster_key: None,
+                insert_order: Vec::new(),
+                source: None
             }
         }
         pub fn add_column(&mut self, name: &String, coltype: ColType, compression: CompressType) -> Result<(), String> {
@@ -42,18 +79,18 @@ pub mod table {
             // Push uncompressed column to table
             let col = match coltype {
                 ColType::Boolean => {
-                    let mut col_data: BoolCol = BoolCol::new();
-                    for _ in 0..self.size { col_data.insert(None) }
+                    let mut col_data: Segmented<bool> = Segmented::new();
+                    for _ in 0..self.size { handle!(col_data.insert(None)) }
                     Column::Boolean(Box::new(col_data))
                 },
                 ColType::Number => {
-                    let mut col_data: Uncompressed<f64> = Uncompressed::new();
-                    for _ in 0..self.size { col_data.insert(None) }
+                    let mut col_data: Segmented<f64> = Segmented::new();
+                    for _ in 0..self.size { handle!(col_data.insert(None)) }
                     Column::Number(Box::new(col_data))
                 },
                 ColType::String => {
-                    let mut col_data: Uncompressed<String> = Uncompressed::new();
-                    for _ in 0..self.size { col_data.insert(None) }
+                    let mut col_data: Segmented<String> = Segmented::new();
+                    for _ in 0..self.size { handle!(col_data.insert(None)) }
                     Column::String(Box::new(col_data))
                 }
             };
@@ -68,6 +105,8 @@ pub mod table {
         pub fn add_populated_column(&mut self, name: &String, col: Column, compression: CompressType) -> Result<(), String> {
             // Check that column does not already exist
             if self.headers.contains(&name) { return Err("Duplicate column ".to_string() + name) }
+            // Column is populated from live rows, so drop deleted rows first
+            handle!(self.compact());
             // Check length of column
             let len = match &col {
                 Column::Boolean(cbox) => cbox.as_ref().len(),
@@ -96,40 +135,235 @@ pub mod table {
             // Return column at index
             Ok(&self.table[handle!(self.header_idx(name))])
         }
+        pub fn get_value(&self, col_idx: usize, row_idx: usize) -> Result<Val, String> {
+            // Look up a single value without decoding the rest of the column
+            Ok(match &self.table[col_idx] {
+                Column::Boolean(cb) => match handle!(cb.as_ref().get(row_idx)) { Some(x) => Val::BoolVal(x), None => Val::NullVal },
+                Column::Number(cb) => match handle!(cb.as_ref().get(row_idx)) { Some(x) => Val::NumVal(x), None => Val::NullVal },
+                Column::String(cb) => match handle!(cb.as_ref().get(row_idx)) { Some(x) => Val::StrVal(x), None => Val::NullVal }
+            })
+        }
+        pub fn get_row(&self, row_idx: usize) -> Result<Vec<Val>, String> {
+            let mut row = Vec::new();
+            for i in 0..self.table.len() {
+                row.push(handle!(self.get_value(i, row_idx)))
+            }
+            Ok(row)
+        }
         pub fn add_row(&mut self, data: Vec<Val>) -> Result<(), String> {
             // Check that vector has appropriate number of items
             if data.len() != self.table.len() { return Err("Number of items inserted does not match number of fields".to_string()) }
-            // Add item to each column
-            for i in 0..data.len() {
-                match &mut self.table[i] {
-                    Column::Number(vec) => {
-                        match data[i] {
-                            Val::NumVal(x) => vec.as_mut().insert(Some(x)),
-                            Val::NullVal => vec.as_mut().insert(None),
-                            _ => return Err("Cannot insert non-number into a number column".to_string())
-                        }
-                    },
-                    Column::Boolean(vec) => {
-                        match data[i] {
-                            Val::BoolVal(x) => vec.as_mut().insert(Some(x)),
-                            Val::NullVal => vec.as_mut().insert(None),
+            // Check every item against its column before adding any, so that a failed insert leaves the table as it was
+            for (col, val) in self.table.iter().zip(data.iter()) {
+                match (col, val) {
+                    (_, Val::NullVal) | (Column::Number(_), Val::NumVal(_)) | (Column::Boolean(_), Val::BoolVal(_)) | (Column::String(_), Val::StrVal(_)) => (),
+                    (Column::Number(_), _) => return Err("Cannot insert non-number into a number column".to_string()),
+                    (Column::Boolean(_), _) => return Err("Cannot insert non-boolean into a boolean column".to_string()),
+                    (Column::String(_), _) => return Err("Cannot insert non-string into a string column".to_string())
+                }
+            }
+            // Add item to each column, every item is now a value of the column's type or null
+            for (col, val) in self.table.iter_mut().zip(data) {
+                match (col, val) {
+                    (Column::Number(vec), Val::NumVal(x)) => handle!(vec.as_mut().insert(Some(x))),
+                    (Column::Number(vec), _) => handle!(vec.as_mut().insert(None)),
+                    (Column::Boolean(vec), Val::BoolVal(x)) => handle!(vec.as_mut().insert(Some(x))),
+                    (Column::Boolean(vec), _) => handle!(vec.as_mut().insert(None)),
+                    (Column::String(vec), Val::StrVal(x)) => handle!(vec.as_mut().insert(Some(x))),
+                    (Column::String(vec), _) => handle!(vec.as_mut().insert(None))
+                }
+            }
+            // Increment size
+            if self.cluster_key.is_some() { self.insert_order.push(self.size) }
+            self.size += 1;
+            self.deleted.push(false);
+            // Sort the last segment on the cluster key once it's full
+            if self.cluster_key.is_some() && self.size.is_multiple_of(SEGMENT_ROWS) { handle!(self.cluster_segment()) }
+            // Columns waiting on auto compression get compressed once there are enough rows to sample
+            if self.size >= AUTO_SAMPLE_ROWS {
+                for i in 0..self.table.len() {
+                    if self.compression_strats[i] == CompressType::Auto { handle!(self.recompress(i, CompressType::Auto)) }
+                }
+            }
+            Ok(())
+        }
+        // Mark rows (by physical index) as deleted, compacting once more than half the rows are deleted
+        pub fn delete_rows(&mut self, rows: &Vec<usize>) -> Result<(), String> {
+            for row_idx in rows {
+                if *row_idx >= self.size { return Err("Row ".to_string() + &row_idx.to_string() + " does not exist") }
+                if !self.deleted[*row_idx] {
+                    self.deleted.set(*row_idx, true);
+                    self.num_deleted += 1;
+                }
+            }
+            if self.num_deleted * 2 > self.size {
+                handle!(self.compact());
+                // Segment boundaries have moved, so sort everything on the cluster key again
+                handle!(self.cluster());
+            }
+            Ok(())
+        }
+        // Rebuild every column without its deleted rows
+        pub fn compact(&mut self) -> Result<(), String> {
+            if self.num_deleted == 0 { return Ok(()) }
+            for col_idx in 0..self.table.len() {
+                // Copy live rows into an uncompressed column
+                let vals: Vec<Val> = self.column_values(col_idx).into_iter().enumerate().filter(|(i, _)| !self.deleted[*i]).map(|(_, v)| v).collect();
+                let col = handle!(Table::uncompressed_column(&self.table[col_idx], &vals));
+                handle!(self.replace_column(col_idx, col));
+            }
+            // Number the remaining rows by when they were inserted
+            if self.cluster_key.is_some() {
+                let live: Vec<usize> = self.insert_order.iter().enumerate().filter(|(i, _)| !self.deleted[*i]).map(|(_, n)| *n).collect();
+                let mut by_insert: Vec<usize> = (0..live.len()).collect();
+                by_insert.sort_by_key(|i| live[*i]);
+                self.insert_order = vec![0; live.len()];
+                for (n, i) in by_insert.into_iter().enumerate() {
+                    self.insert_order[i] = n
+                }
+            }
+            self.size -= self.num_deleted;
+            self.deleted = bitvec![0; self.size];
+            self.num_deleted = 0;
+            Ok(())
+        }
+        // Keep rows sorted on a column from now on, sorting the rows already in the table
+        pub fn set_cluster_key(&mut self, col_idx: usize) -> Result<(), String> {
+            if col_idx >= self.table.len() { return Err("Column ".to_string() + &col_idx.to_string() + " does not exist") }
+            if self.cluster_key.is_none() { self.insert_order = (0..self.size).collect() }
+            self.cluster_key = Some(col_idx);
+            self.cluster()
+        }
+        // Where to move rows start..size so they're sorted on the cluster key, rows with equal keys stay in insertion order
+        fn cluster_order(&self, key: usize, start: usize) -> Result<Vec<usize>, String> {
+            let keys = handle!(self.column_range(key, start, self.size));
+            let mut order: Vec<usize> = (0..keys.len()).collect();
+            order.sort_by(|a, b| cluster_ordering(&keys[*a], &keys[*b]).then(self.insert_order[start + a].cmp(&self.insert_order[start + b])));
+            Ok(order)
+        }
+        // Move the tombstones and insertion numbers of rows start..size along with the rows
+        fn reorder_rows(&mut self, start: usize, order: &[usize]) {
+            let deleted: BitVec = order.iter().map(|i| self.deleted[start + i]).collect();
+            let inserted: Vec<usize> = order.iter().map(|i| self.insert_order[start + i]).collect();
+            self.deleted[start..].copy_from_bitslice(&deleted);
+            self.insert_order[start..].copy_from_slice(&inserted);
+        }
+        // Sort the rows of the last segment on the cluster key, leaving the rest of the table alone
+        fn cluster_segment(&mut self) -> Result<(), String> {
+            let key = match self.cluster_key { Some(key) => key, None => return Ok(()) };
+            let order = handle!(self.cluster_order(key, self.size - SEGMENT_ROWS));
+            for col in self.table.iter_mut() {
+                handle!(col.reorder_last_segment(&order));
+            }
+            self.reorder_rows(self.size - SEGMENT_ROWS, &order);
+            Ok(())
+        }
+        // Sort every row on the cluster key
+        fn cluster(&mut self) -> Result<(), String> {
+            let key = match self.cluster_key { Some(key) => key, None => return Ok(()) };
+            let order = handle!(self.cluster_order(key, 0));
+            for col_idx in 0..self.table.len() {
+                let vals = self.column_values(col_idx);
+                let sorted: Vec<Val> = order.iter().map(|i| vals[*i].clone()).collect();
+                let col = handle!(Table::uncompressed_column(&self.table[col_idx], &sorted));
+                handle!(self.replace_column(col_idx, col));
+            }
+            self.reorder_rows(0, &order);
+            Ok(())
+        }
+        // Overwrite values in place, given as (row index, column index, value); nothing changes if any value has the wrong type.
+        // Only the segments holding an updated row are rewritten
+        pub fn update_rows(&mut self, updates: &Vec<(usize, usize, Val)>) -> Result<(), String> {
+            // Group new values by column, type checking everything before touching the table
+            let mut by_col: Vec<Vec<(usize, Val)>> = vec![Vec::new(); self.table.len()];
+            for (row_idx, col_idx, val) in updates {
+                if *row_idx >= self.size || self.deleted[*row_idx] { return Err("Row ".to_string() + &row_idx.to_string() + " does not exist") }
+                if *col_idx >= self.table.len() { return Err("Column ".to_string() + &col_idx.to_string() + " does not exist") }
+                match (&self.table[*col_idx], val) {
+                    (_, Val::NullVal) | (Column::Number(_), Val::NumVal(_)) | (Column::Boolean(_), Val::BoolVal(_)) | (Column::String(_), Val::StrVal(_)) => (),
+                    (Column::Number(_), _) => return Err("Cannot insert non-number into a number column".to_string()),
+                    (Column::Boolean(_), _) => return Err("Cannot insert non-boolean into a boolean column".to_string()),
+                    (Column::String(_), _) => return Err("Cannot insert non-string into a string column".to_string())
+                }
+                by_col[*col_idx].push((*row_idx, val.clone()));
+            }
+            for (col_idx, col_updates) in by_col.into_iter().enumerate() {
+                if col_updates.is_empty() { continue }
+                handle!(match &mut self.table[col_idx] {
+                    Column::Number(cb) => cb.update(&col_updates.into_iter().map(|(i, v)| (i, match v { Val::NumVal(x) => Some(x), _ => None })).collect::<Vec<_>>()),
+                    Column::Boolean(cb) => cb.update(&col_updates.into_iter().map(|(i, v)| (i, match v { Val::BoolVal(x) => Some(x), _ => None })).collect::<Vec<_>>()),
+                    Column::String(cb) => cb.update(&col_updates.into_iter().map(|(i, v)| (i, match v { Val::StrVal(x) => Some(x), _ => None })).collect::<Vec<_>>())
+                })
+            }
+            // Rows whose cluster key changed are moved to where they now belong
+            if self.cluster_key.is_some_and(|key| updates.iter().any(|u| u.1 == key)) { handle!(self.cluster()) }
+            Ok(())
+        }
+        // Values of a column from row start to end (exclusive), including deleted rows
+        fn column_range(&self, col_idx: usize, start: usize, end: usize) -> Result<Vec<Val>, String> {
+            Ok(match &self.table[col_idx] {
+                Column::Boolean(cb) => handle!(cb.as_ref().slice(start, end)).into_iter().map(|x| match x { Some(b) => Val::BoolVal(b), None => Val::NullVal }).collect(),
+                Column::Number(cb) => handle!(cb.as_ref().slice(start, end)).into_iter().map(|x| match x { Some(n) => Val::NumVal(n), None => Val::NullVal }).collect(),
+                Column::String(cb) => handle!(cb.as_ref().slice(start, end)).into_iter().map(|x| match x { Some(s) => Val::StrVal(s), None => Val::NullVal }).collect()
+            })
+        }
+        // Every value of a column, including deleted rows
+        fn column_values(&self, col_idx: usize) -> Vec<Val> {
+            match &self.table[col_idx] {
+                Column::Boolean(cb) => cb.as_ref().iter().map(|x| match x { Some(b) => Val::BoolVal(b), None => Val::NullVal }).collect(),
+                Column::Number(cb) => cb.as_ref().iter().map(|x| match x { Some(n) => Val::NumVal(n), None => Val::NullVal }).collect(),
+                Column::String(cb) => cb.as_ref().iter().map(|x| match x { Some(s) => Val::StrVal(s), None => Val::NullVal }).collect()
+            }
+        }
+        // Build an uncompressed column of the same type as col out of vals
+        fn uncompressed_column(col: &Column, vals: &Vec<Val>) -> Result<Column, String> {
+            Ok(match col {
+                Column::Boolean(_) => {
+                    let mut col_data: Segmented<bool> = Segmented::new();
+                    for val in vals {
+                        match val {
+                            Val::BoolVal(x) => handle!(col_data.insert(Some(*x))),
+                            Val::NullVal => handle!(col_data.insert(None)),
                             _ => return Err("Cannot insert non-boolean into a boolean column".to_string())
                         }
-                    },
-                    Column::String(vec) => {
-                        match data[i].clone() {
-                            Val::StrVal(x) => vec.as_mut().insert(Some(x)),
-                            Val::NullVal => vec.as_mut().insert(None),
+                    }
+                    Column::Boolean(Box::new(col_data))
+                },
+                Column::Number(_) => {
+                    let mut col_data: Segmented<f64> = Segmented::new();
+                    for val in vals {
+                        match val {
+                            Val::NumVal(x) => handle!(col_data.insert(Some(*x))),
+                            Val::NullVal => handle!(col_data.insert(None)),
+                            _ => return Err("Cannot insert non-number into a number column".to_string())
+                        }
+                    }
+                    Column::Number(Box::new(col_data))
+                },
+                Column::String(_) => {
+                    let mut col_data: Segmented<String> = Segmented::new();
+                    for val in vals {
+                        match val {
+                            Val::StrVal(x) => handle!(col_data.insert(Some(x.clone()))),
+                            Val::NullVal => handle!(col_data.insert(None)),
                             _ => return Err("Cannot insert non-string into a string column".to_string())
                         }
                     }
+                    Column::String(Box::new(col_data))
                 }
-            }
-            // Increment size
-            self.size += 1;
-            Ok(())
+            })
+        }
+        // Put an uncompressed column in place of an existing one, then compress it with the existing strategy
+        fn replace_column(&mut self, col_idx: usize, col: Column) -> Result<(), String> {
+            let strategy = self.compression_strats[col_idx];
+            self.table[col_idx] = col;
+            self.compression_strats[col_idx] = CompressType::Uncompressed;
+            self.recompress(col_idx, strategy)
         }
         pub fn get_headers(&self) -> &Vec<String> { &self.headers }
+        pub fn get_compression_strats(&self) -> &Vec<CompressType> { &self.compression_strats }
+        pub fn get_source(&self) -> &Option<(String, String)> { &self.source }
+        pub fn set_source(&mut self, source: Option<(String, String)>) { self.source = source }
         pub fn iter<'a>(&'a self) -> TableIterator<'a> {
             // Column iterators
             let mut citers = Vec::new();
@@ -142,14 +376,82 @@ pub mod table {
             };
             // Create iterator
             TableIterator {
-                col_iters: citers
+                col_iters: citers,
+                deleted: &self.deleted,
+                index: 0
+            }
+        }
+        // Iterate through rows in the order they were inserted, which differs from the physical order in clustered tables
+        pub fn iter_inserted<'a>(&'a self) -> Box<dyn Iterator<Item=Vec<Val>> + 'a> {
+            if self.cluster_key.is_none() { return Box::new(self.iter()) }
+            let mut rows: Vec<(usize, Vec<Val>)> = self.iter_indexed().map(|(i, row)| (self.insert_order[i], row)).collect();
+            rows.sort_by_key(|row| row.0);
+            Box::new(rows.into_iter().map(|row| row.1))
+        }
+        // Iterate through rows along with their physical index (for get_value and delete_rows)
+        pub fn iter_indexed<'a>(&'a self) -> IndexedTableIterator<'a> {
+            IndexedTableIterator(self.iter())
+        }
+        // Iterate through rows of the zones marked to keep, along with their physical index
+        pub fn iter_zones<'a>(&'a self, keep: Vec<bool>) -> ZoneTableIterator<'a> {
+            ZoneTableIterator { table: self, keep, zone: 0, rows: Vec::new().into_iter() }
+        }
+        // Which zones could hold rows satisfying every comparison, given as (column, op, literal, whether a null satisfies it)
+        pub fn zones_matching(&self, preds: &Vec<(usize, BopType, Val, bool)>) -> Vec<bool> {
+            let mut keep = vec![true; self.size.div_ceil(ZONE_ROWS)];
+            for (col_idx, op, val, null_matches) in preds {
+                for (zone_idx, k) in keep.iter_mut().enumerate() {
+                    *k = *k && match (&self.table[*col_idx], val) {
+                        (Column::Number(cb), Val::NumVal(c)) => cb.as_ref().zones().may_match(zone_idx, *op, c, *null_matches),
+                        (Column::String(cb), Val::StrVal(c)) => cb.as_ref().zones().may_match(zone_idx, *op, c, *null_matches),
+                        (Column::Boolean(cb), Val::BoolVal(c)) => cb.as_ref().zones().may_match(zone_idx, *op, c, *null_matches),
+                        // Comparing different types converts values, which doesn't keep their order
+                        _ => true
+                    }
+                }
+            }
+            keep
+        }
+        // Live rows of a zone, decoded through random access so that other zones aren't touched
+        fn zone_rows(&self, zone_idx: usize) -> Result<Vec<(usize, Vec<Val>)>, String> {
+            let start = zone_idx * ZONE_ROWS;
+            let end = usize::min(start + ZONE_ROWS, self.size);
+            let mut rows: Vec<(usize, Vec<Val>)> = (start..end).map(|i| (i, Vec::new())).collect();
+            for col_idx in 0..self.table.len() {
+                for (row, val) in rows.iter_mut().zip(handle!(self.column_range(col_idx, start, end))) {
+                    row.1.push(val)
+                }
             }
+            Ok(rows.into_iter().filter(|(i, _)| !self.deleted[*i]).collect())
+        }
+        // Rows whose value in a column equals one of vals, or none if a value isn't of the column's type
+        pub fn rows_in(&self, col_idx: usize, vals: &[Val]) -> Option<BitVec> {
+            if vals.is_empty() { return Some(bitvec![0; self.size]) }
+            Some(match &self.table[col_idx] {
+                Column::Number(cb) => cb.as_ref().select_in(&vals.iter().map(|v| match v { Val::NumVal(x) => Some(*x), _ => None }).collect::<Option<Vec<f64>>>()?),
+                Column::String(cb) => cb.as_ref().select_in(&vals.iter().map(|v| match v { Val::StrVal(x) => Some(x.clone()), _ => None }).collect::<Option<Vec<String>>>()?),
+                Column::Boolean(cb) => cb.as_ref().select_in(&vals.iter().map(|v| match v { Val::BoolVal(x) => Some(*x), _ => None }).collect::<Option<Vec<bool>>>()?)
+            })
+        }
+        // Rows that are null in a column, including deleted rows
+        pub fn null_rows(&self, col_idx: usize) -> BitVec {
+            self.table[col_idx].nulls()
+        }
+        // Number of null rows in a column, including deleted rows
+        pub fn null_count(&self, col_idx: usize) -> usize {
+            self.table[col_idx].null_count()
+        }
+        // Iterate through only the selected rows, along with their physical index
+        pub fn iter_selected<'a>(&'a self, selected: BitVec) -> SelectedTableIterator<'a> {
+            let rows: Vec<usize> = selected.iter_ones().take_while(|i| *i < self.size).filter(|i| !self.deleted[*i]).collect();
+            SelectedTableIterator { table: self, rows: rows.into_iter() }
         }
+        // Number of rows, not counting deleted rows
         pub fn len(&self) -> usize {
-            self.size
+            self.size - self.num_deleted
         }
-        pub fn add_aggregate(&mut self, name: &String, val: &Val, expr: &Expr, init: &Option<Expr>) {
-            self.aggregates.push((name.clone(), val.clone(), expr.clone(), init.clone()))
+        pub fn add_aggregate(&mut self, name: &String, val: &Val, expr: &Expr, init: &Option<Expr>, group: &Option<String>) {
+            self.aggregates.push((name.clone(), val.clone(), expr.clone(), init.clone(), group.clone()))
         }
         pub fn update_aggregates(&mut self, vals: &Vec<Val>) -> Result<(), String> {
             // Check length of values vector
@@ -162,7 +464,36 @@ pub mod table {
             };
             Ok(())
         }
-        pub fn get_aggregates(&self) -> &Vec<(String, Val, Expr, Option<Expr>)> {
+        // Fold a row's values into the aggregates, a grouped aggregate is given the key of the row and only that key's value changes.
+        // Gives back the values replaced, so they can be restored if the insert fails
+        pub fn fold_aggregates(&mut self, vals: Vec<(Option<Val>, Val)>) -> Result<ReplacedAggregates, String> {
+            // Check length of values vector
+            if vals.len() != self.aggregates.len() { return Err("Number of aggregate values given does not match number of aggregates stored".to_string()) }
+            let mut previous = Vec::new();
+            for (i, (key, val)) in vals.into_iter().enumerate() {
+                match (key, &mut self.aggregates[i].1) {
+                    (Some(key), Val::GroupVal(groups)) => {
+                        let old = Rc::make_mut(groups).set(&key, val);
+                        previous.push((Some(key), old))
+                    },
+                    (None, current) => previous.push((None, Some(std::mem::replace(current, val)))),
+                    _ => return Err("Aggregate ".to_string() + &self.aggregates[i].0 + " is not grouped")
+                }
+            }
+            Ok(previous)
+        }
+        // Undo fold_aggregates with the values it gave back
+        pub fn restore_aggregates(&mut self, previous: ReplacedAggregates) {
+            for (i, (key, old)) in previous.into_iter().enumerate() {
+                match (key, old, &mut self.aggregates[i].1) {
+                    (Some(key), Some(old), Val::GroupVal(groups)) => { Rc::make_mut(groups).set(&key, old); },
+                    (Some(key), None, Val::GroupVal(groups)) => Rc::make_mut(groups).remove(&key),
+                    (None, Some(old), current) => *current = old,
+                    _ => ()
+                }
+            }
+        }
+        pub fn get_aggregates(&self) -> &Vec<(String, Val, Expr, Option<Expr>, Option<String>)> {
             &self.aggregates
         }
         pub fn get_aggregate(&self, name: &String) -> Result<Val, String> {
@@ -214,81 +545,81 @@ pub mod table {
             Ok(self.computations[cmp_idx].1.clone())
         }
         pub fn recompress(&mut self, col_idx: usize, strategy: CompressType) -> Result<(), String> {
+            // Auto leaves the column uncompressed until there are enough rows to sample
+            if strategy == CompressType::Auto {
+                if self.size >= AUTO_SAMPLE_ROWS { return self.auto_compress(col_idx).map(|_| ()) }
+                if self.compression_strats[col_idx] != CompressType::Auto {
+                    handle!(self.recompress(col_idx, CompressType::Uncompressed));
+                    self.compression_strats[col_idx] = CompressType::Auto;
+                }
+                return Ok(())
+            }
             // If already compressing using chosen strategy, don't do anything
             if self.compression_strats[col_idx] == strategy { return Ok(()) }
+            // Otherwise, compress every segment accordingly
+            handle!(self.table[col_idx].recompress(strategy));
             // Change comression strategy array
             self.compression_strats[col_idx] = strategy;
-            // Otherwise, compress accordingly
-            match &mut self.table[col_idx] {
-                Column::Boolean(_) => {
-                    match strategy {
-                        CompressType::Uncompressed => (),
-                        _ => return Err("Boolean columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
-                    }
-                },
-                Column::Number(curr) => {
-                    let new_col: Box<dyn ColumnInterface<f64>> = match strategy {
-                        CompressType::BitMap => {
-                            let mut new_col: BitMap<f64> = BitMap::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        },
-                        CompressType::RunLength => {
-                            let mut new_col: RunLength<f64> = RunLength::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        },
-                        CompressType::Xor => {
-                            let mut new_col: XorCol = XorCol::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        },
-                        CompressType::Uncompressed => {
-                            let mut new_col: Uncompressed<f64> = Uncompressed::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        }
-                    };
-                    *curr = new_col;
-                },
-                Column::String(curr) => {
-                    let new_col: Box<dyn ColumnInterface<String>> = match strategy {
-                        CompressType::BitMap => {
-                            let mut new_col: BitMap<String> = BitMap::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        },
-                        CompressType::RunLength => {
-                            let mut new_col: RunLength<String> = RunLength::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        },
-                        CompressType::Uncompressed => {
-                            let mut new_col: Uncompressed<String> = Uncompressed::new();
-                            for item in curr.as_ref().iter() {
-                                new_col.insert(item);
-                            }
-                            Box::new(new_col)
-                        },
-                        _ => return Err("String columns do not implement compression type ".to_string() + &str_of_ctype(strategy))
-                    };
-                    *curr = new_col;
+            Ok(())
+        }
+        // Estimate the size in bytes of a column under every strategy its type supports, by compressing a sample of it
+        pub fn estimate_sizes(&self, col_idx: usize) -> Result<Vec<(CompressType, usize)>, String> {
+            let vals = self.column_values(col_idx);
+            let sample: Vec<Val> = match vals.len() <= AUTO_SAMPLE_ROWS {
+                true => vals.clone(),
+                false => {
+                    let chunk = AUTO_SAMPLE_ROWS / AUTO_SAMPLE_CHUNKS;
+                    let stride = vals.len() / AUTO_SAMPLE_CHUNKS;
+                    (0..AUTO_SAMPLE_CHUNKS).flat_map(|i| vals[i * stride..i * stride + chunk].iter().cloned()).collect()
                 }
             };
+            let mut sizes = Vec::new();
+            for strategy in AUTO_CANDIDATES {
+                // Compress the sample on its own, skipping strategies the column type doesn't implement
+                let mut scratch = Table::new();
+                scratch.table.push(handle!(Table::uncompressed_column(&self.table[col_idx], &sample)));
+                scratch.compression_strats.push(CompressType::Uncompressed);
+                if scratch.recompress(0, strategy).is_err() { continue }
+                let bytes = scratch.table[0].size_in_bytes();
+                // Scale up to the whole column
+                sizes.push((strategy, match sample.len() {
+                    0 => bytes,
+                    n => bytes * vals.len() / n
+                }));
+            }
+            Ok(sizes)
+        }
+        // Compress a column with the strategy estimated to be smallest, returning the estimates and the choice.
+        // Full segments are each encoded with the strategy that makes them smallest instead
+        pub fn auto_compress(&mut self, col_idx: usize) -> Result<(Vec<(CompressType, usize)>, CompressType), String> {
+            let sizes = handle!(self.estimate_sizes(col_idx));
+            let mut best = sizes[0];
+            for size in &sizes {
+                if size.1 < best.1 { best = *size }
+            }
+            handle!(self.table[col_idx].recompress_auto(&AUTO_CANDIDATES, best.0));
+            self.compression_strats[col_idx] = best.0;
+            Ok((sizes, best.0))
+        }
+        // Check that the row bookkeeping agrees with the number of rows
+        pub fn check(&self) -> Result<(), String> {
+            if self.deleted.len() != self.size { return Err(format!("{} tombstones for {} rows", self.deleted.len(), self.size)) }
+            if self.deleted.count_ones() != self.num_deleted { return Err(format!("{} rows are deleted but {} are counted", self.deleted.count_ones(), self.num_deleted)) }
+            if self.cluster_key.is_some() && self.insert_order.len() != self.size { return Err(format!("Insertion order covers {} rows instead of {}", self.insert_order.len(), self.size)) }
             Ok(())
         }
+        // Decode a column, checking its segments and that it holds every row
+        pub fn check_column(&self, col_idx: usize) -> Result<(), String> {
+            let len = handle!(self.table[col_idx].check());
+            if len != self.size { return Err(format!("Column holds {} rows instead of {}", len, self.size)) }
+            Ok(())
+        }
+        // Rows, encoded bytes and the bytes the same column would take uncompressed
+        pub fn storage_stats(&self, col_idx: usize) -> Result<(usize, usize, usize), String> {
+            let vals = self.column_values(col_idx);
+            let uncompressed = handle!(Table::uncompressed_column(&self.table[col_idx], &vals));
+            Ok((vals.len(), self.table[col_idx].size_in_bytes(), uncompressed.size_in_bytes()))
+        }
         pub fn get_col_types(&self) -> Vec<ColType> {
             let mut col_types = Vec::new();
             for column in &self.table {
@@ -302,12 +633,101 @@ pub mod table {
         }
     }
 
+    impl Persist for Table {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            handle!(self.headers.write(out));
+            handle!(self.size.write(out));
+            handle!(self.deleted.write(out));
+            handle!(self.compression_strats.write(out));
+            // Aggregates
+            handle!(self.aggregates.len().write(out));
+            for ag in &self.aggregates {
+                handle!(ag.0.write(out));
+                handle!(ag.1.write(out));
+                handle!(ag.2.write(out));
+                handle!(ag.3.write(out));
+                handle!(ag.4.write(out));
+            }
+            // Computations
+            handle!(self.computations.len().write(out));
+            for cmp in &self.computations {
+                handle!(cmp.0.write(out));
+                handle!(cmp.1.write(out));
+                handle!(cmp.2.write(out));
+            }
+            // Columns, in their compressed form
+            for col in &self.table {
+                handle!(col.save(out))
+            }
+            handle!(self.cluster_key.write(out));
+            self.insert_order.write(out)
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            let headers: Vec<String> = handle!(Vec::read(r));
+            let size = handle!(usize::read(r));
+            let deleted = handle!(BitVec::read(r));
+            if deleted.len() != size { return Err("Corrupt database file: mismatched row count".to_string()) }
+            let num_deleted = deleted.count_ones();
+            let compression_strats: Vec<CompressType> = handle!(Vec::read(r));
+            if compression_strats.len() != headers.len() { return Err("Corrupt database file: mismatched column count".to_string()) }
+            // Aggregates
+            let mut aggregates = Vec::new();
+            for _ in 0..handle!(usize::read(r)) {
+                let name = handle!(String::read(r));
+                let val = handle!(Val::read(r));
+                let expr = handle!(Expr::read(r));
+                let init = handle!(Option::<Expr>::read(r));
+                aggregates.push((name, val, expr, init, handle!(Option::<String>::read(r))));
+            }
+            // Computations
+            let mut computations = Vec::new();
+            for _ in 0..handle!(usize::read(r)) {
+                let name = handle!(String::read(r));
+                let val = handle!(Val::read(r));
+                computations.push((name, val, handle!(Expr::read(r))));
+            }
+            // Columns
+            let mut table = Vec::new();
+            for _ in &compression_strats {
+                table.push(handle!(Column::load(r)));
+            }
+            let cluster_key: Option<usize> = handle!(Option::read(r));
+            let insert_order: Vec<usize> = handle!(Vec::read(r));
+            if cluster_key.is_some_and(|key| key >= headers.len() || insert_order.len() != size) { return Err("Corrupt database file: bad cluster key".to_string()) }
+            Ok(Table {
+                table,
+                headers,
+                size,
+                deleted,
+                num_deleted,
+                aggregates,
+                computations,
+                compression_strats,
+                cluster_key,
+                insert_order,
+                source: None
+            })
+        }
+    }
+
     pub struct TableIterator<'a> {
-        col_iters: Vec<IterCont<'a>>
+        col_iters: Vec<IterCont<'a>>,
+        deleted: &'a BitVec,
+        index: usize
     }
-    impl<'a> Iterator for TableIterator<'a> {
-        type Item = Vec<Val>;
-        fn next(&mut self) -> Option<Self::Item> {
+    impl<'a> TableIterator<'a> {
+        // Next live row and its physical index
+        fn next_indexed(&mut self) -> Option<(usize, Vec<Val>)> {
+            loop {
+                let row = match self.next_row() {
+                    Some(r) => r,
+                    None => return None
+                };
+                self.index += 1;
+                if !self.deleted[self.index - 1] { return Some((self.index - 1, row)) }
+            }
+        }
+        fn next_row(&mut self) -> Option<Vec<Val>> {
             let mut retvec = Vec::new();
             for col_iter in self.col_iters.iter_mut() {
                 match col_iter {
@@ -331,4 +751,51 @@ pub mod table {
             Some(retvec)
         }
     }
-}
\ No newline at end of file
+    impl<'a> Iterator for TableIterator<'a> {
+        type Item = Vec<Val>;
+        fn next(&mut self) -> Option<Self::Item> {
+            self.next_indexed().map(|r| r.1)
+        }
+    }
+    pub struct IndexedTableIterator<'a>(TableIterator<'a>);
+    impl<'a> Iterator for IndexedTableIterator<'a> {
+        type Item = (usize, Vec<Val>);
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next_indexed()
+        }
+    }
+    pub struct ZoneTableIterator<'a> {
+        table: &'a Table,
+        keep: Vec<bool>,
+        zone: usize,
+        rows: std::vec::IntoIter<(usize, Vec<Val>)>
+    }
+    impl<'a> Iterator for ZoneTableIterator<'a> {
+        type Item = SelectedRow;
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                if let Some(row) = self.rows.next() { return Some(Ok(row)) }
+                // Move on to the next zone that is kept
+                while self.zone < self.keep.len() && !self.keep[self.zone] { self.zone += 1 }
+                if self.zone >= self.keep.len() { return None }
+                let zone = self.zone;
+                self.zone += 1;
+                match self.table.zone_rows(zone) {
+                    Ok(rows) => self.rows = rows.into_iter(),
+                    Err(s) => return Some(Err(s))
+                }
+            }
+        }
+    }
+    pub struct SelectedTableIterator<'a> {
+        table: &'a Table,
+        rows: std::vec::IntoIter<usize>
+    }
+    impl<'a> Iterator for SelectedTableIterator<'a> {
+        type Item = SelectedRow;
+        fn next(&mut self) -> Option<Self::Item> {
+            let row_idx = self.rows.next()?;
+            Some(self.table.get_row(row_idx).map(|row| (row_idx, row)))
+        }
+    }
+}
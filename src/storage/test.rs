@@ -1,17 +1,23 @@
 #[cfg(test)]
 mod test_column {
     use super::super::column::generic::*;
+    use crate::sqlscript::types::types::BopType;
+    // Whether min and max are the smallest and largest values of a zone, as far as comparisons with it can tell
+    fn zone_bounds<T: Clone + PartialOrd>(zones: &ZoneMap<T>, zone_idx: usize, min: &T, max: &T) -> bool {
+        zones.may_match(zone_idx, BopType::LteBop, min, false) && !zones.may_match(zone_idx, BopType::LtBop, min, false) &&
+            zones.may_match(zone_idx, BopType::GteBop, max, false) && !zones.may_match(zone_idx, BopType::GtBop, max, false)
+    }
 
     #[test]
     fn run_length_1() -> Result<(), String> {
         // New run length column
         let mut col: RunLength<f64> = RunLength::new();
         // Insert some new values
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(4.0));
-        col.insert(Some(4.0));
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(4.0))?;
+        col.insert(Some(4.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -26,11 +32,11 @@ mod test_column {
         // New run length column
         let mut col: RunLength<f64> = RunLength::new();
         // Insert some new values
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(None);
-        col.insert(None);
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(None)?;
+        col.insert(None)?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -48,11 +54,11 @@ mod test_column {
         // New run length column
         let mut col: BitMap<f64> = BitMap::new();
         // Insert some new values
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(4.0));
-        col.insert(Some(4.0));
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(4.0))?;
+        col.insert(Some(4.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -67,11 +73,11 @@ mod test_column {
         // New run length column
         let mut col: BitMap<f64> = BitMap::new();
         // Insert some new values
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(None);
-        col.insert(None);
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(None)?;
+        col.insert(None)?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -85,11 +91,149 @@ mod test_column {
         Ok(())
     }
     #[test]
+    fn bitmap_3() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // Many distinct values
+        let mut col: BitMap<f64> = BitMap::new();
+        for i in 0..20000 {
+            col.insert(if i % 11 == 0 { None } else { Some((i % 5000) as f64) })?
+        }
+        // Floats are stored apart by their bits, but selected the way == compares them
+        col.insert(Some(-0.0))?;
+        col.insert(Some(f64::NAN))?;
+        col.insert(Some(f64::NAN))?;
+        assert!(col.get(20000)?.unwrap().is_sign_negative());
+        assert!(col.get(20002)?.unwrap().is_nan());
+        assert_eq!(col.select_in(&[0.0]).count_ones(), 4);
+        assert_eq!(col.select_in(&[f64::NAN]).count_ones(), 0);
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let loaded = BitMap::<f64>::read(&mut Reader::new(&out))?;
+        let vals = loaded.uncompress();
+        assert_eq!(vals.len(), 20003);
+        for (i, val) in vals.iter().take(20000).enumerate() {
+            assert_eq!(*val, if i % 11 == 0 { None } else { Some((i % 5000) as f64) })
+        }
+        assert_eq!(loaded.slice(4999, 5001)?, vec![Some(4999.0), Some(0.0)]);
+        // Rows past the end of the column are rejected when read
+        let mut col: BitMap<f64> = BitMap::new();
+        for i in 0..10 {
+            col.insert(if i < 5 { None } else { Some(1.0) })?
+        }
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let len_pos = out.len() - 8;
+        out[len_pos..].copy_from_slice(&6u64.to_le_bytes());
+        assert!(BitMap::<f64>::read(&mut Reader::new(&out)).is_err());
+        Ok(())
+    }
+    #[test]
+    fn dict_1() -> Result<(), String> {
+        // New dictionary column
+        let mut col: DictCol<String> = DictCol::new();
+        // Insert some new values
+        col.insert(Some("rock".to_string()))?;
+        col.insert(Some("jazz".to_string()))?;
+        col.insert(None)?;
+        col.insert(Some("rock".to_string()))?;
+        // Uncompress col
+        let col_unc = col.uncompress();
+        // Check values
+        assert_eq!(col_unc.len(), 4);
+        assert_eq!(col_unc[0].as_ref().unwrap(), "rock");
+        assert_eq!(col_unc[1].as_ref().unwrap(), "jazz");
+        assert!(col_unc[2].is_none());
+        assert_eq!(col_unc[3].as_ref().unwrap(), "rock");
+        Ok(())
+    }
+    #[test]
+    fn dict_2() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // New dictionary column
+        let mut col: DictCol<String> = DictCol::new();
+        // Insert enough distinct values that codes have to be widened several times
+        for i in 0..40 {
+            col.insert(Some((i % 20).to_string()))?;
+            if i % 7 == 0 { col.insert(None)? }
+        }
+        // Write and read back
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let mut loaded: DictCol<String> = DictCol::read(&mut Reader::new(&out))?;
+        // Continue inserting after loading
+        loaded.insert(Some("5".to_string()))?;
+        loaded.insert(Some("new".to_string()))?;
+        // Check values
+        let col_unc = loaded.uncompress();
+        assert_eq!(col_unc.len(), 48);
+        assert_eq!(col_unc[0].as_ref().unwrap(), "0");
+        assert!(col_unc[1].is_none());
+        assert_eq!(col_unc[45].as_ref().unwrap(), "19");
+        assert_eq!(col_unc[46].as_ref().unwrap(), "5");
+        assert_eq!(col_unc[47].as_ref().unwrap(), "new");
+        Ok(())
+    }
+    #[test]
+    fn forpack_1() -> Result<(), String> {
+        // New frame-of-reference column
+        let mut col: ForPack = ForPack::new();
+        // Insert integral values spanning several blocks, with some exceptions mixed in
+        for i in 0..300 {
+            col.insert(Some(1990.0 + (i % 30) as f64))?;
+        }
+        col.insert(None)?;
+        col.insert(Some(2.5))?;
+        col.insert(Some(f64::NAN))?;
+        col.insert(Some(-0.0))?;
+        col.insert(Some(1e300))?;
+        // Uncompress col
+        let col_unc = col.uncompress();
+        // Check values
+        assert_eq!(col_unc.len(), 305);
+        assert_eq!(col_unc[0].unwrap(), 1990.0);
+        assert_eq!(col_unc[129].unwrap(), 1999.0);
+        assert_eq!(col_unc[299].unwrap(), 2019.0);
+        assert!(col_unc[300].is_none());
+        assert_eq!(col_unc[301].unwrap(), 2.5);
+        assert!(col_unc[302].unwrap().is_nan());
+        assert!(col_unc[303].unwrap().is_sign_negative());
+        assert_eq!(col_unc[304].unwrap(), 1e300);
+        Ok(())
+    }
+    #[test]
+    fn forpack_2() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // New frame-of-reference column
+        let mut col: ForPack = ForPack::new();
+        // Exceptions inside full blocks
+        for i in 0..200 {
+            col.insert(if i % 50 == 0 { None } else if i % 61 == 0 { Some(i as f64 + 0.25) } else { Some(-(i as f64)) })?;
+        }
+        // Write and read back
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let mut loaded = ForPack::read(&mut Reader::new(&out))?;
+        // Continue inserting after loading
+        for _ in 0..100 {
+            loaded.insert(Some(7.0))?;
+        }
+        // Check values
+        let col_unc = loaded.uncompress();
+        assert_eq!(col_unc.len(), 300);
+        assert!(col_unc[0].is_none());
+        assert_eq!(col_unc[1].unwrap(), -1.0);
+        assert!(col_unc[150].is_none());
+        assert_eq!(col_unc[122].unwrap(), 122.25);
+        assert_eq!(col_unc[199].unwrap(), -199.0);
+        assert_eq!(col_unc[299].unwrap(), 7.0);
+        Ok(())
+    }
+    #[test]
     fn xor_1() -> Result<(), String> {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(None);
+        col.insert(None)?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -105,11 +249,11 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(None);
-        col.insert(None);
-        col.insert(None);
-        col.insert(None);
-        col.insert(None);
+        col.insert(None)?;
+        col.insert(None)?;
+        col.insert(None)?;
+        col.insert(None)?;
+        col.insert(None)?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -125,7 +269,7 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(5.0));
+        col.insert(Some(5.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -138,8 +282,8 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(None);
-        col.insert(Some(5.0));
+        col.insert(None)?;
+        col.insert(Some(5.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -152,9 +296,9 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -168,9 +312,9 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(4.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
+        col.insert(Some(4.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -184,9 +328,9 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(0.0));
-        col.insert(Some(5.0));
-        col.insert(Some(3.0));
+        col.insert(Some(0.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(3.0))?;
         // Uncompress col
         let col_unc = col.uncompress();
         // Check values
@@ -200,21 +344,21 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(0.0));
-        col.insert(Some(14.0));
-        col.insert(Some(3.0));
-        col.insert(Some(-1110.2292));
-        col.insert(Some(3.0));
-        col.insert(Some(-3.0));
-        col.insert(Some(5.0));
-        col.insert(Some(5.0));
-        col.insert(Some(45.654));
-        col.insert(Some(5.0));
-        col.insert(Some(13.6765));
-        col.insert(Some(100000.7));
-        col.insert(Some(0.0));
-        col.insert(Some(45.0));
-        col.insert(Some(0.0000005));
+        col.insert(Some(0.0))?;
+        col.insert(Some(14.0))?;
+        col.insert(Some(3.0))?;
+        col.insert(Some(-1110.2292))?;
+        col.insert(Some(3.0))?;
+        col.insert(Some(-3.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(45.654))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(13.6765))?;
+        col.insert(Some(100000.7))?;
+        col.insert(Some(0.0))?;
+        col.insert(Some(45.0))?;
+        col.insert(Some(0.0000005))?;
         // Uncompress col5
         let col_unc = col.uncompress();
         // Check values
@@ -230,21 +374,21 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(0.0));
-        col.insert(Some(14.0));
-        col.insert(Some(3.0));
-        col.insert(None);
-        col.insert(Some(3.0));
-        col.insert(Some(-3.0));
-        col.insert(Some(5.0));
-        col.insert(None);
-        col.insert(Some(45.654));
-        col.insert(Some(5.0));
-        col.insert(Some(13.6765));
-        col.insert(Some(100000.7));
-        col.insert(Some(0.0));
-        col.insert(Some(45.0));
-        col.insert(None);
+        col.insert(Some(0.0))?;
+        col.insert(Some(14.0))?;
+        col.insert(Some(3.0))?;
+        col.insert(None)?;
+        col.insert(Some(3.0))?;
+        col.insert(Some(-3.0))?;
+        col.insert(Some(5.0))?;
+        col.insert(None)?;
+        col.insert(Some(45.654))?;
+        col.insert(Some(5.0))?;
+        col.insert(Some(13.6765))?;
+        col.insert(Some(100000.7))?;
+        col.insert(Some(0.0))?;
+        col.insert(Some(45.0))?;
+        col.insert(None)?;
         // Uncompress col5
         let col_unc = col.uncompress();
         // Check values
@@ -260,12 +404,12 @@ mod test_column {
         // New run length column
         let mut col: XorCol = XorCol::new();
         // Insert some new values
-        col.insert(Some(5.0));
-        col.insert(Some(6.0));
-        col.insert(Some(8.0));
-        col.insert(Some(13.0));
-        col.insert(Some(2.0));
-        col.insert(Some(5.0));
+        col.insert(Some(5.0))?;
+        col.insert(Some(6.0))?;
+        col.insert(Some(8.0))?;
+        col.insert(Some(13.0))?;
+        col.insert(Some(2.0))?;
+        col.insert(Some(5.0))?;
         // Uncompress col5
         let col_unc = col.uncompress();
         // Check values
@@ -277,11 +421,197 @@ mod test_column {
         Ok(())
     }
     #[test]
+    fn delta_1() -> Result<(), String> {
+        // New delta column
+        let mut col: DeltaCol = DeltaCol::new();
+        // Regular intervals
+        for i in 0..1000 {
+            col.insert(Some(1700000000000.0 + (i * 1000) as f64))?;
+        }
+        // Check values, regular intervals take about one bit each
+        let col_unc = col.uncompress();
+        assert_eq!(col_unc.len(), 1000);
+        assert_eq!(col_unc[0].unwrap(), 1700000000000.0);
+        assert_eq!(col_unc[999].unwrap(), 1700000999000.0);
+        let mut out = Vec::new();
+        col.save(&mut out)?;
+        assert!(out.len() < 200);
+        Ok(())
+    }
+    #[test]
+    fn delta_2() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // New delta column
+        let mut col: DeltaCol = DeltaCol::new();
+        // Irregular intervals, nulls, non-integral and negative values
+        col.insert(None)?;
+        col.insert(Some(10.0))?;
+        col.insert(Some(20.0))?;
+        col.insert(Some(25.0))?;
+        col.insert(None)?;
+        col.insert(Some(-1000.0))?;
+        col.insert(Some(0.5))?;
+        col.insert(Some(3.0))?;
+        col.insert(Some(-0.0))?;
+        col.insert(Some(f64::INFINITY))?;
+        // Write and read back
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let mut loaded = DeltaCol::read(&mut Reader::new(&out))?;
+        // Continue inserting after loading
+        loaded.insert(Some(4.0))?;
+        loaded.insert(Some(5.0))?;
+        // Check values
+        let col_unc = loaded.uncompress();
+        assert_eq!(col_unc.len(), 12);
+        assert!(col_unc[0].is_none());
+        assert_eq!(col_unc[1].unwrap(), 10.0);
+        assert_eq!(col_unc[2].unwrap(), 20.0);
+        assert_eq!(col_unc[3].unwrap(), 25.0);
+        assert!(col_unc[4].is_none());
+        assert_eq!(col_unc[5].unwrap(), -1000.0);
+        assert_eq!(col_unc[6].unwrap(), 0.5);
+        assert_eq!(col_unc[7].unwrap(), 3.0);
+        assert!(col_unc[8].unwrap().is_sign_negative());
+        assert_eq!(col_unc[9].unwrap(), f64::INFINITY);
+        assert_eq!(col_unc[10].unwrap(), 4.0);
+        assert_eq!(col_unc[11].unwrap(), 5.0);
+        Ok(())
+    }
+    // Check get and slice against the full decoded column
+    fn check_random_access<T: Clone + PartialEq + std::fmt::Debug>(col: &dyn ColumnInterface<T>) -> Result<(), String> {
+        let col_unc = col.uncompress();
+        assert_eq!(col.len(), col_unc.len());
+        for i in 0..col_unc.len() {
+            assert_eq!(col.get(i)?, col_unc[i]);
+        }
+        for (start, end) in [(0, 0), (0, 1), (3, 70), (63, 64), (64, 200), (150, col_unc.len()), (col_unc.len(), col_unc.len())] {
+            assert_eq!(col.slice(start, end)?, col_unc[start..end].to_vec());
+        }
+        // Out of bounds
+        assert!(col.get(col_unc.len()).is_err());
+        assert!(col.slice(5, col_unc.len() + 1).is_err());
+        assert!(col.slice(5, 4).is_err());
+        Ok(())
+    }
+    #[test]
+    fn alp_1() -> Result<(), String> {
+        // New alp column
+        let mut col: AlpCol = AlpCol::new();
+        let mut xor: XorCol = XorCol::new();
+        // Prices with two decimal places, enough for a couple of blocks
+        for i in 0..2100 {
+            let x = Some(((i * 37) % 5000 + 99) as f64 / 100.0);
+            col.insert(x)?;
+            xor.insert(x)?;
+        }
+        // Check values, decimals take far fewer bits than under xor
+        let col_unc = col.uncompress();
+        assert_eq!(col_unc.len(), 2100);
+        assert_eq!(col_unc[0].unwrap(), 0.99);
+        assert_eq!(col_unc[1500].unwrap(), 5.99);
+        assert!(col_unc == xor.uncompress());
+        assert!(col.size_in_bytes() * 2 < xor.size_in_bytes());
+        Ok(())
+    }
+    #[test]
+    fn alp_2() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // Every bit pattern comes back exactly, whether encoded or kept as an exception
+        let specials = [f64::NAN, -f64::NAN, f64::from_bits(0x7ff8000000000123), -0.0, 0.0, f64::INFINITY, f64::NEG_INFINITY, f64::MIN_POSITIVE, 5e-324, f64::MAX, 1e300, 0.1 + 0.2, std::f64::consts::PI];
+        let expected: Vec<Option<f64>> = (0..2500).map(|i| if i % 97 == 0 { None } else if i % 31 == 0 { Some(specials[i % specials.len()]) } else { Some(-(i as f64) * 0.125) }).collect();
+        let mut col: AlpCol = AlpCol::new();
+        for x in &expected {
+            col.insert(*x)?;
+        }
+        let same = |vals: Vec<Option<f64>>| vals.len() == expected.len() && vals.iter().zip(expected.iter()).all(|(a, b)| a.map(f64::to_bits) == b.map(f64::to_bits));
+        assert!(same(col.uncompress()));
+        assert!(same((0..2500).map(|i| col.get(i).unwrap()).collect()));
+        assert!(same(col.slice(0, 2500)?));
+        // Write and read back
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let mut loaded = AlpCol::read(&mut Reader::new(&out))?;
+        assert!(same(loaded.uncompress()));
+        // Continue inserting after loading
+        loaded.insert(Some(-0.0))?;
+        assert!(loaded.get(2500)?.unwrap().is_sign_negative());
+        Ok(())
+    }
+    #[test]
+    fn random_access_num() -> Result<(), String> {
+        let mut cols: Vec<Box<dyn ColumnInterface<f64>>> = vec![
+            Box::new(Uncompressed::new()),
+            Box::new(RunLength::new()),
+            Box::new(BitMap::new()),
+            Box::new(XorCol::new()),
+            Box::new(ForPack::new()),
+            Box::new(DeltaCol::new()),
+            Box::new(AlpCol::new())
+        ];
+        for col in cols.iter_mut() {
+            for i in 0..300 {
+                col.insert(if i % 17 == 0 { None } else if i % 23 == 0 { Some(i as f64 / 4.0) } else { Some(((i / 5) * 3) as f64) })?
+            }
+            check_random_access(col.as_ref())?;
+        }
+        Ok(())
+    }
+    #[test]
+    fn random_access_str() -> Result<(), String> {
+        let mut cols: Vec<Box<dyn ColumnInterface<String>>> = vec![
+            Box::new(Uncompressed::new()),
+            Box::new(RunLength::new()),
+            Box::new(BitMap::new()),
+            Box::new(DictCol::new())
+        ];
+        for col in cols.iter_mut() {
+            col.insert(None)?;
+            for i in 0..250 {
+                col.insert(if i % 13 == 0 { None } else { Some(((i / 4) % 9).to_string()) })?
+            }
+            check_random_access(col.as_ref())?;
+        }
+        Ok(())
+    }
+    #[test]
+    fn random_access_bool() -> Result<(), String> {
+        let mut col = BoolCol::new();
+        for i in 0..250 {
+            col.insert(if i % 7 == 0 { None } else { Some(i % 3 == 0) })?
+        }
+        check_random_access(&col)
+    }
+    #[test]
+    fn random_access_persist() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // Checkpoints have to be rebuilt when loading
+        let mut xor_col = XorCol::new();
+        let mut delta_col = DeltaCol::new();
+        for i in 0..200 {
+            xor_col.insert(if i % 9 == 0 { None } else { Some(i as f64 * 1.5) })?;
+            delta_col.insert(if i % 9 == 0 { None } else { Some((i * i) as f64) })?;
+        }
+        let mut out = Vec::new();
+        xor_col.write(&mut out)?;
+        delta_col.write(&mut out)?;
+        let mut r = Reader::new(&out);
+        let mut xor_col = XorCol::read(&mut r)?;
+        let mut delta_col = DeltaCol::read(&mut r)?;
+        // Keep inserting past the next checkpoint
+        for i in 0..10 {
+            xor_col.insert(Some(i as f64))?;
+            delta_col.insert(Some(i as f64))?;
+        }
+        check_random_access(&xor_col)?;
+        check_random_access(&delta_col)
+    }
+    #[test]
     fn bool_1() -> Result<(), String> {
         // New run length column
         let mut col: BoolCol = BoolCol::new();
         // Insert some new values
-        col.insert(Some(true));
+        col.insert(Some(true))?;
         // Uncompress col5
         let col_unc = col.uncompress();
         // Check values
@@ -294,8 +624,8 @@ mod test_column {
         // New run length column
         let mut col: BoolCol = BoolCol::new();
         // Insert some new values
-        col.insert(Some(true));
-        col.insert(Some(false));
+        col.insert(Some(true))?;
+        col.insert(Some(false))?;
         // Uncompress col5
         let col_unc = col.uncompress();
         // Check values
@@ -309,9 +639,9 @@ mod test_column {
         // New run length column
         let mut col: BoolCol = BoolCol::new();
         // Insert some new values
-        col.insert(Some(true));
-        col.insert(None);
-        col.insert(Some(false));
+        col.insert(Some(true))?;
+        col.insert(None)?;
+        col.insert(Some(false))?;
         // Uncompress col5
         let col_unc = col.uncompress();
         // Check values
@@ -321,13 +651,344 @@ mod test_column {
         assert_eq!(col_unc[2].unwrap(), false);
         Ok(())
     }
+    #[test]
+    fn bool_4() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // Sorted flags, with a few nulls
+        let mut cols: Vec<Box<dyn ColumnInterface<bool>>> = vec![Box::new(BoolCol::new()), Box::new(RunLength::new()), Box::new(BitMap::new())];
+        for col in cols.iter_mut() {
+            for i in 0..5000 {
+                col.insert(if i % 1000 == 999 { None } else { Some(i < 3000) })?
+            }
+            check_random_access(col.as_ref())?;
+            assert_eq!(col.select_in(&[false]).count_ones(), 1998);
+        }
+        // Long runs are smaller run length encoded than as a bitvector
+        assert!(cols[1].size_in_bytes() * 2 < cols[0].size_in_bytes());
+        assert!(cols[2].size_in_bytes() < cols[0].size_in_bytes());
+        // Write and read back
+        let mut out = Vec::new();
+        cols[1].save(&mut out)?;
+        cols[2].save(&mut out)?;
+        let mut r = Reader::new(&out);
+        assert!(RunLength::<bool>::read(&mut r)?.uncompress() == cols[0].uncompress());
+        assert!(BitMap::<bool>::read(&mut r)?.uncompress() == cols[0].uncompress());
+        Ok(())
+    }
+    #[test]
+    fn size_in_bytes() -> Result<(), String> {
+        // Long runs of a few values
+        let mut unc: Uncompressed<f64> = Uncompressed::new();
+        let mut runlen: RunLength<f64> = RunLength::new();
+        let mut bitmap: BitMap<f64> = BitMap::new();
+        let mut forpack = ForPack::new();
+        let mut delta = DeltaCol::new();
+        let mut xor = XorCol::new();
+        let mut bools = BoolCol::new();
+        for i in 0..1000 {
+            let x = Some((i / 250) as f64);
+            unc.insert(x)?;
+            runlen.insert(x)?;
+            bitmap.insert(x)?;
+            forpack.insert(x)?;
+            delta.insert(x)?;
+            xor.insert(x)?;
+            bools.insert(Some(i % 2 == 0))?;
+        }
+        assert_eq!(unc.size_in_bytes(), 1000 * 16);
+        assert_eq!(runlen.size_in_bytes(), 4 * (24 + 8));
+        assert_eq!(bools.size_in_bytes(), 2 * 16 * 8);
+        for size in [bitmap.size_in_bytes(), forpack.size_in_bytes(), delta.size_in_bytes(), xor.size_in_bytes()] {
+            assert!(size > 0 && size < unc.size_in_bytes())
+        }
+        // Strings count what they own on the heap
+        let mut unc: Uncompressed<String> = Uncompressed::new();
+        let mut dict: DictCol<String> = DictCol::new();
+        for i in 0..100 {
+            let x = if i % 10 == 0 { None } else { Some("value".to_string() + &(i % 4).to_string()) };
+            unc.insert(x.clone())?;
+            dict.insert(x)?;
+        }
+        assert_eq!(unc.size_in_bytes(), 100 * 24 + 90 * 6);
+        assert!(dict.size_in_bytes() < unc.size_in_bytes());
+        Ok(())
+    }
+    #[test]
+    fn zone_maps() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        use crate::sqlscript::types::types::CompressType;
+        // Every encoding of a column keeps the same statistics
+        for strategy in [CompressType::Uncompressed, CompressType::RunLength, CompressType::BitMap, CompressType::ForPack, CompressType::Delta, CompressType::Xor, CompressType::Alp] {
+            let mut col: Segmented<f64> = Segmented::new();
+            col.recompress(strategy)?;
+            for i in 0..(ZONE_ROWS + 10) {
+                col.insert(if i % 100 == 0 { None } else if i == 5 { Some(f64::NAN) } else { Some((i / 10) as f64) })?
+            }
+            let zones = col.zones();
+            assert!(zone_bounds(zones, 0, &0.0, &(((ZONE_ROWS - 1) / 10) as f64)));
+            assert!(zone_bounds(zones, 1, &((ZONE_ROWS / 10) as f64), &((ZONE_ROWS / 10 + 1) as f64)));
+            // Only the first zone holds nulls
+            assert!(!zones.may_match(0, BopType::GtBop, &200.0, false));
+            assert!(zones.may_match(0, BopType::GtBop, &200.0, true));
+            assert!(!zones.may_match(1, BopType::GtBop, &200.0, true));
+            assert!(zones.may_match(1, BopType::EqBop, &102.0, false));
+            assert!(!zones.may_match(1, BopType::LtBop, &102.0, false));
+        }
+        // Statistics are saved with each segment
+        let mut dict: Segmented<String> = Segmented::new();
+        dict.recompress(CompressType::Dict)?;
+        for i in 0..(SEGMENT_ROWS + 20) {
+            dict.insert(Some(((b'a' + (i % 5) as u8) as char).to_string()))?
+        }
+        let mut out = Vec::new();
+        dict.write(&mut out)?;
+        let loaded = Segmented::<String>::read(&mut Reader::new(&out))?;
+        let last = SEGMENT_ROWS / ZONE_ROWS;
+        assert!(zone_bounds(loaded.zones(), last, &"a".to_string(), &"e".to_string()));
+        assert!(!loaded.zones().may_match(last, BopType::StrEqBop, &"f".to_string(), true));
+        // New rows extend the loaded statistics
+        let mut loaded = loaded;
+        loaded.insert(None)?;
+        assert!(loaded.zones().may_match(last, BopType::StrEqBop, &"f".to_string(), true));
+        // A zone map that doesn't cover its segment is rejected
+        let mut col: Segmented<f64> = Segmented::new();
+        col.insert(Some(1.0))?;
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let zones_at = out.len() - (8 + 9 + 9 + 8);
+        out[zones_at] = 2;
+        assert!(Segmented::<f64>::read(&mut Reader::new(&out)).is_err());
+        Ok(())
+    }
+    #[test]
+    fn select_in() -> Result<(), String> {
+        use bitvec::prelude::*;
+        // Every encoding selects the same rows
+        let mut nums: Vec<Box<dyn ColumnInterface<f64>>> = vec![Box::new(Uncompressed::new()), Box::new(RunLength::new()), Box::new(BitMap::new()), Box::new(ForPack::new()), Box::new(DeltaCol::new()), Box::new(XorCol::new()), Box::new(AlpCol::new())];
+        let mut strs: Vec<Box<dyn ColumnInterface<String>>> = vec![Box::new(Uncompressed::new()), Box::new(RunLength::new()), Box::new(BitMap::new()), Box::new(DictCol::new())];
+        for i in 0..100 {
+            for col in nums.iter_mut() {
+                col.insert(if i % 7 == 0 { None } else { Some((i / 10) as f64) })?
+            }
+            for col in strs.iter_mut() {
+                col.insert(if i % 7 == 0 { None } else { Some((i % 4).to_string()) })?
+            }
+        }
+        let expected: BitVec = (0..100).map(|i| i % 7 != 0 && (i / 10 == 2 || i / 10 == 5)).collect();
+        for col in &nums {
+            assert!(col.select_in(&[2.0, 5.0, 11.0]) == expected);
+            assert!(col.select_in(&[]).not_any());
+        }
+        let expected: BitVec = (0..100).map(|i| i % 7 != 0 && i % 4 == 3).collect();
+        for col in &strs {
+            assert!(col.select_in(&["3".to_string(), "x".to_string()]) == expected);
+        }
+        let mut bools = BoolCol::new();
+        for i in 0..10 {
+            bools.insert(if i == 0 { None } else { Some(i % 2 == 0) })?
+        }
+        assert_eq!(bools.select_in(&[true]).count_ones(), 4);
+        Ok(())
+    }
+    #[test]
+    fn segmented() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        use crate::sqlscript::types::types::CompressType;
+        // Runs of a single value, then evenly spaced values
+        let mut col: Segmented<f64> = Segmented::new();
+        col.recompress(CompressType::RunLength)?;
+        for i in 0..(SEGMENT_ROWS * 2 + 10) {
+            col.insert(Some(if i < SEGMENT_ROWS { 1.0 } else { (i * 3) as f64 }))?
+        }
+        assert!(col.segment_strats() == vec![CompressType::RunLength; 3]);
+        assert_eq!(col.len(), SEGMENT_ROWS * 2 + 10);
+        // Random access crosses segments
+        assert_eq!(col.get(SEGMENT_ROWS * 2)?, Some((SEGMENT_ROWS * 6) as f64));
+        let vals = col.slice(SEGMENT_ROWS - 1, SEGMENT_ROWS + 1)?;
+        assert!(vals == vec![Some(1.0), Some((SEGMENT_ROWS * 3) as f64)]);
+        assert!(col.slice(0, SEGMENT_ROWS * 3).is_err());
+        // A segment can be encoded on its own
+        col.recompress_segment(1, CompressType::Delta)?;
+        assert!(col.segment_strats() == vec![CompressType::RunLength, CompressType::Delta, CompressType::RunLength]);
+        assert!(col.recompress(CompressType::Dict).is_err());
+        assert!(col.segment_strats() == vec![CompressType::RunLength, CompressType::Delta, CompressType::RunLength]);
+        // Segments keep their encodings when loading
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let mut loaded = Segmented::<f64>::read(&mut Reader::new(&out))?;
+        assert!(loaded.segment_strats() == col.segment_strats());
+        assert!(loaded.uncompress() == col.uncompress());
+        assert!(zone_bounds(loaded.zones(), 0, &1.0, &1.0));
+        assert_eq!(loaded.select_in(&[1.0, 30.0]).count_ones(), SEGMENT_ROWS);
+        // New segments use the column's strategy
+        for _ in 0..SEGMENT_ROWS {
+            loaded.insert(None)?
+        }
+        assert!(loaded.segment_strats()[3] == CompressType::RunLength);
+        Ok(())
+    }
+    #[test]
+    fn nullable() -> Result<(), String> {
+        use bitvec::prelude::*;
+        use super::super::persist::persist::{ Persist, Reader };
+        use crate::sqlscript::types::types::CompressType;
+        // Nulls are kept out of every encoding
+        let expected: Vec<Option<f64>> = (0..300).map(|i| if i % 3 == 0 || (100..150).contains(&i) { None } else { Some((i / 20) as f64) }).collect();
+        for strategy in [CompressType::Uncompressed, CompressType::RunLength, CompressType::BitMap, CompressType::ForPack, CompressType::Delta, CompressType::Xor, CompressType::Alp] {
+            let mut col = Nullable::new(f64::encoding(strategy)?);
+            for x in &expected {
+                col.insert(*x)?
+            }
+            assert_eq!(col.len(), 300);
+            assert_eq!(col.null_count(), expected.iter().filter(|x| x.is_none()).count());
+            assert!(col.nulls() == expected.iter().map(|x| x.is_none()).collect::<BitVec>());
+            assert!(col.iter().collect::<Vec<Option<f64>>>() == expected);
+            assert_eq!(col.get(99)?, None);
+            assert_eq!(col.get(160)?, Some(8.0));
+            assert!(col.slice(95, 155)? == expected[95..155].to_vec());
+            assert!(col.select_in(&[8.0]) == expected.iter().map(|x| *x == Some(8.0)).collect::<BitVec>());
+            // Round trip
+            let mut out = Vec::new();
+            col.save(&mut out)?;
+            let loaded = Nullable::<f64>::load(strategy, &mut Reader::new(&out))?;
+            assert!(loaded.iter().collect::<Vec<Option<f64>>>() == expected);
+        }
+        // No validity bitmap until the first null
+        let mut col = Nullable::new(f64::encoding(CompressType::RunLength)?);
+        for _ in 0..100 {
+            col.insert(Some(1.0))?
+        }
+        let size = col.size_in_bytes();
+        col.insert(None)?;
+        assert!(col.size_in_bytes() > size);
+        assert_eq!(col.get(0)?, Some(1.0));
+        assert_eq!(col.get(100)?, None);
+        // Segments each track their own nulls
+        let mut segs: Segmented<String> = Segmented::new();
+        for i in 0..(SEGMENT_ROWS + 10) {
+            segs.insert(if i % 1000 == 0 { None } else { Some("a".to_string()) })?
+        }
+        assert_eq!(segs.null_count(), 5);
+        assert!(segs.nulls().iter_ones().collect::<Vec<usize>>() == vec![0, 1000, 2000, 3000, 4000]);
+        let mut out = Vec::new();
+        segs.write(&mut out)?;
+        assert!(Segmented::<String>::read(&mut Reader::new(&out))?.nulls() == segs.nulls());
+        Ok(())
+    }
+    #[test]
+    fn update_in_place() -> Result<(), String> {
+        use crate::sqlscript::types::types::CompressType;
+        // Runs are split around a new value, and joined again when it goes back
+        let mut runs: RunLength<f64> = RunLength::new();
+        for x in [1.0, 1.0, 1.0, 2.0, 2.0] {
+            runs.insert(Some(x))?
+        }
+        let size = runs.size_in_bytes();
+        assert!(runs.set(1, Some(5.0))?);
+        assert!(runs.uncompress() == vec![Some(1.0), Some(5.0), Some(1.0), Some(2.0), Some(2.0)]);
+        assert!(runs.set(2, Some(2.0))? && runs.set(0, None)?);
+        assert!(runs.uncompress() == vec![None, Some(5.0), Some(2.0), Some(2.0), Some(2.0)]);
+        assert!(runs.set(0, Some(5.0))? && runs.set(1, Some(2.0))? && runs.set(0, Some(2.0))?);
+        assert!(runs.size_in_bytes() < size);
+        assert_eq!(runs.get(4)?, Some(2.0));
+        assert!(runs.set(5, None).is_err());
+        // Rows move between the bitmaps of their values, and values without rows are dropped
+        let mut bitmap: BitMap<String> = BitMap::new();
+        for i in 0..10 {
+            bitmap.insert(Some(if i == 4 { "b".to_string() } else { "a".to_string() }))?
+        }
+        assert!(bitmap.set(4, Some("c".to_string()))? && bitmap.set(5, None)?);
+        assert!(bitmap.select_in(&["b".to_string()]).not_any());
+        assert_eq!(bitmap.get(4)?, Some("c".to_string()));
+        assert_eq!(bitmap.get(5)?, None);
+        assert_eq!(bitmap.select_in(&["a".to_string()]).count_ones(), 8);
+        // Only segments holding an updated row change, each keeping its strategy
+        let mut col: Segmented<f64> = Segmented::new();
+        col.recompress(CompressType::RunLength)?;
+        for i in 0..(SEGMENT_ROWS * 2 + 10) {
+            col.insert(Some((i / 100) as f64))?
+        }
+        col.recompress_segment(0, CompressType::Delta)?;
+        let mut expected = col.uncompress();
+        let updates = vec![(SEGMENT_ROWS + 5, Some(-1.0)), (5, None), (6, Some(500.0)), (SEGMENT_ROWS * 2 + 1, Some(3.0))];
+        col.update(&updates)?;
+        for (idx, x) in &updates {
+            expected[*idx] = *x
+        }
+        assert!(col.uncompress() == expected);
+        assert!(col.segment_strats() == vec![CompressType::Delta, CompressType::RunLength, CompressType::RunLength]);
+        assert_eq!(col.check()?, SEGMENT_ROWS * 2 + 10);
+        // Zones of the updated segments follow the new values
+        assert!(col.zones().may_match(0, BopType::GtBop, &1000.0, true));
+        assert!(col.zones().may_match(0, BopType::GtBop, &400.0, false));
+        assert!(col.zones().may_match(SEGMENT_ROWS / ZONE_ROWS, BopType::LteBop, &-1.0, false) && !col.zones().may_match(SEGMENT_ROWS / ZONE_ROWS, BopType::LtBop, &-1.0, false));
+        assert!(col.update(&[(SEGMENT_ROWS * 3, None)]).is_err());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_roaring {
+    use super::super::roaring::roaring::Roaring;
+    use super::super::persist::persist::{ Persist, Reader };
+
+    #[test]
+    fn roaring_1() -> Result<(), String> {
+        // Long runs, a dense stretch and scattered indexes, across several containers
+        let mut expected: Vec<u32> = (0..70000).collect();
+        expected.extend((70000..140000).filter(|i| i % 3 != 0));
+        expected.extend((140000..1000000).step_by(997));
+        let mut set = Roaring::new();
+        for i in &expected {
+            set.push(*i)
+        }
+        assert_eq!(set.len(), expected.len());
+        assert!(set.iter().collect::<Vec<u32>>() == expected);
+        assert!(set.contains(69999) && set.contains(70001) && !set.contains(70002) && set.contains(140000 + 997) && !set.contains(140001));
+        assert!(set.iter_range(69998, 70005).collect::<Vec<u32>>() == vec![69998, 69999, 70000, 70001, 70003, 70004]);
+        assert_eq!(set.iter_range(140001, 140000 + 997 * 3).count(), 2);
+        // Runs take up very little space
+        let mut runs = Roaring::new();
+        for i in 0..100000 {
+            runs.push(i)
+        }
+        assert!(runs.size_in_bytes() < 100);
+        // Round trip
+        let mut out = Vec::new();
+        set.write(&mut out)?;
+        let loaded = Roaring::read(&mut Reader::new(&out))?;
+        assert_eq!(loaded.len(), expected.len());
+        assert!(loaded.iter().collect::<Vec<u32>>() == expected);
+        // Indexes can be set and cleared anywhere, splitting and joining runs
+        for i in [50, 70002, 70000, 140001, 2000000] {
+            set.insert(i)
+        }
+        for i in [10, 11, 12, 70001, 140000] {
+            set.remove(i)
+        }
+        set.remove(12);
+        set.insert(11);
+        let mut changed: Vec<u32> = expected.iter().copied().filter(|i| ![10, 12, 70001, 140000].contains(i)).collect();
+        changed.extend([70002, 140001, 2000000]);
+        changed.sort();
+        assert_eq!(set.len(), changed.len());
+        assert!(set.iter().collect::<Vec<u32>>() == changed);
+        assert!(set.contains(11) && !set.contains(12) && set.contains(2000000));
+        // Clearing every index of a container drops it
+        let mut single = Roaring::new();
+        single.insert(5);
+        single.remove(5);
+        assert_eq!(single.len(), 0);
+        assert_eq!(single.size_in_bytes(), 0);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod table_tests {
     use super::super::table::*;
     use crate::sqlscript::types::types::{Val, ColType, CompressType};
-    use super::super::column::generic::Column;
+    use super::super::column::generic::{Column, ReadColumn};
     #[test]
     fn test_bool_column() -> Result<(), String> {
         // Setup
@@ -474,6 +1135,13 @@ mod table_tests {
             },
             _ => assert!(false)
         }
+        // A row with an item of the wrong type adds nothing to any column
+        assert!(test_table.add_row(vec![Val::NumVal(3.0), Val::NumVal(5.0)]).is_err());
+        assert_eq!(test_table.len(), 1);
+        match (test_table.get_column(&col_name1)?, test_table.get_column(&col_name2)?) {
+            (Column::Number(x), Column::String(y)) => assert!(x.len() == 1 && y.len() == 1),
+            _ => assert!(false)
+        }
         Ok(())
     }
     #[test]
@@ -560,4 +1228,474 @@ mod table_tests {
         assert_eq!(i, 2);
         Ok(())
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_get_row() -> Result<(), String> {
+        // Setup
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Xor).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::String, CompressType::RunLength).unwrap();
+        test_table.add_column(&"Test3".to_string(), ColType::Boolean, CompressType::Uncompressed).unwrap();
+        for i in 0..100 {
+            test_table.add_row(vec![Val::NumVal(i as f64), Val::StrVal((i / 10).to_string()), if i % 2 == 0 { Val::NullVal } else { Val::BoolVal(true) }]).unwrap();
+        }
+        // Look up rows
+        match test_table.get_row(71)?.as_slice() {
+            [Val::NumVal(x), Val::StrVal(s), Val::BoolVal(true)] => {
+                assert_eq!(*x, 71.0);
+                assert_eq!(s, "7")
+            },
+            _ => assert!(false)
+        }
+        match test_table.get_row(0)?.as_slice() {
+            [Val::NumVal(x), Val::StrVal(_), Val::NullVal] => assert_eq!(*x, 0.0),
+            _ => assert!(false)
+        }
+        assert!(test_table.get_row(100).is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_delete_rows() -> Result<(), String> {
+        // Setup
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Delta).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::String, CompressType::Dict).unwrap();
+        for i in 0..10 {
+            test_table.add_row(vec![Val::NumVal(i as f64), Val::StrVal((i % 3).to_string())]).unwrap();
+        }
+        // Tombstoned rows are skipped
+        test_table.delete_rows(&vec![0, 4, 4])?;
+        assert_eq!(test_table.len(), 8);
+        let rows: Vec<(usize, Vec<Val>)> = test_table.iter_indexed().collect();
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[3].0, 5);
+        match &rows[3].1[0] {
+            Val::NumVal(x) => assert_eq!(*x, 5.0),
+            _ => assert!(false)
+        }
+        // Deleting more than half compacts
+        test_table.delete_rows(&vec![1, 2, 3, 5])?;
+        assert_eq!(test_table.len(), 4);
+        let rows: Vec<(usize, Vec<Val>)> = test_table.iter_indexed().collect();
+        assert_eq!(rows[1].0, 1);
+        match (&rows[0].1[0], &rows[1].1[1]) {
+            (Val::NumVal(x), Val::StrVal(s)) => {
+                assert_eq!(*x, 6.0);
+                assert_eq!(s, "1")
+            },
+            _ => assert!(false)
+        }
+        assert!(test_table.delete_rows(&vec![4]).is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_update_rows() -> Result<(), String> {
+        // Setup
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::RunLength).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::String, CompressType::BitMap).unwrap();
+        for i in 0..6 {
+            test_table.add_row(vec![Val::NumVal(1.0), Val::StrVal((i % 2).to_string())]).unwrap();
+        }
+        test_table.delete_rows(&vec![0])?;
+        // Split a run and move a value between bitmaps
+        test_table.update_rows(&vec![(3, 0, Val::NumVal(2.0)), (3, 1, Val::StrVal("0".to_string())), (4, 1, Val::NullVal)])?;
+        let rows: Vec<Vec<Val>> = test_table.iter().collect();
+        assert_eq!(rows.len(), 5);
+        for (i, row) in rows.iter().enumerate() {
+            match (&row[0], &row[1]) {
+                (Val::NumVal(x), Val::StrVal(s)) => {
+                    assert_eq!(*x, if i == 2 { 2.0 } else { 1.0 });
+                    assert_eq!(*s, if i == 2 { "0".to_string() } else { ((i + 1) % 2).to_string() })
+                },
+                (Val::NumVal(x), Val::NullVal) => {
+                    assert_eq!(*x, 1.0);
+                    assert_eq!(i, 3)
+                },
+                _ => assert!(false)
+            }
+        }
+        // Wrong types and deleted rows are rejected without changing anything
+        assert!(test_table.update_rows(&vec![(1, 0, Val::NumVal(5.0)), (2, 0, Val::BoolVal(true))]).is_err());
+        assert!(test_table.update_rows(&vec![(0, 0, Val::NumVal(5.0))]).is_err());
+        match test_table.get_value(0, 1)? {
+            Val::NumVal(x) => assert_eq!(x, 1.0),
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_auto_compress() -> Result<(), String> {
+        // Setup
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Auto).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::String, CompressType::Uncompressed).unwrap();
+        test_table.add_column(&"Test3".to_string(), ColType::Number, CompressType::Uncompressed).unwrap();
+        test_table.add_column(&"Test4".to_string(), ColType::Boolean, CompressType::Auto).unwrap();
+        // Auto columns stay uncompressed until there are enough rows to sample
+        for i in 0..1023 {
+            test_table.add_row(vec![Val::NumVal(((i / 100) % 5) as f64 + 0.5), Val::StrVal((i % 3).to_string()), Val::NumVal((1000 + i * 10) as f64), Val::BoolVal(i % 2 == 0)]).unwrap();
+        }
+        assert!(test_table.get_compression_strats()[0] == CompressType::Auto);
+        test_table.add_row(vec![Val::NumVal(0.5), Val::StrVal("0".to_string()), Val::NumVal(11230.0), Val::BoolVal(true)]).unwrap();
+        assert!(test_table.get_compression_strats()[0] == CompressType::RunLength);
+        assert!(test_table.get_compression_strats()[3] == CompressType::Uncompressed);
+        // Estimates cover exactly the strategies each type implements
+        let sizes = test_table.estimate_sizes(1)?;
+        let strategies: Vec<CompressType> = sizes.iter().map(|s| s.0).collect();
+        assert!(strategies == vec![CompressType::Uncompressed, CompressType::RunLength, CompressType::BitMap, CompressType::Dict]);
+        assert_eq!(test_table.estimate_sizes(3)?.len(), 3);
+        // Evenly spaced values are smallest as deltas
+        let (sizes, chosen) = test_table.auto_compress(2)?;
+        assert!(chosen == CompressType::Delta);
+        assert_eq!(sizes.len(), 7);
+        for size in sizes {
+            if size.0 != CompressType::Delta { assert!(size.1 > 0) }
+        }
+        // Values are unchanged
+        match (test_table.get_value(0, 1023)?, test_table.get_value(2, 500)?) {
+            (Val::NumVal(x), Val::NumVal(y)) => {
+                assert_eq!(x, 0.5);
+                assert_eq!(y, 6000.0)
+            },
+            _ => assert!(false)
+        }
+        // Columns still waiting on auto are saved that way
+        use super::super::persist::persist::{ Persist, Reader };
+        let mut pending_table = table::Table::new();
+        pending_table.add_column(&"Test1".to_string(), ColType::String, CompressType::Auto).unwrap();
+        pending_table.add_row(vec![Val::StrVal("a".to_string())]).unwrap();
+        let mut out = Vec::new();
+        pending_table.write(&mut out)?;
+        let loaded = table::Table::read(&mut Reader::new(&out))?;
+        assert!(loaded.get_compression_strats()[0] == CompressType::Auto);
+        assert_eq!(loaded.len(), 1);
+        Ok(())
+    }
+    #[test]
+    fn test_zones() -> Result<(), String> {
+        use crate::sqlscript::types::types::BopType;
+        use super::super::column::generic::ZONE_ROWS;
+        // Setup
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Delta).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::String, CompressType::Dict).unwrap();
+        for i in 0..(ZONE_ROWS * 3) {
+            test_table.add_row(vec![Val::NumVal(i as f64), Val::StrVal((i / ZONE_ROWS).to_string())]).unwrap();
+        }
+        // Only the middle zone can hold these values
+        let keep = test_table.zones_matching(&vec![(0, BopType::GteBop, Val::NumVal(ZONE_ROWS as f64), false), (1, BopType::StrEqBop, Val::StrVal("1".to_string()), false)]);
+        assert!(keep == vec![false, true, false]);
+        // Comparisons between different types can't prune
+        assert!(test_table.zones_matching(&vec![(0, BopType::EqBop, Val::StrVal("1".to_string()), false)]) == vec![true; 3]);
+        // Deleted rows are skipped
+        test_table.delete_rows(&vec![ZONE_ROWS, ZONE_ROWS + 1])?;
+        let rows: Vec<(usize, Vec<Val>)> = test_table.iter_zones(keep).collect::<Result<_, _>>()?;
+        assert_eq!(rows.len(), ZONE_ROWS - 2);
+        assert_eq!(rows[0].0, ZONE_ROWS + 2);
+        match (&rows[0].1[0], &rows[0].1[1]) {
+            (Val::NumVal(x), Val::StrVal(s)) => {
+                assert_eq!(*x, (ZONE_ROWS + 2) as f64);
+                assert_eq!(s, "1")
+            },
+            _ => assert!(false)
+        }
+        // Rows past the end of the table are an error or left out, never a panic
+        assert!(test_table.iter_zones(vec![true; 5]).any(|row| row.is_err()));
+        let selected = test_table.iter_selected(bitvec::bitvec![1; ZONE_ROWS * 3 + 10]).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(selected.len(), ZONE_ROWS * 3 - 2);
+        Ok(())
+    }
+    #[test]
+    fn test_segments() -> Result<(), String> {
+        use super::super::column::generic::SEGMENT_ROWS;
+        // Setup
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Auto).unwrap();
+        for i in 0..(SEGMENT_ROWS * 2 + 5) {
+            test_table.add_row(vec![Val::NumVal(if i < SEGMENT_ROWS { (i / 1000) as f64 } else { (i * 7) as f64 })]).unwrap();
+        }
+        // Auto picks for the whole column, but each full segment gets whichever strategy suits its own values
+        let (_, chosen) = test_table.auto_compress(0)?;
+        assert!(chosen == CompressType::Delta);
+        assert!(test_table.get_compression_strats()[0] == CompressType::Delta);
+        let strats = test_table.get_column(&"Test1".to_string())?.segment_strats();
+        assert!(strats == vec![CompressType::RunLength, CompressType::Delta, CompressType::Delta]);
+        // New segments use the reported choice
+        for i in 0..SEGMENT_ROWS {
+            test_table.add_row(vec![Val::NumVal(i as f64)])?
+        }
+        assert!(test_table.get_column(&"Test1".to_string())?.segment_strats()[3] == CompressType::Delta);
+        // Values are unchanged
+        match (test_table.get_value(0, 2500)?, test_table.get_value(0, SEGMENT_ROWS + 1)?) {
+            (Val::NumVal(x), Val::NumVal(y)) => {
+                assert_eq!(x, 2.0);
+                assert_eq!(y, ((SEGMENT_ROWS + 1) * 7) as f64)
+            },
+            _ => assert!(false)
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_cluster() -> Result<(), String> {
+        use super::super::column::generic::SEGMENT_ROWS;
+        use crate::sqlscript::types::types::BopType;
+        use super::super::persist::persist::{ Persist, Reader };
+        // Setup, keys arrive scattered
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Key".to_string(), ColType::Number, CompressType::RunLength).unwrap();
+        test_table.add_column(&"Seq".to_string(), ColType::Number, CompressType::Uncompressed).unwrap();
+        test_table.set_cluster_key(0)?;
+        let key = |i: usize| if i % 50 == 0 { Val::NullVal } else { Val::NumVal(((i * 7) % 10) as f64) };
+        for i in 0..(SEGMENT_ROWS * 2 + 10) {
+            test_table.add_row(vec![key(i), Val::NumVal(i as f64)]).unwrap();
+        }
+        // Full segments are sorted on the key (nulls first, ties in insertion order), the open segment isn't yet
+        let rows: Vec<Vec<Val>> = test_table.iter().collect();
+        for seg in 0..2 {
+            for i in (seg * SEGMENT_ROWS + 1)..((seg + 1) * SEGMENT_ROWS) {
+                match (&rows[i - 1][0], &rows[i][0], &rows[i - 1][1], &rows[i][1]) {
+                    (Val::NullVal, Val::NullVal, Val::NumVal(s1), Val::NumVal(s2)) => assert!(s1 < s2),
+                    (Val::NullVal, Val::NumVal(_), _, _) => assert!(true),
+                    (Val::NumVal(k1), Val::NumVal(k2), Val::NumVal(s1), Val::NumVal(s2)) => assert!(k1 < k2 || (k1 == k2 && s1 < s2)),
+                    _ => assert!(false)
+                }
+            }
+        }
+        match (&rows[SEGMENT_ROWS * 2][1], &rows[SEGMENT_ROWS * 2 + 9][1]) {
+            (Val::NumVal(x), Val::NumVal(y)) => assert_eq!((*x, *y), ((SEGMENT_ROWS * 2) as f64, (SEGMENT_ROWS * 2 + 9) as f64)),
+            _ => assert!(false)
+        }
+        // Sorted segments take a run per key, and zones are rebuilt
+        assert!(test_table.get_column(&"Key".to_string())?.size_in_bytes() < 4000);
+        match test_table.get_column(&"Key".to_string())? {
+            Column::Number(cb) => {
+                assert!(cb.zones().may_match(0, BopType::LtBop, &-1.0, true));
+                assert!(cb.zones().may_match(3, BopType::LteBop, &7.0, false) && !cb.zones().may_match(3, BopType::LtBop, &7.0, false));
+                assert_eq!(cb.select_in(&[3.0]).count_ones(), rows.iter().filter(|r| matches!(r[0], Val::NumVal(3.0))).count())
+            },
+            _ => assert!(false)
+        }
+        // Rows still come back in insertion order when asked for
+        for (i, row) in test_table.iter_inserted().enumerate() {
+            match &row[1] {
+                Val::NumVal(x) => assert_eq!(*x, i as f64),
+                _ => assert!(false)
+            }
+        }
+        // Cluster key and insertion order survive writing and reading back
+        let mut out = Vec::new();
+        test_table.write(&mut out)?;
+        let mut loaded = table::Table::read(&mut Reader::new(&out))?;
+        assert!(loaded.iter_inserted().zip(test_table.iter_inserted()).all(|(a, b)| matches!((&a[1], &b[1]), (Val::NumVal(x), Val::NumVal(y)) if x == y)));
+        // Compacting sorts the whole table again
+        let remove: Vec<usize> = loaded.iter_indexed().filter(|(_, row)| matches!(row[1], Val::NumVal(x) if x as usize % 3 != 0)).map(|(i, _)| i).collect();
+        loaded.delete_rows(&remove)?;
+        assert_eq!(loaded.len(), (SEGMENT_ROWS * 2 + 10).div_ceil(3));
+        let keys: Vec<Val> = loaded.iter().map(|row| row[0].clone()).collect();
+        let expected: usize = keys.iter().filter(|k| matches!(k, Val::NullVal)).count();
+        assert!(keys[..expected].iter().all(|k| matches!(k, Val::NullVal)));
+        assert!(keys[expected..].windows(2).all(|w| matches!((&w[0], &w[1]), (Val::NumVal(x), Val::NumVal(y)) if x <= y)));
+        for (i, row) in loaded.iter_inserted().enumerate() {
+            match &row[1] {
+                Val::NumVal(x) => assert_eq!(*x, (i * 3) as f64),
+                _ => assert!(false)
+            }
+        }
+        // Updating the key of a row moves it to where it now belongs, across segments
+        let first = loaded.iter_indexed().next().unwrap().0;
+        loaded.update_rows(&vec![(first, 0, Val::NumVal(100.0))])?;
+        let keys: Vec<Val> = loaded.iter().map(|row| row[0].clone()).collect();
+        assert!(keys[expected - 1..].windows(2).all(|w| matches!((&w[0], &w[1]), (Val::NumVal(x), Val::NumVal(y)) if x <= y)));
+        assert!(matches!(keys[keys.len() - 1], Val::NumVal(100.0)));
+        assert_eq!(loaded.iter_inserted().count(), loaded.len());
+        Ok(())
+    }
+    #[test]
+    fn test_persist() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        // Setup
+        let mut test_table = table::Table::new();
+        // Create new columns using every compression strategy
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Xor).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::Number, CompressType::RunLength).unwrap();
+        test_table.add_column(&"Test3".to_string(), ColType::String, CompressType::BitMap).unwrap();
+        test_table.add_column(&"Test4".to_string(), ColType::Boolean, CompressType::Uncompressed).unwrap();
+        // Add rows to table
+        test_table.add_row(vec![Val::NumVal(112.2), Val::NumVal(1.0), Val::StrVal("Hello".to_string()), Val::BoolVal(true)]).unwrap();
+        test_table.add_row(vec![Val::NullVal, Val::NumVal(1.0), Val::NullVal, Val::NullVal]).unwrap();
+        test_table.add_row(vec![Val::NumVal(-0.5), Val::NumVal(2.0), Val::StrVal("Hello".to_string()), Val::BoolVal(false)]).unwrap();
+        // Write and read back
+        let mut out = Vec::new();
+        test_table.write(&mut out)?;
+        let loaded = table::Table::read(&mut Reader::new(&out))?;
+        // Check values
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get_headers(), test_table.get_headers());
+        let rows: Vec<Vec<Val>> = loaded.iter().collect();
+        match (&rows[0][0], &rows[0][2], &rows[0][3]) {
+            (Val::NumVal(112.2), Val::StrVal(s), Val::BoolVal(true)) => assert_eq!(s, "Hello"),
+            _ => assert!(false)
+        }
+        match (&rows[1][0], &rows[1][1], &rows[1][2], &rows[1][3]) {
+            (Val::NullVal, Val::NumVal(1.0), Val::NullVal, Val::NullVal) => assert!(true),
+            _ => assert!(false)
+        }
+        match (&rows[2][0], &rows[2][1], &rows[2][3]) {
+            (Val::NumVal(-0.5), Val::NumVal(2.0), Val::BoolVal(false)) => assert!(true),
+            _ => assert!(false)
+        }
+        // Truncated files are rejected
+        assert!(table::Table::read(&mut Reader::new(&out[..out.len() - 1])).is_err());
+        // Subqueries can't be written, only their values
+        use crate::sqlscript::types::types::{ Expr, Query, BopType };
+        use std::rc::Rc;
+        let expr = Expr::BopExpr(Rc::new(Expr::IdentExpr("Test1".to_string())), BopType::PlusBop, Rc::new(Expr::QueryExpr(Rc::new(Query::Exit))));
+        assert!(expr.write(&mut Vec::new()).is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_checksums() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        use super::super::column::generic::Segmented;
+        // Setup, with a full segment and a partly full one
+        let mut col: Segmented<f64> = Segmented::new();
+        for i in 0..5000 {
+            col.insert(if i % 10 == 0 { None } else { Some(i as f64) })?;
+        }
+        assert_eq!(col.check()?, 5000);
+        // Checksums don't depend on the encoding
+        col.recompress(CompressType::Xor)?;
+        assert_eq!(col.check()?, 5000);
+        // Read back, still matching
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let loaded = Segmented::<f64>::read(&mut Reader::new(&out))?;
+        assert_eq!(loaded.check()?, 5000);
+        // Written without compression, a flipped bit in the last value (just before the last segment's single zone) is caught
+        col.recompress(CompressType::Uncompressed)?;
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let last = out.len() - 1 - (8 + 9 + 9 + 8);
+        out[last] ^= 1;
+        let corrupted = Segmented::<f64>::read(&mut Reader::new(&out))?;
+        assert!(corrupted.check().is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_mapped() -> Result<(), String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        use super::super::mapped::mapped::MappedFile;
+        use std::rc::Rc;
+        // Setup, with many segments read in place from the file
+        let mut test_table = table::Table::new();
+        test_table.add_column(&"Test1".to_string(), ColType::Number, CompressType::Delta).unwrap();
+        test_table.add_column(&"Test2".to_string(), ColType::String, CompressType::Dict).unwrap();
+        for i in 0..80000 {
+            let name = if i % 5 == 0 { Val::NullVal } else { Val::StrVal((i % 3).to_string()) };
+            test_table.add_row(vec![Val::NumVal(i as f64), name]).unwrap();
+        }
+        let mut out = Vec::new();
+        test_table.write(&mut out)?;
+        let path = std::env::temp_dir().join("alexdb_test_mapped.db").to_str().unwrap().to_string();
+        std::fs::write(&path, &out).unwrap();
+        // Read the table from a map of the file
+        let file = Rc::new(MappedFile::open(&path)?);
+        let loaded = table::Table::read(&mut Reader::mapped(&file))?;
+        assert_eq!(loaded.len(), 80000);
+        for (a, b) in loaded.iter().zip(test_table.iter()) {
+            match (&a[0], &b[0], &a[1], &b[1]) {
+                (Val::NumVal(x), Val::NumVal(y), Val::StrVal(s), Val::StrVal(t)) => assert!(x == y && s == t),
+                (Val::NumVal(x), Val::NumVal(y), Val::NullVal, Val::NullVal) => assert!(x == y),
+                _ => assert!(false)
+            }
+        }
+        // Random access jumps between segments
+        for i in [79999, 3, 40000, 4096, 12345, 79998] {
+            match (loaded.get_value(0, i)?, loaded.get_value(1, i)?) {
+                (Val::NumVal(x), Val::NullVal) => assert!(x == i as f64 && i % 5 == 0),
+                (Val::NumVal(x), Val::StrVal(s)) => assert!(x == i as f64 && s == (i % 3).to_string()),
+                _ => assert!(false)
+            }
+        }
+        // Writing a mapped table copies its encoded bytes
+        let mut rewritten = Vec::new();
+        loaded.write(&mut rewritten)?;
+        assert!(rewritten == out);
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+    // Write a column, map the file and read it back
+    fn map_column<T: super::super::column::generic::Encodable>(col: &super::super::column::generic::Segmented<T>, name: &str) -> Result<super::super::column::generic::Segmented<T>, String> {
+        use super::super::persist::persist::{ Persist, Reader };
+        use super::super::mapped::mapped::MappedFile;
+        use std::rc::Rc;
+        let mut out = Vec::new();
+        col.write(&mut out)?;
+        let path = std::env::temp_dir().join(name).to_str().unwrap().to_string();
+        std::fs::write(&path, &out).unwrap();
+        let file = Rc::new(MappedFile::open(&path)?);
+        let loaded = super::super::column::generic::Segmented::read(&mut Reader::mapped(&file))?;
+        let _ = std::fs::remove_file(&path);
+        Ok(loaded)
+    }
+    #[test]
+    fn test_mapped_encodings() -> Result<(), String> {
+        use super::super::column::generic::Segmented;
+        use super::super::persist::persist::{ Persist, Reader };
+        use super::super::mapped::mapped::MappedFile;
+        use std::rc::Rc;
+        let rows = 4096 * 2 + 100;
+        let strats = [CompressType::Uncompressed, CompressType::RunLength, CompressType::BitMap, CompressType::ForPack, CompressType::Delta, CompressType::Xor, CompressType::Alp];
+        for (n, strat) in strats.iter().enumerate() {
+            let mut col: Segmented<f64> = Segmented::new();
+            col.recompress(*strat)?;
+            for i in 0..rows {
+                col.insert(if i % 7 == 0 { None } else { Some((i / 50) as f64) })?
+            }
+            let mut mapped = map_column(&col, &format!("alexdb_test_mapped_num{}.db", n))?;
+            mapped.check()?;
+            // Every read goes straight to the mapped bytes
+            assert!(mapped.iter().eq(col.iter()));
+            for i in [0, 1, 4095, 4096, 5000, rows - 1] {
+                assert!(mapped.get(i)? == col.get(i)?)
+            }
+            assert!(mapped.slice(4000, 4200)? == col.slice(4000, 4200)?);
+            assert_eq!(mapped.null_count(), col.null_count());
+            assert!(mapped.nulls() == col.nulls());
+            // Inserting decodes only the last segment
+            mapped.insert(Some(-1.0))?;
+            col.insert(Some(-1.0))?;
+            assert!(mapped.iter().eq(col.iter()));
+        }
+        let strats = [CompressType::Uncompressed, CompressType::RunLength, CompressType::BitMap, CompressType::Dict];
+        for (n, strat) in strats.iter().enumerate() {
+            let mut col: Segmented<String> = Segmented::new();
+            col.recompress(*strat)?;
+            for i in 0..rows {
+                col.insert(if i % 7 == 0 { None } else { Some((i % 4).to_string()) })?
+            }
+            let mapped = map_column(&col, &format!("alexdb_test_mapped_str{}.db", n))?;
+            mapped.check()?;
+            assert!(mapped.iter().eq(col.iter()));
+            assert!(mapped.get(4097)? == col.get(4097)?);
+            assert!(mapped.slice(10, 5000)? == col.slice(10, 5000)?);
+            assert!(mapped.select_in(&["1".to_string()]) == col.select_in(&["1".to_string()]));
+        }
+        let mut bools: Segmented<bool> = Segmented::new();
+        for i in 0..rows {
+            bools.insert(if i % 7 == 0 { None } else { Some(i % 3 == 0) })?
+        }
+        let mapped = map_column(&bools, "alexdb_test_mapped_bool.db")?;
+        assert!(mapped.iter().eq(bools.iter()));
+        assert!(mapped.slice(4090, 4100)? == bools.slice(4090, 4100)?);
+        // A truncated file is an error rather than a panic
+        let mut out = Vec::new();
+        bools.write(&mut out)?;
+        let path = std::env::temp_dir().join("alexdb_test_mapped_cut.db").to_str().unwrap().to_string();
+        std::fs::write(&path, &out[..out.len() / 2]).unwrap();
+        let file = Rc::new(MappedFile::open(&path)?);
+        assert!(Segmented::<bool>::read(&mut Reader::mapped(&file)).is_err());
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+}
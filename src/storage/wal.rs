@@ -0,0 +1,137 @@
+pub mod wal {
+    use std::fs::{ File, OpenOptions };
+    use std::io::{ Read, Write, Seek, SeekFrom };
+    use crate::sqlscript::types::types::Val;
+    use super::super::persist::persist::{ Persist, Reader, checksum };
+
+    macro_rules! handle{
+        ($e:expr) => {
+            (match $e { Ok(v) => v, Err(s) => return Err(s) })
+        }
+    }
+
+    // Every log starts with the magic bytes followed by its generation
+    const LOG_MAGIC: &[u8; 6] = b"ALXLOG";
+    const HEADER_LEN: u64 = 14;
+
+    pub enum LogRecord {
+        Query(String), // A mutating query, exactly as it was executed
+        Import(String, Vec<Vec<Val>>) // Rows read from a CSV file by an import, so replay doesn't depend on the file
+    }
+    impl Persist for LogRecord {
+        fn write(&self, out: &mut Vec<u8>) -> Result<(), String> {
+            match self {
+                LogRecord::Query(q) => {
+                    out.push(0);
+                    q.write(out)
+                },
+                LogRecord::Import(table_name, rows) => {
+                    out.push(1);
+                    handle!(table_name.write(out));
+                    rows.write(out)
+                }
+            }
+        }
+        fn read(r: &mut Reader) -> Result<Self, String> {
+            match handle!(u8::read(r)) {
+                0 => Ok(LogRecord::Query(handle!(String::read(r)))),
+                1 => {
+                    let table_name = handle!(String::read(r));
+                    Ok(LogRecord::Import(table_name, handle!(Vec::read(r))))
+                },
+                t => Err("Corrupt log file: unknown record tag ".to_string() + &t.to_string())
+            }
+        }
+    }
+
+    pub struct WriteAheadLog {
+        file: File,
+        generation: u64,
+        records: usize
+    }
+    impl WriteAheadLog {
+        // Open (or create) the log at path, returning it along with the records it holds.
+        // A partially written record at the end (from a crash mid-append) is discarded.
+        pub fn open(path: &String) -> Result<(WriteAheadLog, Vec<LogRecord>), String> {
+            let mut file = match OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path) {
+                Ok(f) => f,
+                Err(_) => return Err("Error opening log ".to_string() + path)
+            };
+            let mut bytes = Vec::new();
+            match file.read_to_end(&mut bytes) {
+                Ok(_) => (),
+                Err(_) => return Err("Error reading log ".to_string() + path)
+            };
+            // Brand new log, or a crash while the header was being rewritten (the records were already checkpointed)
+            if bytes.len() < HEADER_LEN as usize {
+                let mut wal = WriteAheadLog { file, generation: 0, records: 0 };
+                handle!(wal.reset(0));
+                return Ok((wal, Vec::new()))
+            }
+            // Check header
+            let mut r = Reader::new(&bytes);
+            if handle!(r.take(LOG_MAGIC.len())) != LOG_MAGIC { return Err(path.clone() + " is not an AlexDB log file") }
+            let generation = handle!(u64::read(&mut r));
+            // Read records until the end, or until a record is torn or fails its checksum
+            let mut records = Vec::new();
+            let mut good_len = HEADER_LEN as usize;
+            while let (Ok(len), Ok(sum)) = (u32::read(&mut r), u64::read(&mut r)) {
+                let len = len as usize;
+                let payload = match r.take(len) {
+                    Ok(p) => p,
+                    Err(_) => break
+                };
+                if checksum(payload) != sum { break }
+                match LogRecord::read(&mut Reader::new(payload)) {
+                    Ok(record) => records.push(record),
+                    Err(_) => break
+                };
+                good_len += 12 + len;
+            }
+            // Drop anything after the last good record
+            match file.set_len(good_len as u64).and_then(|_| file.seek(SeekFrom::End(0))) {
+                Ok(_) => (),
+                Err(_) => return Err("Error repairing log ".to_string() + path)
+            };
+            let num_records = records.len();
+            Ok((WriteAheadLog { file, generation, records: num_records }, records))
+        }
+        // Durably append a record; only returns once the record is on disk
+        pub fn append(&mut self, record: &LogRecord) -> Result<(), String> {
+            let mut payload = Vec::new();
+            handle!(record.write(&mut payload));
+            let mut out = Vec::new();
+            handle!((payload.len() as u32).write(&mut out));
+            handle!(checksum(&payload).write(&mut out));
+            out.extend_from_slice(&payload);
+            match self.file.write_all(&out).and_then(|_| self.file.sync_data()) {
+                Ok(_) => (),
+                Err(_) => return Err("Error writing to log".to_string())
+            };
+            self.records += 1;
+            Ok(())
+        }
+        // Throw away every record and start the given generation
+        pub fn reset(&mut self, generation: u64) -> Result<(), String> {
+            let mut out = Vec::new();
+            out.extend_from_slice(LOG_MAGIC);
+            handle!(generation.write(&mut out));
+            match self.file.set_len(0)
+                .and_then(|_| self.file.seek(SeekFrom::Start(0)))
+                .and_then(|_| self.file.write_all(&out))
+                .and_then(|_| self.file.sync_data()) {
+                Ok(_) => (),
+                Err(_) => return Err("Error truncating log".to_string())
+            };
+            self.generation = generation;
+            self.records = 0;
+            Ok(())
+        }
+        pub fn generation(&self) -> u64 {
+            self.generation
+        }
+        pub fn num_records(&self) -> usize {
+            self.records
+        }
+    }
+}